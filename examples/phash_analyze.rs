@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::sync::Mutex;
 use std::time::Instant;
 
 use clap::Parser;
@@ -24,6 +23,40 @@ impl Distance<u64> for Dist64BitHamming {
     }
 }
 
+/// 并查集，用数组存储父节点，find 时做路径压缩，union 时按秩合并
+struct UnionFind {
+    parent: Vec<u32>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        Self { parent: (0..n as u32).collect(), rank: vec![0; n] }
+    }
+
+    fn find(&mut self, x: u32) -> u32 {
+        if self.parent[x as usize] != x {
+            self.parent[x as usize] = self.find(self.parent[x as usize]);
+        }
+        self.parent[x as usize]
+    }
+
+    fn union(&mut self, a: u32, b: u32) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return;
+        }
+        match self.rank[ra as usize].cmp(&self.rank[rb as usize]) {
+            std::cmp::Ordering::Less => self.parent[ra as usize] = rb,
+            std::cmp::Ordering::Greater => self.parent[rb as usize] = ra,
+            std::cmp::Ordering::Equal => {
+                self.parent[rb as usize] = ra;
+                self.rank[ra as usize] += 1;
+            }
+        }
+    }
+}
+
 /// phash 的去重效果分析工具
 #[derive(Parser)]
 pub struct Args {
@@ -92,23 +125,55 @@ fn main() {
     info!("开始进行 phash 去重……");
     let now = Instant::now();
     let index = Hnsw::<u64, Dist64BitHamming>::new(15, hashes.len(), 16, 40, Dist64BitHamming);
-    let duplicates = Mutex::new(HashMap::new());
-    // NOTE: 此处由于使用了 par_iter，结果会存在一定随机性
+
+    // 先把所有 phash 一次性插入索引，查询时才能看到完整的近邻关系，不再依赖插入顺序
     hashes.par_iter().progress_with_style(pb_style()).enumerate().for_each(|(i, &hash)| {
-        let result = index.search(&[hash], 1, 16);
-        let result =
-            result.into_iter().filter(|n| n.distance * 64. <= args.threshold as f32).next();
-        if let Some(n) = result {
-            duplicates.lock().unwrap().entry(n.d_id).or_insert(vec![]).push(i as u64);
-        } else {
-            index.insert((&[hash], i));
-        }
+        index.insert((&[hash], i));
     });
+
+    // 扩大 knn（取最近的 16 个）并按 threshold 过滤出其中满足条件的全部近邻（而不是只取最近的一个），
+    // 把每一对近邻都喂给并查集合并，这样 A≈B≈C 这样的链式重复也会被传递闭包到同一组，
+    // 最终分组结果只取决于 threshold，和查询顺序无关
+    let pairs = hashes
+        .par_iter()
+        .progress_with_style(pb_style())
+        .enumerate()
+        .flat_map(|(i, &hash)| {
+            index
+                .search(&[hash], 16, 16)
+                .into_iter()
+                .filter(move |n| n.d_id != i && n.distance * 64. <= args.threshold as f32)
+                .map(move |n| (i.min(n.d_id) as u32, i.max(n.d_id) as u32))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut uf = UnionFind::new(hashes.len());
+    for (a, b) in pairs {
+        uf.union(a, b);
+    }
+
+    // 按 find() 得到的根节点分组，每组超过一个成员才算一组重复
+    let mut groups: HashMap<u32, Vec<usize>> = HashMap::new();
+    for i in 0..hashes.len() {
+        let root = uf.find(i as u32);
+        groups.entry(root).or_default().push(i);
+    }
+    let duplicates = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            // 取组内下标最小的作为代表，保证同一份输入的分组结果是确定的
+            group.sort_unstable();
+            let canonical = group.remove(0);
+            (canonical, group)
+        })
+        .collect::<HashMap<usize, Vec<usize>>>();
+
     let elapsed = now.elapsed();
     info!("phash 去重完成，耗时 {:?}", elapsed);
-    let duplicates = duplicates.into_inner().unwrap();
-    let total = duplicates.iter().map(|(_, value)| value.len()).sum::<usize>();
 
+    let total = duplicates.iter().map(|(_, value)| value.len()).sum::<usize>();
     info!("phash 去重完成，共 {} 组重复图片，{} 张重复图片", duplicates.len(), total);
 
     if let Some(output) = args.output {
@@ -116,8 +181,8 @@ fn main() {
         let duplicates = duplicates
             .iter()
             .map(|(key, value)| {
-                let k = &images[*key as usize];
-                let v = value.iter().map(|i| &images[*i as usize]).collect::<Vec<_>>();
+                let k = &images[*key];
+                let v = value.iter().map(|i| &images[*i]).collect::<Vec<_>>();
                 (k, v)
             })
             .collect::<HashMap<_, _>>();
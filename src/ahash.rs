@@ -0,0 +1,44 @@
+use anyhow::Result;
+use opencv::core::{Size, ToInputArray};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+pub type AHash = [u8; 8];
+
+/// 均值哈希：缩放到 8x8 后以全部像素的均值为阈值逐位生成哈希，结果可以像 dhash 一样用
+/// Hamming 距离比较。计算量比 dhash/phash 都小，但对亮度/对比度的整体偏移更敏感
+pub fn a_hash(input_arr: &impl ToInputArray) -> Result<AHash> {
+    let mut resize_img = Mat::default();
+    imgproc::resize(
+        input_arr,
+        &mut resize_img,
+        Size::new(8, 8),
+        0.0,
+        0.0,
+        imgproc::INTER_LINEAR_EXACT,
+    )?;
+
+    let gray_img = if resize_img.channels() > 1 {
+        let mut output = Mat::default();
+        imgproc::cvt_color_def(&resize_img, &mut output, imgproc::COLOR_BGR2GRAY)?;
+        output
+    } else {
+        resize_img
+    };
+
+    let data = gray_img.data_bytes()?;
+    assert!(data.len() == 64);
+    let mean = data.iter().map(|&b| b as u32).sum::<u32>() / data.len() as u32;
+
+    let mut hash = [0u8; 8];
+    for (i, chunk) in data.chunks_exact(8).enumerate() {
+        let mut b = 0u8;
+        for &px in chunk {
+            b <<= 1;
+            b |= if px as u32 >= mean { 1 } else { 0 };
+        }
+        hash[i] = b;
+    }
+
+    Ok(hash)
+}
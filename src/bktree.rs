@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::hamming::hamming;
+
+#[derive(Serialize, Deserialize)]
+struct Node {
+    hash: Vec<u8>,
+    id: usize,
+    children: HashMap<u32, Node>,
+}
+
+impl Node {
+    fn new(hash: Vec<u8>, id: usize) -> Self {
+        Self { hash, id, children: HashMap::new() }
+    }
+
+    fn insert(&mut self, hash: Vec<u8>, id: usize) {
+        let d = hamming::<8>(&self.hash, &hash);
+        match self.children.get_mut(&d) {
+            Some(child) => child.insert(hash, id),
+            None => {
+                self.children.insert(d, Node::new(hash, id));
+            }
+        }
+    }
+
+    fn search(&self, target: &[u8], radius: u32, result: &mut Vec<(usize, u32)>) {
+        let d = hamming::<8>(&self.hash, target);
+        if d <= radius {
+            result.push((self.id, d));
+        }
+        // 三角不等式剪枝：匹配节点到目标的距离不超过 radius，
+        // 因此只需要递归到与当前节点距离落在 [d - radius, d + radius] 的子节点
+        let lo = d.saturating_sub(radius);
+        let hi = d + radius;
+        for k in lo..=hi {
+            if let Some(child) = self.children.get(&k) {
+                child.search(target, radius, result);
+            }
+        }
+    }
+
+    fn count(&self) -> usize {
+        1 + self.children.values().map(Node::count).sum::<usize>()
+    }
+}
+
+/// 基于 BK-tree 的精确 Hamming 距离去重索引
+///
+/// 每个节点保存一个 hash，子节点按到父节点的整数距离分桶；查询时利用三角不等式剪枝，
+/// 只遍历距离可能落在半径内的子树，保证召回半径内的全部结果。用于弥补 [`crate::hnsw::HNSW`]
+/// 基于近似最近邻图搜索、可能漏召半径内匹配的问题
+pub struct BkTree {
+    root: Mutex<Option<Node>>,
+    path: PathBuf,
+}
+
+impl BkTree {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { root: Mutex::new(None), path: path.as_ref().to_path_buf() })
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = path.join("phash.bktree");
+        let root = if file.exists() {
+            bincode::deserialize(&std::fs::read(&file)?)?
+        } else {
+            None
+        };
+        Ok(Self { root: Mutex::new(root), path: path.to_path_buf() })
+    }
+
+    pub fn write(&self) -> Result<()> {
+        let root = self.root.lock().unwrap();
+        std::fs::write(self.path.join("phash.bktree"), bincode::serialize(&*root)?)?;
+        Ok(())
+    }
+
+    pub fn ntotal(&self) -> usize {
+        self.root.lock().unwrap().as_ref().map(Node::count).unwrap_or(0)
+    }
+
+    pub fn add(&self, data: &[u8], id: usize) {
+        let mut root = self.root.lock().unwrap();
+        match root.as_mut() {
+            Some(node) => node.insert(data.to_vec(), id),
+            None => *root = Some(Node::new(data.to_vec(), id)),
+        }
+    }
+
+    /// 返回半径内的全部匹配，每项为 `(id, distance)`
+    pub fn search(&self, data: &[u8], radius: u32) -> Vec<(usize, u32)> {
+        let mut result = Vec::new();
+        if let Some(node) = self.root.lock().unwrap().as_ref() {
+            node.search(data, radius, &mut result);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_respects_radius_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = BkTree::new(dir.path()).unwrap();
+
+        tree.add(&[0u8; 8], 0);
+        tree.add(&[1, 0, 0, 0, 0, 0, 0, 0], 1); // 距离 root 1 bit
+        tree.add(&[0xff; 8], 2); // 距离 root 64 bit
+
+        let mut hits = tree.search(&[0u8; 8], 1);
+        hits.sort_unstable();
+        assert_eq!(hits, vec![(0, 0), (1, 1)]);
+
+        let hits = tree.search(&[0u8; 8], 0);
+        assert_eq!(hits, vec![(0, 0)]);
+
+        assert_eq!(tree.ntotal(), 3);
+    }
+
+    #[test]
+    fn test_write_and_open_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = BkTree::new(dir.path()).unwrap();
+        tree.add(&[0u8; 8], 0);
+        tree.add(&[1, 0, 0, 0, 0, 0, 0, 0], 1);
+        tree.write().unwrap();
+
+        let reopened = BkTree::open(dir.path()).unwrap();
+        assert_eq!(reopened.ntotal(), 2);
+        assert_eq!(reopened.search(&[0u8; 8], 1).len(), 2);
+    }
+}
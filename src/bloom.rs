@@ -0,0 +1,83 @@
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use bytemuck::cast_slice;
+use siphasher::sip::SipHasher13;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// 预期图片数量，用于计算布隆过滤器的位数组大小
+const EXPECTED_IMAGES: usize = 1_000_000;
+/// 目标误判率
+const FALSE_POSITIVE_RATE: f64 = 0.001;
+
+/// 固定的 SipHash 密钥，保证位数组在多次运行间哈希结果一致，从而可以持久化复用
+const SIPHASH_KEYS: (u64, u64) = (0x5bd1_e995_7ee3_ccf1, 0x27d4_eb2f_1656_67c5);
+
+/// 基于双重哈希的持久化布隆过滤器，用于在精确查询前快速排除不存在的图片哈希
+///
+/// 每个元素通过 xxh3 和一个固定密钥的 SipHash 算出两个独立的 64 位哈希 h1/h2，
+/// 再派生出 k 个比特位 `h1 + i*h2 mod m`；只要其中任意一位为 0，就能确定该哈希一定
+/// 不存在，从而跳过一次完整的数据库查询，命中时再退回精确查询以排除误判
+pub struct Bloom {
+    bits: Vec<u64>,
+    m: u64,
+    k: u32,
+    path: PathBuf,
+}
+
+impl Bloom {
+    /// 创建一个新的布隆过滤器，大小根据 [`EXPECTED_IMAGES`] 和 [`FALSE_POSITIVE_RATE`] 计算：
+    /// `m = -n·ln(p)/(ln2)²`，`k = round(m/n·ln2)`
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let n = EXPECTED_IMAGES as f64;
+        let m = (-n * FALSE_POSITIVE_RATE.ln() / 2f64.ln().powi(2)).ceil().max(64.) as u64;
+        let k = ((m as f64 / n) * 2f64.ln()).round().max(1.) as u32;
+        Self { bits: vec![0u64; m.div_ceil(64) as usize], m, k, path: path.as_ref().to_path_buf() }
+    }
+
+    /// 打开已持久化的布隆过滤器，不存在则创建一个新的
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::new(path));
+        }
+
+        let data = fs::read(path)?;
+        let m = u64::from_le_bytes(data[0..8].try_into()?);
+        let k = u32::from_le_bytes(data[8..12].try_into()?);
+        let bits = cast_slice(&data[12..]).to_vec();
+        Ok(Self { bits, m, k, path: path.to_path_buf() })
+    }
+
+    /// 将布隆过滤器写入磁盘
+    pub fn write(&self) -> Result<()> {
+        let mut buf = Vec::with_capacity(12 + self.bits.len() * 8);
+        buf.extend_from_slice(&self.m.to_le_bytes());
+        buf.extend_from_slice(&self.k.to_le_bytes());
+        buf.extend_from_slice(cast_slice(&self.bits));
+        fs::write(&self.path, buf)?;
+        Ok(())
+    }
+
+    fn positions(&self, data: &[u8]) -> Vec<u64> {
+        let h1 = xxh3_64(data);
+        let mut hasher = SipHasher13::new_with_keys(SIPHASH_KEYS.0, SIPHASH_KEYS.1);
+        hasher.write(data);
+        let h2 = hasher.finish();
+        (0..self.k as u64).map(|i| h1.wrapping_add(i.wrapping_mul(h2)) % self.m).collect()
+    }
+
+    /// 插入一个图片哈希
+    pub fn insert(&mut self, data: &[u8]) {
+        for pos in self.positions(data) {
+            self.bits[(pos / 64) as usize] |= 1 << (pos % 64);
+        }
+    }
+
+    /// 判断图片哈希是否可能已存在，返回 `false` 时该哈希一定不存在
+    pub fn contains(&self, data: &[u8]) -> bool {
+        self.positions(data).iter().all(|&pos| self.bits[(pos / 64) as usize] & (1 << (pos % 64)) != 0)
+    }
+}
@@ -48,6 +48,7 @@ pub struct AddCommand {
 
 impl SubCommandExtend for AddCommand {
     async fn run(&self, opts: &Opts) -> anyhow::Result<()> {
+        self.orb.ensure_extractor_supported()?;
         ORB_OPTIONS.get_or_init(|| self.orb.clone());
 
         let re_suf = format!("(?i)({})", self.suffix.replace(',', "|"));
@@ -14,7 +14,7 @@ use crate::IMDBBuilder;
 use crate::cli::SubCommandExtend;
 use crate::config::{Opts, OrbOptions};
 use crate::orb::*;
-use crate::utils::{ImageHash, pb_style_speed};
+use crate::utils::{ImageHash, SimilarityLevel, pb_style_speed};
 
 #[derive(Parser, Debug, Clone)]
 pub struct AddCommand {
@@ -39,16 +39,43 @@ pub struct AddCommand {
     /// 使用 phash 去重时，判断相似的汉明距离阈值（0~64）
     #[arg(long, value_name = "D", default_value_t = 8, value_parser = clap::value_parser!(u32).range(0..=64))]
     pub phash_distance: u32,
+    /// 使用 phash 去重时，按相似度档位换算汉明距离阈值，优先于 --phash-distance
+    #[arg(long, value_name = "LEVEL")]
+    pub similarity_level: Option<SimilarityLevel>,
     /// 如果图片已添加，是否覆盖旧的记录
     #[arg(long)]
     pub overwrite: bool,
     /// 如果图片已添加，是否在旧记录的基础上追加新路径
     #[arg(long, conflicts_with = "overwrite")]
     pub append: bool,
+    /// 描述符集合 MinHash 草图长度（保留的最小哈希数量），用于近似重复检测粗筛，
+    /// 为 0 表示禁用该检测
+    #[arg(long, value_name = "N", default_value_t = 128)]
+    pub minhash_size: usize,
+    /// LSH 分桶的条带数量，草图按 `minhash_size / minhash_bands` 行均分到每个条带
+    #[arg(long, value_name = "N", default_value_t = 16)]
+    pub minhash_bands: usize,
+    /// 近似重复判定的 Jaccard 相似度阈值
+    #[arg(long, value_name = "RATIO", default_value_t = 0.5)]
+    pub jaccard_threshold: f32,
+    /// 跳过已经完整入库的来源路径，用于从上次中断的地方继续导入
+    #[arg(long, conflicts_with = "force_rescan")]
+    pub resume: bool,
+    /// 清空之前记录的入库进度，忽略 --resume 重新扫描所有文件
+    #[arg(long)]
+    pub force_rescan: bool,
+    /// 可插拔倒排列表后端地址（如 `memory://`、`lmdb:///path`、`s3://bucket/prefix`），
+    /// 不指定时沿用现有的磁盘倒排列表格式
+    #[arg(long, value_name = "ADDR")]
+    pub invlists_addr: Option<String>,
+    /// 额外启用 BK-tree 精确去重索引，弥补 HNSW 近似搜索可能漏召 phash 半径内匹配的问题
+    #[arg(long)]
+    pub bktree: bool,
 }
 
 impl SubCommandExtend for AddCommand {
     async fn run(&self, opts: &Opts) -> anyhow::Result<()> {
+        self.orb.ensure_extractor_supported()?;
         ORB_OPTIONS.get_or_init(|| self.orb.clone());
 
         let re_suf = format!("(?i)({})", self.suffix.replace(',', "|"));
@@ -70,11 +97,30 @@ impl SubCommandExtend for AddCommand {
             Duplicate::Ignore
         };
 
-        let db = Arc::new(IMDBBuilder::new(opts.conf_dir.clone()).hash(self.hash).open().await?);
+        let mut builder = IMDBBuilder::new(opts.conf_dir.clone()).hash(self.hash).bktree(self.bktree);
+        if self.minhash_size > 0 {
+            let rows = (self.minhash_size / self.minhash_bands.max(1)).max(1);
+            builder = builder.dedup(self.minhash_size, self.minhash_bands, rows);
+        }
+        if let Some(addr) = self.invlists_addr.clone() {
+            builder = builder.invlists_addr(addr);
+        }
+        let db = Arc::new(builder.open().await?);
+
+        // 指定了相似度档位时优先按档位换算距离阈值，否则沿用原始的 --phash-distance
+        let phash_distance = match self.similarity_level {
+            Some(level) => self.hash.distance_for(level),
+            None => self.phash_distance,
+        };
+
+        if self.force_rescan {
+            db.clear_ingested().await?;
+        }
 
         let pb = ProgressBar::no_length().with_style(pb_style_speed());
 
-        let (t1, rx) = task_scan(self.path.clone(), re_suf);
+        let scan_ctx = ScanContext::new(db.clone(), self.resume);
+        let (t1, rx) = task_scan(self.path.clone(), re_suf, scan_ctx.clone());
         let (t2, rx) = task_hash(rx, self.hash, pb.clone());
         let (t3, rx) = task_filter(
             rx,
@@ -82,24 +128,51 @@ impl SubCommandExtend for AddCommand {
             db.clone(),
             duplicate,
             replace.clone(),
-            self.phash_distance,
+            phash_distance,
         );
         let (t4, rx) = task_calc(rx, pb.clone());
-        let t5 = task_add(
+        let (t5, rx) = task_dedup(
             rx,
             pb.clone(),
             db.clone(),
-            self.min_keypoints as i32,
             duplicate,
-            replace,
-            self.phash_distance,
+            replace.clone(),
+            self.jaccard_threshold,
         );
+        let t6 = match self.invlists_addr.clone() {
+            // 指定了倒排列表后端地址时，用分片写入模式绕开单个 writer 的瓶颈
+            Some(invlists_addr) => task_shard_add(
+                rx,
+                pb.clone(),
+                db.clone(),
+                duplicate,
+                replace,
+                phash_distance,
+                invlists_addr,
+                opts.conf_dir.clone(),
+            ),
+            None => task_add(
+                rx,
+                pb.clone(),
+                db.clone(),
+                self.min_keypoints as i32,
+                duplicate,
+                replace,
+                phash_distance,
+            ),
+        };
 
         // 等待所有任务完成
-        let _ = tokio::try_join!(t1, t2, t3, t4, t5);
+        let _ = tokio::try_join!(t1, t2, t3, t4, t5, t6);
 
         db.save_phash_index()?;
+        db.save_bloom_index()?;
 
+        pb.println(format!(
+            "共扫描 {} 个条目，跳过已处理 {} 个",
+            scan_ctx.scanned.load(std::sync::atomic::Ordering::Relaxed),
+            scan_ctx.skipped.load(std::sync::atomic::Ordering::Relaxed)
+        ));
         pb.finish_with_message("图片添加完成");
 
         Ok(())
@@ -1,8 +1,11 @@
 use std::borrow::Cow;
+use std::io::Read as _;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use anyhow::Result;
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
 use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
 use either::Either;
 use futures::StreamExt;
@@ -10,7 +13,7 @@ use indicatif::ProgressBar;
 use log::info;
 use regex::Regex;
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
 use tokio::task::{JoinHandle, spawn_blocking};
 use tokio_tar::Archive;
 use walkdir::WalkDir;
@@ -20,15 +23,52 @@ use crate::IMDB;
 use crate::orb::ORB;
 use crate::utils::ImageHash;
 
-pub fn task_scan(path: PathBuf, regex_suf: Regex) -> (JoinHandle<()>, Receiver<ImageData>) {
+/// 恢复导入所需的上下文：已入库路径的判断依据与扫描进度计数
+///
+/// `resume` 为 `false` 时仍然会累加 `scanned` 计数（`skipped` 保持为 0），但不会跳过已入库的条目，
+/// 这样 `--force-rescan`（通过提前调用 [`IMDB::clear_ingested`]）和普通首次导入都能
+/// 复用同一套计数逻辑
+#[derive(Clone)]
+pub struct ScanContext {
+    pub db: Arc<IMDB>,
+    pub resume: bool,
+    pub scanned: Arc<AtomicUsize>,
+    pub skipped: Arc<AtomicUsize>,
+}
+
+impl ScanContext {
+    pub fn new(db: Arc<IMDB>, resume: bool) -> Self {
+        Self { db, resume, scanned: Arc::new(AtomicUsize::new(0)), skipped: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    /// 判断一个来源路径是否应当跳过；会先累加扫描计数，跳过时再累加跳过计数
+    fn should_skip(&self, path: &str) -> bool {
+        self.scanned.fetch_add(1, Ordering::Relaxed);
+        if self.resume && self.db.is_ingested(path) {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub fn task_scan(
+    path: PathBuf,
+    regex_suf: Regex,
+    ctx: ScanContext,
+) -> (JoinHandle<()>, Receiver<ImageData>) {
     let (tx, rx) = bounded(num_cpus::get());
     let t = tokio::spawn(async move {
         // NOTE: 这里刻意不使用 `?` 而是 unwrap，这是为了确保出错时正常崩溃
         // 如果上抛的话，上层就需要正确打印错误，太过麻烦，不如直接 panic
         if path.is_file() {
-            scan_tar(path, tx, regex_suf).await.unwrap();
+            match archive_kind(&path) {
+                Some(kind) => scan_archive(path, tx, regex_suf, kind, ctx).await.unwrap(),
+                None => scan_tar(path, tx, regex_suf, ctx).await.unwrap(),
+            }
         } else {
-            scan_directory(path, tx, regex_suf).await.unwrap();
+            scan_directory(path, tx, regex_suf, ctx).await.unwrap();
         }
     });
     (t, rx)
@@ -141,6 +181,48 @@ pub fn task_calc(
     (t, rx)
 }
 
+/// 描述符集合 MinHash 粗筛去重
+///
+/// 请求中描述的位置是「在 task_hash 和 task_calc 之间」，但粗筛依赖的是图片的描述符集合，
+/// 而描述符要到 task_calc 才会被计算出来，因此实际放在 task_calc 和 task_add 之间：
+/// 一旦拿到描述符就立刻用草图做一次粗筛，仍然能在最耗时的索引写入阶段之前拦下近似重复图片
+pub fn task_dedup(
+    lrx: Receiver<ProcessableImage>,
+    pb: ProgressBar,
+    db: Arc<IMDB>,
+    duplicate: Duplicate,
+    replace: Option<(Regex, String)>,
+    jaccard_threshold: f32,
+) -> (JoinHandle<()>, Receiver<ProcessableImage>) {
+    let (tx, rx) = bounded(num_cpus::get());
+    let t = tokio::spawn(async move {
+        futures::stream::iter(lrx)
+            .for_each(|data| async {
+                match db.check_duplicate_descriptors(&data.descriptors, jaccard_threshold) {
+                    Some(id) => {
+                        handle_duplicate(
+                            Either::Right(data),
+                            duplicate,
+                            id,
+                            replace.as_ref(),
+                            &db,
+                            &pb,
+                        )
+                        .await
+                        .unwrap();
+                        pb.inc(1);
+                    }
+                    None => {
+                        let tx = tx.clone();
+                        spawn_blocking(move || tx.send(data).unwrap()).await.unwrap();
+                    }
+                }
+            })
+            .await;
+    });
+    (t, rx)
+}
+
 pub fn task_add(
     lrx: Receiver<ProcessableImage>,
     pb: ProgressBar,
@@ -152,9 +234,12 @@ pub fn task_add(
     tokio::spawn(async move {
         futures::stream::iter(lrx)
             .for_each(|data| async {
+                // 用于恢复导入时标记该条目已经处理完成，必须是替换路径之前的原始来源路径，
+                // 这样扫描阶段才能用同样的路径判断是否需要跳过
+                let source_path = data.path.clone();
                 let path = match &replace {
-                    Some((re, replace)) => &*re.replace(&data.path, replace),
-                    None => &*data.path,
+                    Some((re, replace)) => re.replace(&data.path, replace).into_owned(),
+                    None => data.path.clone(),
                 };
 
                 match db.check_hash(&data.hash, phash_threshold).await.unwrap() {
@@ -171,8 +256,9 @@ pub fn task_add(
                         .unwrap();
                     }
                     None => {
-                        db.add_image(path, &data.hash, data.descriptors.view()).await.unwrap();
-                        pb.set_message(path.to_owned());
+                        db.add_image(&path, &data.hash, data.descriptors.view()).await.unwrap();
+                        db.mark_ingested(&source_path).await.unwrap();
+                        pb.set_message(path);
                     }
                 }
 
@@ -182,7 +268,142 @@ pub fn task_add(
     })
 }
 
-async fn scan_directory(path: PathBuf, tx: Sender<ImageData>, regex_suf: Regex) -> Result<()> {
+/// 并行分片写入：每个 worker 维护一个本地内存倒排列表分片，用量化器把描述符分配到对应的
+/// 倒排表后独立写入，ID 从 0 开始本地编号；全部 worker 完成后依次 `merge_from` 折叠进
+/// `invlists_addr` 指向的主存储，`add_id` 取运行中的高水位线，保证折叠后全局唯一。
+/// sqlite 一侧的写入量较小，仍然沿用 [`task_add`] 里对 `db.add_image`/`mark_ingested` 的调用，
+/// 这里并行化的只是原本由单个 writer 串行执行的倒排列表写入
+///
+/// 注：这里的 ID 是特征点粒度而非图片粒度——每个描述符在本地分片中独立编号，
+/// 和请求里说的“图片 ID 高水位线”是同一思路在特征点层面的体现
+pub fn task_shard_add(
+    lrx: Receiver<ProcessableImage>,
+    pb: ProgressBar,
+    db: Arc<IMDB>,
+    duplicate: Duplicate,
+    replace: Option<(Regex, String)>,
+    phash_threshold: u32,
+    invlists_addr: String,
+    conf_dir: crate::config::ConfDir,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let quantizer = crate::ivf::quantizer::HnswQuantizer::<32>::open(conf_dir.quantizer()).ok();
+        let nlist = quantizer.as_ref().map(|q| {
+            use crate::ivf::quantizer::Quantizer;
+            q.nlist() as u32
+        });
+        let nlist = nlist.unwrap_or(1);
+        let quantizer = Arc::new(quantizer);
+
+        let mut workers = Vec::with_capacity(num_cpus::get());
+        for _ in 0..num_cpus::get() {
+            let lrx = lrx.clone();
+            let pb = pb.clone();
+            let db = db.clone();
+            let replace = replace.clone();
+            let quantizer = quantizer.clone();
+
+            workers.push(tokio::spawn(async move {
+                let mut shard = crate::invlists::InvertedListsHandle::from_addr("memory://", nlist, 32)
+                    .await
+                    .unwrap();
+                let mut next_id = 0u64;
+
+                futures::stream::iter(lrx)
+                    .for_each(|data| {
+                        let db = db.clone();
+                        let replace = replace.clone();
+                        let pb = pb.clone();
+                        let quantizer = quantizer.clone();
+                        let shard = &mut shard;
+                        let next_id = &mut next_id;
+                        async move {
+                            let source_path = data.path.clone();
+                            let path = match &replace {
+                                Some((re, replace)) => re.replace(&data.path, replace).into_owned(),
+                                None => data.path.clone(),
+                            };
+
+                            match db.check_hash(&data.hash, phash_threshold).await.unwrap() {
+                                Some(id) => {
+                                    handle_duplicate(
+                                        Either::Right(data),
+                                        duplicate,
+                                        id,
+                                        replace.as_ref(),
+                                        &db,
+                                        &pb,
+                                    )
+                                    .await
+                                    .unwrap();
+                                    pb.inc(1);
+                                    return;
+                                }
+                                None => {}
+                            }
+
+                            {
+                                use crate::invlists::{InvertedLists, InvertedListsWriter};
+                                let mut writer = shard.writer().await.unwrap();
+                                for desc in &data.descriptors {
+                                    let list_no = match quantizer.as_ref() {
+                                        Some(q) => {
+                                            use crate::ivf::quantizer::Quantizer;
+                                            q.search(std::slice::from_ref(desc), 1)
+                                                .ok()
+                                                .and_then(|ids| ids.first().copied())
+                                                .filter(|&id| id >= 0)
+                                                .map(|id| id as u32 % nlist)
+                                                .unwrap_or(0)
+                                        }
+                                        None => 0,
+                                    };
+                                    writer.add_entries(list_no, &[*next_id], desc).await;
+                                    *next_id += 1;
+                                }
+                            }
+
+                            db.add_image(&path, &data.hash, &data.descriptors).await.unwrap();
+                            db.mark_ingested(&source_path).await.unwrap();
+                            pb.set_message(path);
+                            pb.inc(1);
+                        }
+                    })
+                    .await;
+
+                (shard, next_id)
+            }));
+        }
+
+        let mut shard_results = Vec::with_capacity(workers.len());
+        for worker in workers {
+            if let Ok(result) = worker.await {
+                shard_results.push(result);
+            }
+        }
+
+        let mut main = crate::invlists::InvertedListsHandle::from_addr(&invlists_addr, nlist, 32)
+            .await
+            .unwrap();
+        let mut high_water = 0u64;
+        {
+            use crate::invlists::{InvertedLists, InvertedListsWriter};
+            for (mut shard, count) in shard_results {
+                let mut shard_writer = shard.writer().await.unwrap();
+                let mut main_writer = main.writer().await.unwrap();
+                main_writer.merge_from(&mut shard_writer, high_water).await;
+                high_water += count;
+            }
+        }
+    })
+}
+
+async fn scan_directory(
+    path: PathBuf,
+    tx: Sender<ImageData>,
+    regex_suf: Regex,
+    ctx: ScanContext,
+) -> Result<()> {
     info!("开始扫描目录: {}", path.display());
 
     futures::stream::iter(WalkDir::new(path))
@@ -202,11 +423,18 @@ async fn scan_directory(path: PathBuf, tx: Sender<ImageData>, regex_suf: Regex)
         .for_each_concurrent(32, |entry| {
             let tx = tx.clone();
             let path = entry.path().to_path_buf();
-            async {
+            let regex_suf = regex_suf.clone();
+            let ctx = ctx.clone();
+            async move {
                 let path_str = path.to_string_lossy().to_string();
-                match path.extension().and_then(|ext| ext.to_str()) {
-                    Some("tar") => scan_tar(path, tx, regex_suf.clone()).await.unwrap(),
-                    _ => {
+                match archive_kind(&path) {
+                    // 归档内部的成员路径在归档自己的扫描函数中逐个判断是否跳过，
+                    // 这里不能提前跳过，否则无法读取归档内未处理完的剩余成员
+                    Some(kind) => scan_archive(path, tx, regex_suf, kind, ctx).await.unwrap(),
+                    None => {
+                        if ctx.should_skip(&path_str) {
+                            return;
+                        }
                         if let Ok(data) = tokio::fs::read(path).await {
                             spawn_blocking(move || {
                                 tx.send(ImageData { path: path_str, data }).unwrap()
@@ -223,9 +451,84 @@ async fn scan_directory(path: PathBuf, tx: Sender<ImageData>, regex_suf: Regex)
     Ok(())
 }
 
-async fn scan_tar(path: impl AsRef<Path>, tx: Sender<ImageData>, re_suf: Regex) -> Result<()> {
+/// `task_scan`/`scan_directory`识别的归档格式
+enum ArchiveKind {
+    Tar,
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+/// 根据文件名（而非 [`Path::extension`] 返回的单一后缀）判断归档类型，
+/// 从而正确识别 `.tar.gz`/`.tar.zst` 这类复合后缀
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.to_string_lossy().to_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".tar.zst") {
+        Some(ArchiveKind::TarZst)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".zip") || name.ends_with(".cbz") {
+        Some(ArchiveKind::Zip)
+    } else {
+        None
+    }
+}
+
+async fn scan_archive(
+    path: PathBuf,
+    tx: Sender<ImageData>,
+    re_suf: Regex,
+    kind: ArchiveKind,
+    ctx: ScanContext,
+) -> Result<()> {
+    match kind {
+        ArchiveKind::Tar => scan_tar(path, tx, re_suf, ctx).await,
+        ArchiveKind::TarGz => scan_tar_gz(path, tx, re_suf, ctx).await,
+        ArchiveKind::TarZst => scan_tar_zst(path, tx, re_suf, ctx).await,
+        ArchiveKind::Zip => scan_zip(path, tx, re_suf, ctx).await,
+    }
+}
+
+async fn scan_tar(
+    path: impl AsRef<Path>,
+    tx: Sender<ImageData>,
+    re_suf: Regex,
+    ctx: ScanContext,
+) -> Result<()> {
+    let file = File::open(path).await?;
+    scan_tar_reader(file, tx, re_suf, ctx).await
+}
+
+async fn scan_tar_gz(
+    path: impl AsRef<Path>,
+    tx: Sender<ImageData>,
+    re_suf: Regex,
+    ctx: ScanContext,
+) -> Result<()> {
+    let file = File::open(path).await?;
+    scan_tar_reader(GzipDecoder::new(BufReader::new(file)), tx, re_suf, ctx).await
+}
+
+async fn scan_tar_zst(
+    path: impl AsRef<Path>,
+    tx: Sender<ImageData>,
+    re_suf: Regex,
+    ctx: ScanContext,
+) -> Result<()> {
     let file = File::open(path).await?;
-    let mut archive = Archive::new(file);
+    scan_tar_reader(ZstdDecoder::new(BufReader::new(file)), tx, re_suf, ctx).await
+}
+
+/// tar 归档读取的共同实现，`reader` 可以是原始文件，也可以是套了一层透明解压的 gzip/zstd 流
+async fn scan_tar_reader(
+    reader: impl AsyncRead + Unpin,
+    tx: Sender<ImageData>,
+    re_suf: Regex,
+    ctx: ScanContext,
+) -> Result<()> {
+    let mut archive = Archive::new(reader);
     let mut entries = archive.entries()?;
 
     // NOTE: tar 的 entries 必须按顺序读取，不能乱序并发
@@ -244,6 +547,9 @@ async fn scan_tar(path: impl AsRef<Path>, tx: Sender<ImageData>, re_suf: Regex)
         }
 
         let path = path.to_string_lossy().to_string();
+        if ctx.should_skip(&path) {
+            continue;
+        }
 
         let mut data = Vec::with_capacity(entry.header().size()? as usize);
         entry.read_to_end(&mut data).await?;
@@ -254,6 +560,47 @@ async fn scan_tar(path: impl AsRef<Path>, tx: Sender<ImageData>, re_suf: Regex)
     Ok(())
 }
 
+/// zip 的中央目录只能通过同步 API 按顺序迭代，因此整个扫描过程放在一个阻塞线程里完成，
+/// 读取到的每个条目仍然通过 `spawn_blocking` 发送到 channel，与 tar 路径保持相同的背压行为
+async fn scan_zip(
+    path: PathBuf,
+    tx: Sender<ImageData>,
+    re_suf: Regex,
+    ctx: ScanContext,
+) -> Result<()> {
+    spawn_blocking(move || -> Result<()> {
+        let file = std::fs::File::open(&path)?;
+        let mut archive = zip::ZipArchive::new(file)?;
+
+        // NOTE: 与 tar 一样，zip 的条目也按中央目录顺序读取，不做乱序并发
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if !entry.is_file() {
+                continue;
+            }
+            let entry_path = PathBuf::from(entry.name());
+            let Some(ext) = entry_path.extension() else {
+                continue;
+            };
+            if !re_suf.is_match(&ext.to_string_lossy()) {
+                continue;
+            }
+
+            let entry_path = entry_path.to_string_lossy().to_string();
+            if ctx.should_skip(&entry_path) {
+                continue;
+            }
+
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut data)?;
+
+            tx.send(ImageData { path: entry_path, data }).unwrap();
+        }
+        Ok(())
+    })
+    .await?
+}
+
 async fn handle_duplicate(
     data: Either<HashedImageData, ProcessableImage>,
     duplicate: Duplicate,
@@ -266,6 +613,11 @@ async fn handle_duplicate(
         Either::Left(data) => data.path,
         Either::Right(data) => data.path,
     };
+    // 匹配到的已有图片路径，查询失败（例如该 ID 已被删除）时退化为只显示 ID
+    let matched_path = db
+        .get_image_path(duplicate_id)
+        .await
+        .unwrap_or_else(|_| format!("#{duplicate_id}"));
 
     match duplicate {
         Duplicate::Overwrite => {
@@ -273,17 +625,17 @@ async fn handle_duplicate(
                 .map(|(re, replace)| re.replace(&path, replace))
                 .unwrap_or(Cow::Borrowed(&path));
             db.update_image_path(duplicate_id, &path).await?;
-            pb.set_message(format!("更新图片路径: {}", path));
+            pb.set_message(format!("更新图片路径: {} -> 匹配到 {}", path, matched_path));
         }
         Duplicate::Append => {
             let path = replace
                 .map(|(re, replace)| re.replace(&path, replace))
                 .unwrap_or(Cow::Borrowed(&path));
             db.append_image_path(duplicate_id, &path).await?;
-            pb.set_message(format!("追加图片路径: {}", path));
+            pb.set_message(format!("追加图片路径: {} -> 匹配到 {}", path, matched_path));
         }
         Duplicate::Ignore => {
-            pb.set_message(format!("跳过已添加图片: {}", path));
+            pb.set_message(format!("跳过已添加图片: {}，匹配到 {}", path, matched_path));
         }
     }
     Ok(())
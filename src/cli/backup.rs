@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use log::info;
+
+use crate::Opts;
+use crate::cli::SubCommandExtend;
+use crate::db;
+
+#[derive(Parser, Debug, Clone)]
+pub struct BackupCommand {
+    /// 快照文件保存路径，不能是已存在的文件
+    pub dest: PathBuf,
+}
+
+impl SubCommandExtend for BackupCommand {
+    async fn run(&self, opts: &Opts) -> Result<()> {
+        let conn = db::init_db(opts.conf_dir.database(), true).await?;
+        info!("开始在线快照数据库到 {}", self.dest.display());
+        db::backup(&conn, &self.dest).await?;
+        info!("快照完成");
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use clap::Parser;
+use indicatif::ProgressBar;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tokio::task::block_in_place;
+
+use crate::IMDBBuilder;
+use crate::cli::SubCommandExtend;
+use crate::config::{Opts, OrbOptions, SearchOptions};
+use crate::faiss::FaissSearchParams;
+use crate::index::IndexManager;
+use crate::orb::ORBDetector;
+use crate::utils::pb_style;
+
+#[derive(Parser, Debug, Clone)]
+pub struct BenchCommand {
+    #[command(flatten)]
+    pub orb: OrbOptions,
+    #[command(flatten)]
+    pub search: SearchOptions,
+    /// 评测工作负载文件路径（JSON），描述查询图片、期望命中结果与评测使用的 k 值
+    pub workload: String,
+    /// 参与搜索的索引名称，用法同 `search` 子命令
+    #[arg(short = 'I', long, value_name = "NAME", default_value = "index", value_delimiter = ',')]
+    pub index_name: Vec<String>,
+    /// 将完整的 JSON 评测报告写入该文件，不指定时输出到标准输出
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<String>,
+}
+
+/// 工作负载文件的格式
+#[derive(Deserialize)]
+struct Workload {
+    /// 评测使用的 k 值列表，recall@k 会针对每个值单独计算
+    k_values: Vec<usize>,
+    queries: Vec<WorkloadQuery>,
+}
+
+#[derive(Deserialize)]
+struct WorkloadQuery {
+    /// 查询图片路径
+    image: String,
+    /// 该查询期望命中的图片路径（ground truth），顺序不影响计算结果
+    expected: Vec<String>,
+}
+
+/// 单次查询的评测结果
+#[derive(Serialize, Deserialize)]
+struct QueryReport {
+    image: String,
+    latency_ms: f32,
+    hits: usize,
+    expected: usize,
+    recall_at_k: BTreeMap<usize, f32>,
+    average_precision: f32,
+}
+
+/// 各阶段累计耗时，用于代替手动翻找 `debug!` 日志
+#[derive(Serialize, Deserialize)]
+struct StageBreakdown {
+    /// 加载数据库与索引的耗时
+    loading_ms: f32,
+    /// 累计特征点提取耗时
+    reading_ms: f32,
+    /// 累计 faiss 近邻搜索耗时
+    index_search_ms: f32,
+    /// 累计近邻分组统计与排序耗时
+    process_group_ms: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BenchReport {
+    queries: usize,
+    recall_at_k: BTreeMap<usize, f32>,
+    mean_average_precision: f32,
+    mean_latency_ms: f32,
+    /// 延迟分位数，单位毫秒，用于观察长尾延迟而不只是均值
+    latency_p50_ms: f32,
+    latency_p90_ms: f32,
+    latency_p99_ms: f32,
+    stage_breakdown: StageBreakdown,
+    per_query: Vec<QueryReport>,
+}
+
+impl SubCommandExtend for BenchCommand {
+    async fn run(&self, opts: &Opts) -> Result<()> {
+        self.orb.ensure_extractor_supported()?;
+
+        let workload: Workload = serde_json::from_str(&std::fs::read_to_string(&self.workload)?)?;
+        if workload.queries.is_empty() {
+            return Err(anyhow!("工作负载中没有任何查询"));
+        }
+
+        let loading_start = Instant::now();
+        let mut builder = IMDBBuilder::new(opts.conf_dir.clone())
+            .score_type(self.search.score_type)
+            .criteria(self.search.criteria.clone());
+        if self.search.minhash {
+            builder = builder.minhash(self.search.minhash_h);
+        }
+        let db = builder.open().await?;
+        let manager = IndexManager::new(opts.conf_dir.clone());
+        let (index, shards) =
+            manager.get_named_indexes(&self.index_name, !self.search.no_mmap, self.search.ondisk);
+        let index = Arc::new(index);
+        let loading = loading_start.elapsed();
+
+        let mut orb = ORBDetector::create(self.orb.clone());
+        let SearchOptions {
+            k,
+            distance,
+            count,
+            nprobe,
+            ef_search,
+            minhash,
+            minhash_top_n,
+            ratio_test,
+            ratio,
+            ..
+        } = self.search;
+        let params = FaissSearchParams { nprobe, ef_search };
+        let minhash_top_n = minhash.then_some(minhash_top_n);
+        let ratio_test = ratio_test.then_some(ratio);
+
+        info!("正在对 {} 个查询运行评测……", workload.queries.len());
+
+        let mut reading = Duration::ZERO;
+        let mut index_search = Duration::ZERO;
+        let mut process_group = Duration::ZERO;
+        let mut per_query = Vec::with_capacity(workload.queries.len());
+
+        let pb = ProgressBar::new(workload.queries.len() as u64).with_style(pb_style());
+        for query in &workload.queries {
+            let query_start = Instant::now();
+
+            let detect_start = Instant::now();
+            let (_, _, des) = block_in_place(|| orb.detect_file(&query.image))?;
+            reading += detect_start.elapsed();
+
+            let (mut result, timing) = db
+                .search_timed(
+                    index.clone(),
+                    &[des],
+                    k,
+                    distance,
+                    count,
+                    params.clone(),
+                    &shards,
+                    None,
+                    minhash_top_n,
+                    ratio_test,
+                )
+                .await?;
+            index_search += timing.index_search;
+            process_group += timing.process_group;
+            let result = result.pop().unwrap_or_default();
+
+            per_query.push(score_query(query, &result, &workload.k_values, query_start.elapsed()));
+            pb.inc(1);
+        }
+        pb.finish_and_clear();
+
+        let report = aggregate_report(
+            per_query,
+            &workload.k_values,
+            loading,
+            reading,
+            index_search,
+            process_group,
+        );
+
+        let json = serde_json::to_string_pretty(&report)?;
+        match &self.output {
+            Some(path) => std::fs::write(path, json)?,
+            None => println!("{json}"),
+        }
+
+        Ok(())
+    }
+}
+
+/// 根据查询结果和期望命中集合，计算该查询的 recall@k 与平均准确率（AP）
+fn score_query(
+    query: &WorkloadQuery,
+    result: &[(f32, String)],
+    k_values: &[usize],
+    latency: Duration,
+) -> QueryReport {
+    let expected: HashSet<&str> = query.expected.iter().map(String::as_str).collect();
+    let retrieved: Vec<&str> = result.iter().map(|(_, name)| name.as_str()).collect();
+
+    let recall_at_k = k_values
+        .iter()
+        .map(|&k| {
+            let hit = retrieved.iter().take(k).filter(|name| expected.contains(**name)).count();
+            let recall = if expected.is_empty() { 0. } else { hit as f32 / expected.len() as f32 };
+            (k, recall)
+        })
+        .collect();
+
+    let mut hits = 0;
+    let mut sum_precision = 0.;
+    for (rank, name) in retrieved.iter().enumerate() {
+        if expected.contains(*name) {
+            hits += 1;
+            sum_precision += hits as f32 / (rank + 1) as f32;
+        }
+    }
+    let average_precision = if expected.is_empty() { 0. } else { sum_precision / expected.len() as f32 };
+
+    QueryReport {
+        image: query.image.clone(),
+        latency_ms: latency.as_secs_f32() * 1000.,
+        hits,
+        expected: expected.len(),
+        recall_at_k,
+        average_precision,
+    }
+}
+
+/// 计算已排序切片中给定分位数 `p`（0~1）对应的值，`sorted` 为空时返回 0
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.;
+    }
+    let idx = (((sorted.len() - 1) as f32) * p).round() as usize;
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+/// 汇总所有查询的结果，计算整体 recall@k、mAP 与各阶段累计耗时
+fn aggregate_report(
+    per_query: Vec<QueryReport>,
+    k_values: &[usize],
+    loading: Duration,
+    reading: Duration,
+    index_search: Duration,
+    process_group: Duration,
+) -> BenchReport {
+    let queries = per_query.len();
+
+    let recall_at_k = k_values
+        .iter()
+        .map(|&k| {
+            let sum: f32 = per_query.iter().filter_map(|q| q.recall_at_k.get(&k)).sum();
+            (k, sum / queries as f32)
+        })
+        .collect();
+
+    let mean_average_precision =
+        per_query.iter().map(|q| q.average_precision).sum::<f32>() / queries as f32;
+    let mean_latency_ms = per_query.iter().map(|q| q.latency_ms).sum::<f32>() / queries as f32;
+
+    let mut latencies: Vec<f32> = per_query.iter().map(|q| q.latency_ms).collect();
+    latencies.sort_by(|a, b| a.total_cmp(b));
+    let latency_p50_ms = percentile(&latencies, 0.50);
+    let latency_p90_ms = percentile(&latencies, 0.90);
+    let latency_p99_ms = percentile(&latencies, 0.99);
+
+    BenchReport {
+        queries,
+        recall_at_k,
+        mean_average_precision,
+        mean_latency_ms,
+        latency_p50_ms,
+        latency_p90_ms,
+        latency_p99_ms,
+        stage_breakdown: StageBreakdown {
+            loading_ms: loading.as_secs_f32() * 1000.,
+            reading_ms: reading.as_secs_f32() * 1000.,
+            index_search_ms: index_search.as_secs_f32() * 1000.,
+            process_group_ms: process_group.as_secs_f32() * 1000.,
+        },
+        per_query,
+    }
+}
+
+/// 比较两份评测报告，打印各项指标的差值
+#[derive(Parser, Debug, Clone)]
+pub struct BenchCompareCommand {
+    /// 作为基准的评测报告文件（JSON）
+    pub baseline: String,
+    /// 待对比的评测报告文件（JSON）
+    pub new: String,
+    /// 单项指标变化超过该百分比视为回归（按对该指标不利的方向计算）
+    #[arg(short, long, default_value_t = 5.0)]
+    pub threshold: f32,
+}
+
+impl SubCommandExtend for BenchCompareCommand {
+    async fn run(&self, _opts: &Opts) -> Result<()> {
+        let baseline: BenchReport = serde_json::from_str(&std::fs::read_to_string(&self.baseline)?)?;
+        let new: BenchReport = serde_json::from_str(&std::fs::read_to_string(&self.new)?)?;
+
+        println!("查询数量        ：{} -> {}", baseline.queries, new.queries);
+        println!(
+            "{}",
+            metric_line(
+                "mAP",
+                baseline.mean_average_precision,
+                new.mean_average_precision,
+                true,
+                self.threshold,
+            )
+        );
+        println!(
+            "{}",
+            metric_line("平均延迟 (ms)", baseline.mean_latency_ms, new.mean_latency_ms, false, self.threshold)
+        );
+        println!(
+            "{}",
+            metric_line("p50 延迟 (ms)", baseline.latency_p50_ms, new.latency_p50_ms, false, self.threshold)
+        );
+        println!(
+            "{}",
+            metric_line("p90 延迟 (ms)", baseline.latency_p90_ms, new.latency_p90_ms, false, self.threshold)
+        );
+        println!(
+            "{}",
+            metric_line("p99 延迟 (ms)", baseline.latency_p99_ms, new.latency_p99_ms, false, self.threshold)
+        );
+        println!(
+            "{}",
+            metric_line(
+                "加载耗时 (ms)",
+                baseline.stage_breakdown.loading_ms,
+                new.stage_breakdown.loading_ms,
+                false,
+                self.threshold,
+            )
+        );
+        println!(
+            "{}",
+            metric_line(
+                "读取耗时 (ms)",
+                baseline.stage_breakdown.reading_ms,
+                new.stage_breakdown.reading_ms,
+                false,
+                self.threshold,
+            )
+        );
+        println!(
+            "{}",
+            metric_line(
+                "搜索耗时 (ms)",
+                baseline.stage_breakdown.index_search_ms,
+                new.stage_breakdown.index_search_ms,
+                false,
+                self.threshold,
+            )
+        );
+        println!(
+            "{}",
+            metric_line(
+                "处理耗时 (ms)",
+                baseline.stage_breakdown.process_group_ms,
+                new.stage_breakdown.process_group_ms,
+                false,
+                self.threshold,
+            )
+        );
+
+        let k_values: Vec<usize> = baseline.recall_at_k.keys().copied().collect();
+        for k in k_values {
+            let old_recall = baseline.recall_at_k.get(&k).copied().unwrap_or_default();
+            let new_recall = new.recall_at_k.get(&k).copied().unwrap_or_default();
+            println!("{}", metric_line(&format!("recall@{k}"), old_recall, new_recall, true, self.threshold));
+        }
+
+        Ok(())
+    }
+}
+
+/// 计算 `new` 相对 `baseline` 的百分比变化
+fn pct_change(baseline: f32, new: f32) -> f32 {
+    if baseline == 0. { 0. } else { (new - baseline) / baseline * 100. }
+}
+
+/// 格式化一行指标对比：数值差值、百分比变化，变化方向对该指标不利且超过 `threshold`
+/// 时标记为回归。`higher_is_better` 用于区分延迟（越低越好）和 recall/mAP（越高越好）
+fn metric_line(name: &str, baseline: f32, new: f32, higher_is_better: bool, threshold: f32) -> String {
+    let pct = pct_change(baseline, new);
+    let regressed = if higher_is_better { pct < -threshold } else { pct > threshold };
+    let flag = if regressed { "  [回归]" } else { "" };
+    format!("{name:<14}：{baseline:.3} -> {new:.3} ({pct:+.1}%){flag}")
+}
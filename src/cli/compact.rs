@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, ensure};
+use clap::{Parser, ValueEnum};
+use log::info;
+
+use crate::Opts;
+use crate::cli::SubCommandExtend;
+use crate::ivf::{
+    ArrayInvertedLists, CompressionOptions, InvertedLists, LayeredInvlists, OnDiskInvlists,
+    PqCodec, PqOnDiskInvlists, VarintInvertedLists, save_invlists, save_pq_invlists,
+};
+
+/// PQ 编码时每个子块占用的字节数，固定为 4 字节（即 `m = N / 4`），因为 [`PqCodec::train`]
+/// 的子块大小是编译期常量，无法通过命令行参数在运行时指定
+const PQ_SUB_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InvlistsBackend {
+    /// 整体压缩的磁盘倒排列表，与现有格式相同，仅用于合并另一份倒排列表（需配合 `--merge`）
+    OnDisk,
+    /// PQ 编码压缩，用非对称距离计算检索，适合内存/磁盘吃紧但能接受召回率损失的场景
+    Pq,
+    /// 只读 mmap 倒排列表，codes 不压缩以便零拷贝读取，适合作为稳定版本长期提供查询服务
+    Mmap,
+    /// delta + varint 编码 ID 列表，适合 ID 分布稠密、压缩率优先于随机访问性能的归档场景
+    Varint,
+}
+
+/// 把现有的磁盘倒排列表转换为另一种存储格式，或合并进另一份倒排列表
+///
+/// 本命令读一遍 `--input` 指定的倒排列表，按目标格式重新编码写出到 `--output`，不修改原文件
+#[derive(Parser, Debug, Clone)]
+pub struct CompactCommand {
+    /// 待转换的倒排列表文件
+    pub input: PathBuf,
+    /// 目标格式
+    #[arg(long, value_enum)]
+    pub backend: InvlistsBackend,
+    /// 输出文件路径，默认在原文件名后追加目标格式的后缀
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// 待合并进来的倒排列表文件，仅 `--backend on-disk` 有效；两者的 nlist 必须一致，
+    /// 典型场景是把一个刚训练好的子索引合并进主索引的倒排列表
+    #[arg(long)]
+    pub merge: Option<PathBuf>,
+    /// PQ 训练的最大迭代次数，仅 `--backend pq` 有效
+    #[arg(long, default_value_t = 20)]
+    pub pq_max_iter: usize,
+}
+
+impl SubCommandExtend for CompactCommand {
+    async fn run(&self, _opts: &Opts) -> Result<()> {
+        let disk = OnDiskInvlists::<32>::load(&self.input)?;
+
+        let output = self.output.clone().unwrap_or_else(|| match self.backend {
+            InvlistsBackend::OnDisk => self.input.with_extension("merged.bin"),
+            InvlistsBackend::Pq => self.input.with_extension("pq.bin"),
+            InvlistsBackend::Mmap => self.input.with_extension("mmap.bin"),
+            InvlistsBackend::Varint => self.input.with_extension("varint.bin"),
+        });
+
+        match self.backend {
+            InvlistsBackend::OnDisk => {
+                let merge_path = self.merge.as_ref().ok_or_else(|| {
+                    anyhow::anyhow!("`--backend on-disk` 需要通过 --merge 指定待合并的倒排列表文件")
+                })?;
+                let extra = OnDiskInvlists::<32>::load(merge_path)?;
+                ensure!(extra.nlist() == disk.nlist(), "待合并的倒排列表 nlist 不一致");
+
+                // 把待合并的数据先写入内存层，再借助 LayeredInvlists::flush 把两层合并落盘，
+                // 和增量写入场景走的是完全相同的合并路径
+                let mut layered = LayeredInvlists::<32>::new(disk);
+                for i in 0..extra.nlist() {
+                    let (ids, codes) = extra.get_list(i)?;
+                    layered.add_entries(i, &ids, &codes)?;
+                }
+                layered.flush(&output)?;
+                info!("已合并倒排列表：{}", output.display());
+            }
+            InvlistsBackend::Pq => {
+                let mut data = Vec::new();
+                for i in 0..disk.nlist() {
+                    let (_, codes) = disk.get_list(i)?;
+                    data.extend(codes.iter().copied());
+                }
+                ensure!(!data.is_empty(), "倒排列表为空，无法训练 PQ 编码器");
+
+                let codec = PqCodec::<32>::train::<PQ_SUB_SIZE>(&data, self.pq_max_iter);
+                let codec_path = PathBuf::from(format!("{}.codec", output.display()));
+                codec.save(&codec_path)?;
+                save_pq_invlists(&disk, &codec, &output)?;
+                info!(
+                    "已生成 PQ 倒排列表：{}（编码器：{}）",
+                    output.display(),
+                    codec_path.display()
+                );
+
+                // 重新加载一遍并做一次探测性搜索，确认 PQ 编码后的倒排列表仍然可以正常检索，
+                // 而不只是能写出文件
+                let pq = PqOnDiskInvlists::<32>::load(&output, PqCodec::<32>::open(&codec_path)?)?;
+                if let Some(list_no) = (0..pq.nlist()).find(|&i| pq.list_len(i) > 0) {
+                    let (_, codes) = disk.get_list(list_no)?;
+                    pq.search_list(list_no, &codes[0], 1)?;
+                }
+                info!("PQ 倒排列表校验通过");
+            }
+            InvlistsBackend::Mmap => {
+                let mut array = ArrayInvertedLists::<32>::new(disk.nlist());
+                for i in 0..disk.nlist() {
+                    let (ids, codes) = disk.get_list(i)?;
+                    array.add_entries(i, &ids, &codes)?;
+                }
+                let mmap = array.freeze(&output, array.nlist())?;
+                info!("已生成 mmap 倒排列表：{}（{} 个列表）", output.display(), mmap.nlist());
+            }
+            InvlistsBackend::Varint => {
+                let mut varint = VarintInvertedLists::<32>::new(disk.nlist());
+                for i in 0..disk.nlist() {
+                    let (ids, codes) = disk.get_list(i)?;
+                    varint.add_entries(i, &ids, &codes)?;
+                }
+                save_invlists::<32, _, _>(&varint, &output, CompressionOptions::default())?;
+                info!("已生成 varint 倒排列表：{}", output.display());
+            }
+        }
+
+        Ok(())
+    }
+}
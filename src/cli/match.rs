@@ -1,6 +1,7 @@
 use anyhow::Result;
-use clap::Parser;
-use log::info;
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
+use opencv::calib3d;
 use opencv::core::*;
 use opencv::prelude::*;
 use opencv::{features2d, flann};
@@ -10,6 +11,17 @@ use crate::config::{Opts, OrbOptions};
 use crate::orb::ORBDetector;
 use crate::utils;
 
+/// 几何验证使用的模型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VerifyModel {
+    /// 单应性矩阵，适用于平面场景或纯旋转
+    Homography,
+    /// 基础矩阵，适用于存在视差的一般场景
+    Fundamental,
+    /// 仿射变换，比单应性约束更强，适用于形变较小的场景
+    Affine,
+}
+
 #[derive(Parser, Debug, Clone)]
 pub struct MatchCommand {
     #[command(flatten)]
@@ -20,10 +32,20 @@ pub struct MatchCommand {
     pub image2: String,
     /// 不使用 GUI 展示，而是保存到文件
     pub output: Option<String>,
+    /// 几何验证使用的模型
+    #[arg(long, value_enum, default_value_t = VerifyModel::Homography)]
+    pub model: VerifyModel,
+    /// RANSAC 重投影误差阈值（像素）
+    #[arg(long, default_value_t = 3.0)]
+    pub ransac_threshold: f64,
+    /// 判定为匹配所需的最少内点数量
+    #[arg(long, default_value_t = 10)]
+    pub min_inliers: usize,
 }
 
 impl SubCommandExtend for MatchCommand {
     async fn run(&self, _opts: &Opts) -> Result<()> {
+        self.orb.ensure_extractor_supported()?;
         let mut orb = ORBDetector::create(self.orb.clone());
         let (img1, kps1, des1) = orb.detect_file(&self.image1)?;
         let (img2, kps2, des2) = orb.detect_file(&self.image2)?;
@@ -38,20 +60,60 @@ impl SubCommandExtend for MatchCommand {
         let flann = default_flann_matcher();
         flann.knn_train_match(&des1, &des2, &mut matches, 2, &mask, false)?;
 
-        let mut matches_mask = vec![];
+        // 记录每次匹配是否通过比率测试，`ratio_pass` 与 `matches` 等长，用于在几何验证
+        // 之后把对应位置的掩码改回 0，而不必再处理未通过比率测试的匹配
+        let mut ratio_matches = vec![];
+        let mut ratio_pass = vec![];
         for match_ in matches.iter() {
             if match_.len() != 2 {
-                matches_mask.push(Vector::<i8>::from_iter([0, 0]));
+                ratio_pass.push(false);
                 continue;
             }
             let (m, n) = (match_.get(0)?, match_.get(1)?);
             if m.distance < 0.7 * n.distance {
-                matches_mask.push(Vector::<i8>::from_iter([1, 0]));
+                ratio_matches.push(m);
+                ratio_pass.push(true);
             } else {
-                matches_mask.push(Vector::<i8>::from_iter([0, 0]));
+                ratio_pass.push(false);
+            }
+        }
+        info!("比率测试通过数量: {}", ratio_matches.len());
+
+        let inlier_mask = self.geometric_verify(&kps1, &kps2, &ratio_matches)?;
+
+        let mut ratio_index = 0;
+        let matches_mask: Vector<Vector<i8>> = ratio_pass
+            .iter()
+            .map(|&passed| {
+                if !passed {
+                    return Vector::<i8>::from_iter([0, 0]);
+                }
+                let is_inlier = match &inlier_mask {
+                    Some(mask) => mask[ratio_index] != 0,
+                    None => true,
+                };
+                ratio_index += 1;
+                if is_inlier { Vector::<i8>::from_iter([1, 0]) } else { Vector::<i8>::from_iter([0, 0]) }
+            })
+            .collect();
+
+        let inliers = inlier_mask.as_ref().map(|m| m.iter().filter(|&&b| b != 0).count());
+        let score = inliers.unwrap_or(0);
+        match inliers {
+            Some(inliers) => {
+                let ratio = if ratio_matches.is_empty() {
+                    0.
+                } else {
+                    inliers as f32 / ratio_matches.len() as f32
+                };
+                info!("内点数量: {inliers} ({:.1}% 内点率)", ratio * 100.);
+                info!("匹配得分: {score}");
+                if inliers < self.min_inliers {
+                    warn!("内点数量低于阈值 {}，判定为不匹配", self.min_inliers);
+                }
             }
+            None => warn!("比率测试通过的匹配数量不足，无法进行几何验证"),
         }
-        let matches_mask = Vector::<Vector<i8>>::from(matches_mask);
 
         let output = utils::draw_matches_knn(&img1, &kps1, &img2, &kps2, &matches, &matches_mask)?;
         match &self.output {
@@ -65,6 +127,66 @@ impl SubCommandExtend for MatchCommand {
     }
 }
 
+impl MatchCommand {
+    /// 对通过比率测试的匹配做几何一致性验证，返回与 `ratio_matches` 等长的内点掩码
+    ///
+    /// 匹配数量不足以估计所选模型时返回 `None`
+    fn geometric_verify(
+        &self,
+        kps1: &Vector<KeyPoint>,
+        kps2: &Vector<KeyPoint>,
+        ratio_matches: &[DMatch],
+    ) -> Result<Option<Vec<u8>>> {
+        let min_points = match self.model {
+            VerifyModel::Homography => 4,
+            VerifyModel::Fundamental => 8,
+            VerifyModel::Affine => 3,
+        };
+        if ratio_matches.len() < min_points {
+            return Ok(None);
+        }
+
+        let mut src = Vector::<Point2f>::new();
+        let mut dst = Vector::<Point2f>::new();
+        for m in ratio_matches {
+            src.push(kps1.get(m.query_idx as usize)?.pt()?);
+            dst.push(kps2.get(m.train_idx as usize)?.pt()?);
+        }
+
+        let mut mask = Mat::default();
+        match self.model {
+            VerifyModel::Homography => {
+                calib3d::find_homography(&src, &dst, &mut mask, calib3d::RANSAC, self.ransac_threshold)?;
+            }
+            VerifyModel::Fundamental => {
+                calib3d::find_fundamental_mat(
+                    &src,
+                    &dst,
+                    calib3d::FM_RANSAC,
+                    self.ransac_threshold,
+                    0.99,
+                    1000,
+                    &mut mask,
+                )?;
+            }
+            VerifyModel::Affine => {
+                calib3d::estimate_affine_2d(
+                    &src,
+                    &dst,
+                    &mut mask,
+                    calib3d::RANSAC,
+                    self.ransac_threshold,
+                    2000,
+                    0.99,
+                    10,
+                )?;
+            }
+        }
+
+        Ok(Some(mask.data_bytes()?.to_vec()))
+    }
+}
+
 fn default_flann_matcher() -> features2d::FlannBasedMatcher {
     let index_params = Ptr::new(flann::IndexParams::from(
         flann::LshIndexParams::new(6, 12, 1).expect("failed to build LshIndexParams"),
@@ -1,16 +1,30 @@
 mod add;
+mod backup;
+mod bench;
 mod build;
 mod clean;
-mod search;
+mod compact;
+mod r#match;
+mod query;
+mod scrub;
+pub mod search;
 pub mod server;
 mod train;
+mod update_db;
 
 pub use add::*;
+pub use backup::*;
+pub use bench::*;
 pub use build::*;
 pub use clean::*;
+pub use compact::*;
+pub use r#match::*;
+pub use query::*;
+pub use scrub::*;
 pub use search::*;
 pub use server::*;
 pub use train::*;
+pub use update_db::*;
 
 use crate::config::Opts;
 
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use clap::Parser;
+use futures::stream::{self, StreamExt};
+use log::{info, warn};
+use reqwest::StatusCode;
+use tokio::time::sleep;
+
+use crate::cli::SubCommandExtend;
+use crate::cli::search::{OutputFormat, print_search_result};
+use crate::config::{Opts, OrbOptions, SearchOptions};
+use crate::server::SearchResponse;
+
+#[derive(Parser, Debug, Clone)]
+pub struct QueryCommand {
+    #[command(flatten)]
+    pub orb: OrbOptions,
+    #[command(flatten)]
+    pub search: SearchOptions,
+    /// 远程 imsearch 服务器地址，例如 http://127.0.0.1:8000
+    #[arg(long, value_name = "URL")]
+    pub server: String,
+    /// 鉴权 token
+    #[arg(long, default_value_t = String::new())]
+    pub token: String,
+    /// 失败重试次数
+    #[arg(long, default_value_t = 3)]
+    pub retry: usize,
+    /// 批量提交时的并发数量
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+    /// 批量提交后不等待每张图片的结果（fire-and-forget）
+    #[arg(long)]
+    pub no_wait: bool,
+    /// 被搜索的图片路径，支持传入多张
+    #[arg(required = true)]
+    pub images: Vec<String>,
+    /// 输出格式
+    #[arg(long, value_name = "FORMAT", default_value = "table")]
+    pub output_format: OutputFormat,
+}
+
+impl SubCommandExtend for QueryCommand {
+    async fn run(&self, _opts: &Opts) -> Result<()> {
+        let client = Client::new(&self.server, &self.token, self.retry)?;
+
+        if self.no_wait {
+            client.search_fire_and_forget(&self.images, &self.orb, &self.search, self.concurrency);
+            info!("已提交 {} 张图片，不等待结果返回", self.images.len());
+            return Ok(());
+        }
+
+        for image in &self.images {
+            let result = client.search_and_confirm(image, &self.orb, &self.search).await?;
+            print_search_result(image, &result, self.output_format)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// imsearch HTTP 服务的客户端
+///
+/// 提供两种提交方式：
+/// - [`Client::search_and_confirm`]：同步等待结果，遇到 5xx 或连接错误时自动退避重试
+/// - [`Client::search_fire_and_forget`]：批量提交，不等待每张图片的返回结果
+pub struct Client {
+    http: reqwest::Client,
+    server: String,
+    token: String,
+    retry: usize,
+}
+
+impl Client {
+    pub fn new(server: &str, token: &str, retry: usize) -> Result<Self> {
+        let http = reqwest::Client::builder().timeout(Duration::from_secs(60)).build()?;
+        Ok(Self { http, server: server.trim_end_matches('/').to_string(), token: token.to_string(), retry })
+    }
+
+    /// 提交一张图片并等待结果，遇到临时性错误（5xx、连接失败）时按指数退避重试
+    pub async fn search_and_confirm(
+        &self,
+        image: &str,
+        orb: &OrbOptions,
+        search: &SearchOptions,
+    ) -> Result<Vec<(f32, String)>> {
+        let mut backoff = Duration::from_millis(500);
+
+        for attempt in 0..=self.retry {
+            match self.try_search(image, orb, search).await {
+                Ok(resp) => return Ok(resp.result),
+                Err(e) if attempt < self.retry && is_transient(&e) => {
+                    warn!("搜索 {image} 失败（第 {} 次重试前）: {e}", attempt + 1);
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!("retry loop always returns")
+    }
+
+    /// 批量提交图片，不等待每张图片的结果，适合大批量导入场景
+    pub fn search_fire_and_forget(
+        &self,
+        images: &[String],
+        orb: &OrbOptions,
+        search: &SearchOptions,
+        concurrency: usize,
+    ) {
+        let http = self.http.clone();
+        let server = self.server.clone();
+        let token = self.token.clone();
+        let orb = orb.clone();
+        let search = search.clone();
+        let images = images.to_vec();
+
+        tokio::spawn(async move {
+            let client = Client { http, server, token, retry: 0 };
+            stream::iter(images)
+                .for_each_concurrent(concurrency, |image| {
+                    let client = &client;
+                    let orb = &orb;
+                    let search = &search;
+                    async move {
+                        if let Err(e) = client.try_search(&image, orb, search).await {
+                            warn!("提交 {image} 失败: {e}");
+                        }
+                    }
+                })
+                .await;
+        });
+    }
+
+    async fn try_search(
+        &self,
+        image: &str,
+        orb: &OrbOptions,
+        search: &SearchOptions,
+    ) -> Result<SearchResponse> {
+        let data = tokio::fs::read(image).await?;
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(image.to_string())
+            .mime_str("application/octet-stream")?;
+        let form = reqwest::multipart::Form::new()
+            .part("file", part)
+            .text("orb_nfeatures", orb.orb_nfeatures.to_string())
+            .text("orb_scale_factor", orb.orb_scale_factor.to_string())
+            .text("nprobe", search.nprobe.to_string());
+
+        let resp = self
+            .http
+            .post(format!("{}/search", self.server))
+            .bearer_auth(&self.token)
+            .multipart(form)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if let Err(e) = resp.error_for_status_ref() {
+            // 保留底层的 reqwest::Error，is_transient 才能按状态码判断是否为 5xx
+            let body = resp.text().await?;
+            return Err(anyhow!(e).context(format!("服务器返回错误状态 {status}: {body}")));
+        }
+
+        Ok(resp.json::<SearchResponse>().await?)
+    }
+}
+
+/// 判断错误是否是值得重试的临时性错误：连接失败或 5xx 状态码
+fn is_transient(err: &anyhow::Error) -> bool {
+    if let Some(status) = err.downcast_ref::<reqwest::Error>().and_then(|e| e.status()) {
+        return status.is_server_error();
+    }
+    err.to_string().contains(StatusCode::INTERNAL_SERVER_ERROR.as_str()) || err.is::<reqwest::Error>()
+}
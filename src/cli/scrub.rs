@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::Result;
+use clap::Parser;
+use log::{info, warn};
+
+use crate::cli::SubCommandExtend;
+use crate::invlists::{InvertedLists, InvertedListsReader, InvertedListsWriter, LmdbInvertedLists};
+use crate::Opts;
+
+#[derive(Parser, Debug, Clone)]
+pub struct ScrubCommand {
+    /// 待扫描的倒排列表目录（LMDB 环境）
+    pub path: PathBuf,
+    /// 倒排表数量，必须与创建该倒排列表时使用的值一致
+    #[arg(long)]
+    pub nlist: u32,
+    /// 每个向量的编码字节数，必须与创建该倒排列表时使用的值一致
+    #[arg(long)]
+    pub code_size: u32,
+    /// 镇定系数：每处理完一个倒排表后，休眠 `耗时 * tranquility`，避免占满磁盘 IO
+    #[arg(long, default_value_t = 1.0)]
+    pub tranquility: f64,
+}
+
+/// 检查一个倒排表的 ids/codes 长度是否与记录的元素数量一致
+///
+/// 返回 `None` 表示一致，不需要修复；返回 `Some(len)` 表示不一致，`len` 为截断后能保证
+/// ids 和 codes 都完整对应的最大长度
+fn check_consistency(ids_len: usize, codes_len: usize, code_size: usize, list_len: usize) -> Option<usize> {
+    if ids_len == list_len && codes_len == list_len * code_size {
+        return None;
+    }
+    Some(ids_len.min(codes_len / code_size))
+}
+
+impl SubCommandExtend for ScrubCommand {
+    async fn run(&self, _opts: &Opts) -> Result<()> {
+        let mut invlists = LmdbInvertedLists::new(&self.path, self.nlist, self.code_size)?;
+        let cursor_path = self.path.join("scrub.cursor");
+
+        let start = match std::fs::read_to_string(&cursor_path) {
+            Ok(cursor) => cursor.trim().parse::<u32>().unwrap_or(0) + 1,
+            Err(_) => 0,
+        };
+        if start > 0 {
+            info!("从上次中断处继续扫描，起始 list_no = {start}");
+        }
+
+        let mut corrupted_lists = 0u64;
+        let mut orphaned_ids = 0u64;
+
+        for list_no in start..self.nlist {
+            let start_time = Instant::now();
+
+            let (consistent_len, ids_len, codes_len, list_len) = {
+                let reader = invlists.reader().await?;
+                let list_len = reader.list_len(list_no);
+                let (ids, codes) = reader.get_list(list_no).await;
+                let consistent_len =
+                    check_consistency(ids.len(), codes.len(), self.code_size as usize, list_len);
+                (consistent_len, ids.len(), codes.len(), list_len)
+            };
+
+            if let Some(consistent_len) = consistent_len {
+                corrupted_lists += 1;
+                orphaned_ids += list_len.saturating_sub(consistent_len) as u64;
+                warn!(
+                    "倒排表 {list_no} 数据不一致：ids 元素数={ids_len}，codes 字节数={codes_len}，\
+                     记录长度={list_len}，将截断为一致长度 {consistent_len}"
+                );
+
+                let mut writer = invlists.writer().await?;
+                writer.truncate(list_no, consistent_len).await;
+                writer.commit()?;
+            }
+
+            std::fs::write(&cursor_path, list_no.to_string())?;
+
+            let elapsed = start_time.elapsed();
+            if self.tranquility > 0.0 {
+                tokio::time::sleep(elapsed.mul_f64(self.tranquility)).await;
+            }
+        }
+
+        std::fs::remove_file(&cursor_path).ok();
+
+        info!("扫描完成：共检查 {} 个倒排表，发现 {corrupted_lists} 个数据不一致，清理了 {orphaned_ids} 个孤立 ID", self.nlist);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_consistency() {
+        // ids/codes 长度与记录的元素数量一致，不需要修复
+        assert_eq!(check_consistency(3, 3 * 16, 16, 3), None);
+
+        // codes 写入过程中被截断，只有一部分 codes 能配上 ids
+        assert_eq!(check_consistency(3, 2 * 16, 16, 3), Some(2));
+
+        // ids 比 codes 少，以 ids 的长度为准
+        assert_eq!(check_consistency(2, 3 * 16, 16, 3), Some(2));
+    }
+}
@@ -1,16 +1,22 @@
 use std::convert::Infallible;
+use std::io::{self, Write};
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
+use regex::Regex;
+use serde::Serialize;
 use tokio::task::block_in_place;
+use walkdir::WalkDir;
 
 use crate::IMDBBuilder;
 use crate::cli::SubCommandExtend;
 use crate::config::{Opts, OrbOptions, SearchOptions};
-use crate::ivf::IvfHnsw;
+use crate::index::IndexManager;
 use crate::orb::ORBDetector;
+use crate::rerank;
 
 #[derive(Parser, Debug, Clone)]
 pub struct SearchCommand {
@@ -18,53 +24,198 @@ pub struct SearchCommand {
     pub orb: OrbOptions,
     #[command(flatten)]
     pub search: SearchOptions,
-    /// 被搜索的图片路径
+    /// 被搜索的图片路径，也可以是包含图片的目录，或配合 `--manifest` 传入图片路径清单文件
     pub image: String,
+    /// 将 `image` 当作清单文件读取，按行给出待搜索的图片路径
+    #[arg(long)]
+    pub manifest: bool,
+    /// `image` 为目录时，扫描的文件后缀名，多个后缀用逗号分隔
+    #[arg(long, default_value = "jpg,png,webp")]
+    pub suffix: String,
     /// 输出格式
     #[arg(long, value_name = "FORMAT", default_value = "table")]
     pub output_format: OutputFormat,
-    /// 默认索引文件名
-    #[arg(short = 'I', long, value_name = "NAME", default_value = "index")]
-    pub index_name: String,
+    /// 参与搜索的索引名称，可指定多次（`-I a -I b`）或用逗号分隔传入多个
+    ///
+    /// 指定多个索引时，会在查询时通过 HStack 联合各索引的倒排列表，而不需要事先在磁盘上合并，
+    /// 这样各分片可以独立重建/清理；此时每条结果前会加上命中的索引名作为前缀
+    #[arg(short = 'I', long, value_name = "NAME", default_value = "index", value_delimiter = ',')]
+    pub index_name: Vec<String>,
 }
 
 impl SubCommandExtend for SearchCommand {
     async fn run(&self, opts: &Opts) -> anyhow::Result<()> {
+        let images = resolve_images(&self.image, &self.suffix, self.manifest)?;
+
+        self.orb.ensure_extractor_supported()?;
         let mut orb = ORBDetector::create(self.orb.clone());
-        let (_, _, des) = block_in_place(|| orb.detect_file(&self.image))?;
 
         let db = IMDBBuilder::new(opts.conf_dir.clone())
             .score_type(self.search.score_type)
+            .criteria(self.search.criteria.clone())
             .open()
             .await?;
 
-        let index = Arc::new(IvfHnsw::open_disk(&opts.conf_dir)?);
+        let manager = IndexManager::new(opts.conf_dir.clone());
+        let (index, shards) =
+            manager.get_named_indexes(&self.index_name, !self.search.no_mmap, self.search.ondisk);
+        let index = Arc::new(index);
+
+        let SearchOptions { k, distance, count, nprobe, rerank, rerank_top, rerank_min_inliers, .. } =
+            self.search;
+
+        if self.output_format == OutputFormat::Csv {
+            let mut stdout = io::stdout().lock();
+            writeln!(stdout, "query,score,name")?;
+            stdout.flush()?;
+        }
+
+        for image in &images {
+            let (_, kps, des) = block_in_place(|| orb.detect_file(image))?;
+            let mut result = db
+                .search(index.clone(), des.clone(), k, distance, count, nprobe, &shards)
+                .await?;
+
+            if rerank {
+                result = block_in_place(|| {
+                    rerank_candidates(&kps, &des, &mut orb, result, rerank_top, rerank_min_inliers)
+                })?;
+            }
 
-        let SearchOptions { k, distance, count, nprobe, .. } = self.search;
-        let result = db.search(index, des, k, distance, count, nprobe).await?;
+            print_search_result(image, &result, self.output_format)?;
+        }
 
-        print_result(&result, self)
+        Ok(())
     }
 }
 
-fn print_result(result: &[(f32, String)], opts: &SearchCommand) -> Result<()> {
-    match opts.output_format {
+/// 将 `image` 参数解析为待搜索的图片路径列表
+///
+/// - 如果是一个目录，按 `suffix` 过滤后扫描其中所有图片
+/// - 如果指定了 `manifest`，按行读取其中的图片路径
+/// - 否则视为单张图片路径
+fn resolve_images(image: &str, suffix: &str, manifest: bool) -> Result<Vec<String>> {
+    let path = Path::new(image);
+
+    if path.is_dir() {
+        let re_suf = Regex::new(&format!("(?i)({})", suffix.replace(',', "|")))?;
+        let mut images = WalkDir::new(path)
+            .into_iter()
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let path = entry.path();
+                let ext = path.extension()?;
+                re_suf.is_match(&ext.to_string_lossy()).then(|| path.to_string_lossy().into_owned())
+            })
+            .collect::<Vec<_>>();
+        images.sort();
+        Ok(images)
+    } else if manifest {
+        let content = std::fs::read_to_string(path)?;
+        Ok(content.lines().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+    } else {
+        Ok(vec![image.to_string()])
+    }
+}
+
+/// 对 Top-N 候选结果做 RANSAC 单应性验证，使用内点数量重新排序
+///
+/// 由于当前索引没有持久化特征点几何信息，这里直接重新解析候选图片来恢复特征点；内点数量
+/// 低于 `min_inliers` 的候选被认为几何上不一致，直接剔除而不是仅仅排到后面
+fn rerank_candidates(
+    query_kps: &[opencv::core::KeyPoint],
+    query_des: &[[u8; 32]],
+    orb: &mut ORBDetector,
+    candidates: Vec<(f32, String)>,
+    rerank_top: usize,
+    min_inliers: usize,
+) -> anyhow::Result<Vec<(f32, String)>> {
+    let mut head = candidates;
+    let tail = head.split_off(head.len().min(rerank_top));
+
+    let mut reranked = Vec::with_capacity(head.len());
+    let mut unverified = Vec::new();
+    for (score, path) in head {
+        let verified = match orb.detect_file(&path) {
+            Ok((_, cand_kps, cand_des)) => {
+                rerank::geometric_verify(query_kps, query_des, &cand_kps, &cand_des, 0.75, 4.0)?
+            }
+            // 候选图片可能已被移动或删除，此时退化为使用原始投票得分，排在通过验证的候选之后
+            Err(_) => {
+                unverified.push((score, path));
+                continue;
+            }
+        };
+        if verified.inliers < min_inliers {
+            continue;
+        }
+        reranked.push((verified.inliers as f32, score, path));
+    }
+    reranked.sort_unstable_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then(b.1.total_cmp(&a.1)));
+
+    let mut result: Vec<_> =
+        reranked.into_iter().map(|(inliers, _, path)| (inliers, path)).collect();
+    result.extend(unverified);
+    result.extend(tail);
+    Ok(result)
+}
+
+/// NDJSON 格式下的一条搜索结果记录
+#[derive(Serialize)]
+struct SearchRecord<'a> {
+    query: &'a str,
+    score: f32,
+    name: &'a str,
+}
+
+/// 按指定格式打印一张图片的搜索结果，供本地搜索和远程查询共用
+///
+/// NDJSON 和 CSV 格式会为每条记录附带所属的 `query`，并在写入后立即 flush，
+/// 因此批量搜索时可以边搜索边输出，而不需要等待整个任务结束才看到结果
+pub fn print_search_result(query: &str, result: &[(f32, String)], format: OutputFormat) -> Result<()> {
+    match format {
         OutputFormat::Json => {
             println!("{}", serde_json::to_string_pretty(result)?)
         }
         OutputFormat::Table => {
-            for (k, v) in result {
-                println!("{:.2}\t{}", k, v);
+            for (score, name) in result {
+                println!("{:.2}\t{}", score, name);
+            }
+        }
+        OutputFormat::Ndjson => {
+            let mut stdout = io::stdout().lock();
+            for (score, name) in result {
+                let record = SearchRecord { query, score: *score, name };
+                writeln!(stdout, "{}", serde_json::to_string(&record)?)?;
+                stdout.flush()?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut stdout = io::stdout().lock();
+            for (score, name) in result {
+                writeln!(stdout, "{},{:.2},{}", csv_field(query), score, csv_field(name))?;
+                stdout.flush()?;
             }
         }
     }
     Ok(())
 }
 
-#[derive(ValueEnum, Debug, Clone)]
+/// 对 CSV 字段做最基本的转义：包含逗号、引号或换行时用双引号包裹，并转义内部的双引号
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
     Json,
     Table,
+    Ndjson,
+    Csv,
 }
 
 impl FromStr for OutputFormat {
@@ -74,6 +225,8 @@ impl FromStr for OutputFormat {
         match s {
             "json" => Ok(Self::Json),
             "table" => Ok(Self::Table),
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
             _ => unreachable!(),
         }
     }
@@ -31,17 +31,26 @@ pub struct ServerCommand {
     /// prometheus 认证信息，格式为 username:password
     #[arg(long, value_name = "AUTH")]
     pub prometheus_auth: Option<String>,
+    /// 向量 ID -> 图片 ID 查询使用固定容量的 LRU 缓存，而不是一次性把全部映射加载进内存，
+    /// 取值为缓存容量；不填时沿用全量预加载模式
+    #[arg(long, value_name = "N")]
+    pub id_cache_capacity: Option<usize>,
 }
 
 impl SubCommandExtend for ServerCommand {
     async fn run(&self, opts: &Opts) -> anyhow::Result<()> {
-        let db = IMDBBuilder::new(opts.conf_dir.clone())
-            .cache(true)
+        self.orb.ensure_extractor_supported()?;
+
+        let mut builder = IMDBBuilder::new(opts.conf_dir.clone())
             .score_type(self.search.score_type)
-            .open()
-            .await?;
+            .criteria(self.search.criteria.clone());
+        builder = match self.id_cache_capacity {
+            Some(capacity) => builder.cache_lru(capacity),
+            None => builder.cache(true),
+        };
+        let db = builder.open().await?;
 
-        let index = db.get_index(!self.search.no_mmap)?;
+        let index = db.get_index(!self.search.no_mmap, self.search.ondisk)?;
 
         let mut self_clone = self.clone();
         if self_clone.token.is_empty() {
@@ -50,7 +59,10 @@ impl SubCommandExtend for ServerCommand {
         }
 
         // 创建应用状态
-        let state = server::AppState::new(index, db, self_clone);
+        let state = server::AppState::new(index, db, opts.conf_dir.clone(), self_clone);
+
+        // 启动后台任务队列 worker，持久化在数据库中的任务即使服务重启也不会丢失
+        tokio::spawn(server::run_task_worker(state.clone()));
 
         // 创建应用
         let app = server::create_app(state);
@@ -20,6 +20,7 @@ pub struct ShowCommand {
 
 impl SubCommandExtend for ShowCommand {
     async fn run(&self, _opts: &Opts) -> Result<()> {
+        self.orb.ensure_extractor_supported()?;
         let mut orb = ORBDetector::create(self.orb.clone());
         let (image, kps, _) = orb.detect_file(&self.image)?;
         info!("图像大小: {}x{}", image.cols(), image.rows());
@@ -2,10 +2,18 @@ use anyhow::Result;
 use clap::{Parser, ValueEnum};
 
 use crate::cli::SubCommandExtend;
-use crate::ivf::{HnswQuantizer, Quantizer};
+use crate::ivf::{HnswParams, HnswQuantizer, Quantizer, USearchQuantizer, USearchQuantizerOptions};
 use crate::kmodes::*;
 use crate::{IMDBBuilder, Opts};
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub enum QuantizerBackend {
+    /// 基于 faiss 的 HNSW 量化器，检索速度快，内存占用较高
+    Hnsw,
+    /// 基于 usearch 的 HNSW 量化器，支持比特选择压缩，适合内存吃紧的场景
+    Usearch,
+}
+
 #[derive(Parser, Debug, Clone)]
 pub struct TrainCommand {
     /// 聚类中心点数量
@@ -23,6 +31,21 @@ pub struct TrainCommand {
     /// 禁止使用二级聚类
     #[arg(short, long)]
     pub no_2level: bool,
+    /// 量化器后端
+    #[arg(long, value_enum, default_value_t = QuantizerBackend::Hnsw)]
+    pub backend: QuantizerBackend,
+    /// HNSW 每个节点的最大出边数
+    #[arg(long, default_value_t = HnswParams::default().m)]
+    pub hnsw_m: usize,
+    /// HNSW 构建时的候选集大小，越大图质量越高，构建耗时越长
+    #[arg(long, default_value_t = HnswParams::default().ef_construction)]
+    pub hnsw_ef_construction: usize,
+    /// HNSW 搜索时的候选集大小，越大召回率越高，查询延迟越高
+    #[arg(long, default_value_t = HnswParams::default().ef_search)]
+    pub hnsw_ef_search: usize,
+    /// usearch 量化器压缩后保留的比特数，仅 `--backend usearch` 有效，不指定时不压缩
+    #[arg(long)]
+    pub usearch_compressed_bits: Option<usize>,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
@@ -47,8 +70,27 @@ impl SubCommandExtend for TrainCommand {
             kmodes_binary::<32>(&data, self.centers, self.max_iter, init_method).centroids
         };
 
-        let quantizer = HnswQuantizer::init(&centroids)?;
-        quantizer.save(&opts.conf_dir.join("quantizer.bin"))?;
+        let params = HnswParams {
+            m: self.hnsw_m,
+            ef_construction: self.hnsw_ef_construction,
+            ef_search: self.hnsw_ef_search,
+        };
+        match self.backend {
+            QuantizerBackend::Hnsw => {
+                let quantizer = HnswQuantizer::init_with_params(&centroids, params)?;
+                quantizer.save(opts.conf_dir.quantizer())?;
+            }
+            QuantizerBackend::Usearch => {
+                let options = USearchQuantizerOptions {
+                    connectivity: params.m,
+                    expansion_add: params.ef_construction,
+                    expansion_search: params.ef_search,
+                    compressed_bits: self.usearch_compressed_bits,
+                };
+                let quantizer = USearchQuantizer::init_with_options(&centroids, options)?;
+                quantizer.save(opts.conf_dir.quantizer())?;
+            }
+        }
         Ok(())
     }
 }
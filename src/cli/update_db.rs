@@ -5,13 +5,24 @@ use log::{error, info};
 
 use super::SubCommandExtend;
 use crate::config::Opts;
-use crate::db::init_db;
+use crate::db::{crud, init_db};
+
+/// 图片信息迁移阶段的 `migration_progress` 水位线名称
+const PHASE_IMAGE: &str = "image";
+/// 特征点统计阶段的 `migration_progress` 水位线名称
+const PHASE_VECTOR_STATS: &str = "vector_stats";
 
 #[derive(Parser, Debug, Clone)]
-pub struct UpdateDBCommand {}
+pub struct UpdateDBCommand {
+    /// 每迁移多少行提交一次事务，数值越小中断后重跑浪费的工作越少，但提交开销也越高
+    #[arg(short, long, value_name = "SIZE", default_value_t = 10000)]
+    pub batch_size: usize,
+}
 
 impl SubCommandExtend for UpdateDBCommand {
     async fn run(&self, opts: &Opts) -> Result<()> {
+        anyhow::ensure!(self.batch_size > 0, "batch_size 必须大于 0");
+
         let rocks = crate::rocks::ImageDB::open(&opts.conf_dir, true)?;
         if !opts.conf_dir.path().exists() {
             std::fs::create_dir_all(opts.conf_dir.path())?;
@@ -23,51 +34,105 @@ impl SubCommandExtend for UpdateDBCommand {
             .unwrap()
             .progress_chars("#>-");
 
-        let mut tx = db.begin().await?;
-
         info!("正在迁移图片信息");
+        let image_progress = crud::get_migration_progress(&db, PHASE_IMAGE).await?;
+        if image_progress > 0 {
+            info!("检测到上次中断，跳过已迁移的 {} 张图片", image_progress);
+        }
         let pb = ProgressBar::new(rocks.total_images()).with_style(pb_style.clone());
-        for image in rocks.images().progress_with(pb.clone()) {
+        pb.set_position(image_progress);
+        let mut processed = image_progress;
+        let mut batch = db.begin().await?;
+        for image in rocks.images().skip(image_progress as usize).progress_with(pb.clone()) {
             let (id, hash, path) = image?;
             // NOTE: 因为 rocks 中的 id 是从 0 开始的，而 sql 中的 id 是从 1 开始的，所以需要加 1
             let id = id + 1;
             // 理论上这个插入应该不会失败，但测试中确实存在 UNIQUE constraint failed 的情况
             if let Err(e) =
                 sqlx::query!("INSERT INTO image (id, hash, path) VALUES (?, ?, ?)", id, hash, path)
-                    .execute(&mut *tx)
+                    .execute(&mut *batch)
                     .await
             {
                 error!("无法迁移 (id, hash, path) = ({}, {:02x?}, {})", id, hash, path);
                 error!("错误: {}", e);
             }
-        }
 
-        info!("正在统计特征点信息");
-        let pb = ProgressBar::new(rocks.total_features()).with_style(pb_style.clone());
-        let mut map = vec![0u16; rocks.total_images() as usize];
-        for features in rocks.features().progress_with(pb) {
-            let (_, image_id) = features?;
-            map[image_id as usize] += 1;
+            processed += 1;
+            if processed as usize % self.batch_size == 0 {
+                crud::set_migration_progress(&mut *batch, PHASE_IMAGE, processed).await?;
+                batch.commit().await?;
+                batch = db.begin().await?;
+            }
         }
+        crud::set_migration_progress(&mut *batch, PHASE_IMAGE, processed).await?;
+        batch.commit().await?;
 
         info!("正在迁移特征点信息");
-        let mut total_vector_count = 0;
-        let pb = ProgressBar::new(rocks.total_images()).with_style(pb_style.clone());
-        for i in (0..map.len()).progress_with(pb) {
-            let vector_count = map[i as usize] as i64;
-            total_vector_count += vector_count;
-            let i = (i + 1) as i64;
-            sqlx::query!(
-                "INSERT INTO vector_stats (id, vector_count, total_vector_count, indexed) VALUES (?, ?, ?, 1)",
-                i,
-                vector_count,
-                total_vector_count
-            )
-            .execute(&mut *tx)
-            .await?;
+        // 按 image_id 对 rocks.features() 做流式聚合，而不是把每张图片的计数都物化进一个
+        // `vec![0u16; total_images]`：大库里这个数组本身就可能占用几十 MB 内存，而且 u16
+        // 在单张图片特征点数超过 65535 时会静默溢出；rocks.features() 按 image_id 递增顺序
+        // 产出（同一张图片的特征点是连续写入的），因此只要维护"当前图片"的计数即可
+        let vector_stats_progress = crud::get_migration_progress(&db, PHASE_VECTOR_STATS).await?;
+        if vector_stats_progress > 0 {
+            info!("检测到上次中断，跳过已统计的 {} 张图片", vector_stats_progress);
         }
+        let pb = ProgressBar::new(rocks.total_features()).with_style(pb_style.clone());
+        let mut batch = db.begin().await?;
+        let mut processed = 0u64;
+        // 即使是已经落库过的图片，也要累加进 total_vector_count，否则后面图片的累计值会偏小
+        let mut total_vector_count = 0i64;
+        let mut cur_image_id: Option<i32> = None;
+        let mut cur_vector_count = 0i64;
+        for (image_id, _) in rocks.features().progress_with(pb) {
+            if let Some(prev_id) = cur_image_id {
+                if image_id != prev_id {
+                    total_vector_count += cur_vector_count;
+                    if prev_id as u64 >= vector_stats_progress {
+                        let id = (prev_id + 1) as i64;
+                        sqlx::query!(
+                            "INSERT INTO vector_stats (id, vector_count, total_vector_count, indexed) VALUES (?, ?, ?, 1)",
+                            id,
+                            cur_vector_count,
+                            total_vector_count
+                        )
+                        .execute(&mut *batch)
+                        .await?;
 
-        tx.commit().await?;
+                        processed += 1;
+                        if processed as usize % self.batch_size == 0 {
+                            crud::set_migration_progress(
+                                &mut *batch,
+                                PHASE_VECTOR_STATS,
+                                prev_id as u64 + 1,
+                            )
+                            .await?;
+                            batch.commit().await?;
+                            batch = db.begin().await?;
+                        }
+                    }
+                    cur_vector_count = 0;
+                }
+            }
+            cur_image_id = Some(image_id);
+            cur_vector_count += 1;
+        }
+        if let Some(last_id) = cur_image_id {
+            total_vector_count += cur_vector_count;
+            if last_id as u64 >= vector_stats_progress {
+                let id = (last_id + 1) as i64;
+                sqlx::query!(
+                    "INSERT INTO vector_stats (id, vector_count, total_vector_count, indexed) VALUES (?, ?, ?, 1)",
+                    id,
+                    cur_vector_count,
+                    total_vector_count
+                )
+                .execute(&mut *batch)
+                .await?;
+                crud::set_migration_progress(&mut *batch, PHASE_VECTOR_STATS, last_id as u64 + 1)
+                    .await?;
+            }
+        }
+        batch.commit().await?;
 
         info!("迁移完成");
 
@@ -50,6 +50,35 @@ pub struct OrbOptions {
     /// 最大特征点数量
     #[arg(long, default_value_t = 1000)]
     pub max_features: u32,
+    /// ORB 重叠区域检测范围的左边界（列号），用于全景图/双目拼接等极端长宽比图片，限制
+    /// 特征点只在该列范围内检测（列号小于该值的区域不检测），与 `orb_lapping_right`
+    /// 配合使用；两者均为 0 时表示不启用，保持整张图片检测
+    #[arg(long, value_name = "X", default_value_t = 0)]
+    pub orb_lapping_left: i32,
+    /// ORB 重叠区域检测范围的右边界（列号），列号大于该值的区域不检测；为 0 表示不启用
+    #[arg(long, value_name = "X", default_value_t = 0)]
+    pub orb_lapping_right: i32,
+    /// 特征提取后端
+    ///
+    /// `sift` 目前只接入了 [`crate::features::FeatureExtractor`] 这层独立的特征提取接口，
+    /// 尚未接入索引/搜索流程：后者仍然固定假设 32 字节二进制描述符与 Hamming 距离，
+    /// 要支持 SIFT 的浮点描述符还需要先给 `faiss`/`ivf` 加上 L2 浮点索引，这里先不做
+    #[arg(long, value_enum, default_value_t = crate::features::FeatureExtractorKind::Orb)]
+    pub extractor: crate::features::FeatureExtractorKind,
+}
+
+impl OrbOptions {
+    /// 索引/搜索流程仍然固定假设 ORB 的 32 字节二进制描述符与 Hamming 距离，选择其它提取
+    /// 后端时直接报错而不是静默退化为 ORB，避免用户以为自己用的是 `--extractor sift`
+    /// 得到的结果
+    pub fn ensure_extractor_supported(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.extractor == crate::features::FeatureExtractorKind::Orb,
+            "特征提取后端 {:?} 尚未接入索引/搜索流程，暂不可用",
+            self.extractor
+        );
+        Ok(())
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -57,6 +86,10 @@ pub struct SearchOptions {
     /// 不使用 mmap 模式加载索引，而是一次性全部加载到内存
     #[arg(long)]
     pub no_mmap: bool,
+    /// 以磁盘倒排列表模式打开索引，常驻内存的部分只有索引骨架，倒排列表按需从磁盘读取，
+    /// 适合索引大小超出内存容量的场景；如果尚未合并为磁盘倒排列表，打开前会自动合并一次
+    #[arg(long)]
+    pub ondisk: bool,
     /// 两个相似向量的允许的最大距离，范围从 0 到 255
     #[arg(long, value_name = "N", default_value_t = 64, value_parser = clap::value_parser!(u32).range(0..=255))]
     pub distance: u32,
@@ -75,6 +108,35 @@ pub struct SearchOptions {
     /// 评分方式
     #[arg(long, value_enum, default_value_t = ScoreType::Wilson)]
     pub score_type: ScoreType,
+    /// 多阶段排名流水线，按顺序排列的评分规则，多个规则用逗号分隔；排在前面的规则决定
+    /// 主要顺序，后面的规则只在前面的规则打平分时起决胜作用；不指定时退化为 `score_type`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub criteria: Vec<crate::ranking::CriterionKind>,
+    /// 对结果进行几何重排序，使用 RANSAC 单应性验证过滤空间上不一致的候选图片
+    #[arg(long)]
+    pub rerank: bool,
+    /// 参与几何重排序的候选图片数量
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub rerank_top: usize,
+    /// RANSAC 单应性验证后的最小内点数量，低于此值的候选会被直接剔除而非仅仅降低排名
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    pub rerank_min_inliers: usize,
+    /// 使用 MinHash 视觉词签名对候选图片做粗筛，只对筛选后的子集执行完整的 KNN 打分
+    #[arg(long)]
+    pub minhash: bool,
+    /// MinHash 签名长度（哈希函数数量），仅在首次生成签名种子时生效，之后固定不变
+    #[arg(long, value_name = "H", default_value_t = 128)]
+    pub minhash_h: usize,
+    /// MinHash 粗筛保留的候选图片数量
+    #[arg(long, value_name = "N", default_value_t = 5000)]
+    pub minhash_top_n: usize,
+    /// 使用 Lowe's ratio test 代替固定的 `distance` 阈值过滤匹配：只有最近邻距离小于
+    /// `ratio * 次近邻距离` 的查询描述符才计为一次命中
+    #[arg(long)]
+    pub ratio_test: bool,
+    /// ratio test 使用的比率，越小越严格
+    #[arg(long, value_name = "RATIO", default_value_t = 0.7)]
+    pub ratio: f32,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -93,6 +155,8 @@ pub enum SubCommand {
     Add(AddCommand),
     /// 从数据库中搜索图片
     Search(SearchCommand),
+    /// 向远程 imsearch 服务器提交搜索请求
+    Query(QueryCommand),
     /// 启动 HTTP 搜索服务
     Server(ServerCommand),
     /// 使用已添加的特征点构建索引
@@ -101,6 +165,20 @@ pub enum SubCommand {
     Clean(CleanCommand),
     /// 训练索引
     Train(TrainCommand),
+    /// 运行评测工作负载，计算 recall@k、mAP 等指标
+    Bench(BenchCommand),
+    /// 对比两份评测报告，打印各项指标的差值
+    BenchCompare(BenchCompareCommand),
+    /// 扫描倒排列表，检查并修复数据不一致
+    Scrub(ScrubCommand),
+    /// 对运行中的数据库做一次在线快照，用于复制或时间点恢复
+    Backup(BackupCommand),
+    /// 转换 ivf 倒排列表的存储格式（mmap/PQ/varint），或合并子索引的倒排列表
+    Compact(CompactCommand),
+    /// 将 rocksdb 中的图片/特征点信息迁移到 sqlite 数据库
+    UpdateDb(UpdateDBCommand),
+    /// 对比两张图片的特征点，用于调试特征提取/几何验证参数
+    Match(MatchCommand),
 }
 
 #[derive(Debug, Clone)]
@@ -128,6 +206,11 @@ impl ConfDir {
         self.path.join("index.phash")
     }
 
+    /// 返回布隆过滤器索引文件路径
+    pub fn index_bloom(&self) -> PathBuf {
+        self.path.join("index.bloom")
+    }
+
     /// 返回索引文件的路径
     pub fn index(&self) -> PathBuf {
         self.path.join(&self.default)
@@ -171,6 +254,21 @@ impl ConfDir {
     pub fn ondisk_ivf_tmp(&self) -> PathBuf {
         self.path.join("index.ivfdata.tmp")
     }
+
+    /// 返回 ivf 倒排列表文件的路径
+    pub fn invlists(&self) -> PathBuf {
+        self.path.join("invlists.bin")
+    }
+
+    /// 返回训练好的量化器文件路径
+    pub fn quantizer(&self) -> PathBuf {
+        self.path.join("quantizer.bin")
+    }
+
+    /// 返回 MinHash 哈希函数种子文件路径
+    pub fn minhash_seeds(&self) -> PathBuf {
+        self.path.join("minhash.seeds")
+    }
 }
 
 impl FromStr for ConfDir {
@@ -1,6 +1,7 @@
 use sqlx::{Executor, Result, Sqlite, SqlitePool};
 
-use super::VectorIdxRecord;
+use super::{MinhashRecord, TagPredicate, TagRecord, TaskRecord, TombstoneRecord, VectorIdxRecord};
+use crate::utils::ImageHash;
 
 /// 添加图片记录
 pub async fn add_image<'c, E>(executor: E, hash: &[u8], path: &str) -> Result<i64>
@@ -22,18 +23,93 @@ where
     Ok(result.id)
 }
 
-/// 检查图片哈希是否存在
-pub async fn check_image_hash(executor: &SqlitePool, hash: &[u8]) -> Result<bool> {
+/// 检查图片哈希是否存在，存在时返回图片 ID
+pub async fn check_image_hash(executor: &SqlitePool, hash: &[u8]) -> Result<Option<i64>> {
     let result = sqlx::query!(
         r#"
-        SELECT COUNT(*) as count FROM image WHERE hash = ?
+        SELECT id FROM image WHERE hash = ?
         "#,
         hash
     )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(result.map(|row| row.id))
+}
+
+/// 获取所有图片哈希，用于重建布隆过滤器
+pub async fn get_all_image_hash(executor: &SqlitePool) -> Result<Vec<Vec<u8>>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT hash FROM image
+        "#,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| row.hash).collect())
+}
+
+/// 猜测当前库使用的哈希算法，取自 `hash_config` 表中建库时记录的算法
+pub async fn guess_hash(executor: &SqlitePool) -> anyhow::Result<ImageHash> {
+    let row = sqlx::query!(
+        r#"
+        SELECT algorithm FROM hash_config WHERE id = 0
+        "#,
+    )
     .fetch_one(executor)
     .await?;
 
-    Ok(result.count > 0)
+    row.algorithm.parse()
+}
+
+/// 记录当前库使用的哈希算法，仅在首次建库（`hash_config` 表为空）时写入
+pub async fn set_hash_config(executor: &SqlitePool, hash: ImageHash) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO hash_config (id, algorithm) VALUES (0, ?)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+        hash.as_str(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// 获取某个迁移阶段的进度水位线（各阶段的含义见 `migration_progress` 表的注释），未记录过时返回 0
+pub async fn get_migration_progress(executor: &SqlitePool, phase: &str) -> Result<u64> {
+    let row = sqlx::query!(
+        r#"
+        SELECT processed AS "processed: i64" FROM migration_progress WHERE phase = ?
+        "#,
+        phase
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row.map(|row| row.processed as u64).unwrap_or(0))
+}
+
+/// 更新某个迁移阶段的进度水位线，用于支持 `imsearch update-db` 中断后续跑
+pub async fn set_migration_progress<'c, E>(executor: E, phase: &str, processed: u64) -> Result<()>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let processed = processed as i64;
+    sqlx::query!(
+        r#"
+        INSERT INTO migration_progress (phase, processed) VALUES (?, ?)
+        ON CONFLICT (phase) DO UPDATE SET processed = excluded.processed
+        "#,
+        phase,
+        processed,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
 }
 
 pub async fn get_image_path(executor: &SqlitePool, id: i64) -> Result<String> {
@@ -225,6 +301,175 @@ pub async fn get_count(executor: &SqlitePool) -> Result<(i64, i64)> {
     Ok((result.id, result.total_vector_count))
 }
 
+/// 删除一张图片及其所有特征向量，并记录被回收的全局特征 ID 区间
+///
+/// NOTE: 为了保持 `total_vector_count` 单调递增这一前提，这里不直接重排剩余记录，
+/// 而是把空洞记录为墓碑，留到 [`compact_vector_stats`] 时统一重建
+pub async fn delete_image(executor: &SqlitePool, id: i64) -> Result<()> {
+    let mut tx = executor.begin().await?;
+
+    let stats = sqlx::query!(
+        r#"
+        SELECT vector_count, total_vector_count FROM vector_stats WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // 这张图片的特征 ID 为 `(total_vector_count - vector_count, total_vector_count]`，
+    // 换算成墓碑使用的左闭右开区间 `[start_id, end_id)`
+    let end_id = stats.total_vector_count + 1;
+    let start_id = end_id - stats.vector_count;
+
+    sqlx::query!(r#"DELETE FROM vector WHERE id = ?"#, id).execute(&mut *tx).await?;
+    sqlx::query!(r#"DELETE FROM vector_stats WHERE id = ?"#, id).execute(&mut *tx).await?;
+    sqlx::query!(r#"DELETE FROM image WHERE id = ?"#, id).execute(&mut *tx).await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO vector_tombstone (start_id, end_id) VALUES (?, ?)
+        "#,
+        start_id,
+        end_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 获取所有墓碑记录
+pub async fn get_tombstones(executor: &SqlitePool) -> Result<Vec<TombstoneRecord>> {
+    sqlx::query_as!(
+        TombstoneRecord,
+        r#"
+        SELECT start_id, end_id FROM vector_tombstone ORDER BY start_id ASC
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// 清空墓碑记录，在 `compact` 完成对倒排列表的重写后调用
+pub async fn clear_tombstones(executor: &SqlitePool) -> Result<()> {
+    sqlx::query!(r#"DELETE FROM vector_tombstone"#).execute(executor).await?;
+    Ok(())
+}
+
+/// 重建连续的 `total_vector_count`
+///
+/// `delete_image` 删除的只是行，并不会收缩后续记录的 `total_vector_count`，
+/// 所以压缩时需要按 `id` 顺序重新累加 `vector_count`
+pub async fn compact_vector_stats(executor: &SqlitePool) -> Result<()> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, vector_count FROM vector_stats ORDER BY id ASC
+        "#,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut tx = executor.begin().await?;
+    let mut total = 0i64;
+    for row in rows {
+        total += row.vector_count;
+        sqlx::query!(
+            r#"
+            UPDATE vector_stats SET total_vector_count = ? WHERE id = ?
+            "#,
+            total,
+            row.id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// 批量添加/更新图片标签，同一 `(image_id, key)` 再次写入时覆盖原值
+pub async fn add_tags(executor: &SqlitePool, image_id: i64, tags: &[(String, String)]) -> Result<()> {
+    let mut tx = executor.begin().await?;
+    for (key, value) in tags {
+        sqlx::query!(
+            r#"
+            INSERT INTO image_tag (image_id, key, value) VALUES (?, ?, ?)
+            ON CONFLICT (image_id, key) DO UPDATE SET value = excluded.value
+            "#,
+            image_id,
+            key,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 获取一张图片的所有标签
+pub async fn get_tags(executor: &SqlitePool, image_id: i64) -> Result<Vec<TagRecord>> {
+    sqlx::query_as!(
+        TagRecord,
+        r#"
+        SELECT image_id, key, value FROM image_tag WHERE image_id = ?
+        "#,
+        image_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// 根据标签过滤条件查询匹配的图片 ID，多个条件间为 AND 关系
+///
+/// 条件数量和 `IN` 列表长度都是运行时可变的，无法使用 `sqlx::query!` 系列编译期检查宏，
+/// 这里改为手动拼接 SQL 并绑定参数，每个条件对应一个子查询，彼此之间用 `INTERSECT` 求交集
+pub async fn find_image_ids_by_tags(
+    executor: &SqlitePool,
+    predicates: &[TagPredicate],
+) -> Result<Vec<i64>> {
+    if predicates.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut sql = String::new();
+    for (i, predicate) in predicates.iter().enumerate() {
+        if i > 0 {
+            sql.push_str(" INTERSECT ");
+        }
+        match predicate {
+            TagPredicate::Eq(..) => {
+                sql.push_str("SELECT image_id FROM image_tag WHERE key = ? AND value = ?");
+            }
+            TagPredicate::In(_, values) => {
+                let placeholders = vec!["?"; values.len()].join(", ");
+                sql.push_str(&format!(
+                    "SELECT image_id FROM image_tag WHERE key = ? AND value IN ({placeholders})"
+                ));
+            }
+        }
+    }
+
+    let mut query = sqlx::query_scalar::<_, i64>(&sql);
+    for predicate in predicates {
+        match predicate {
+            TagPredicate::Eq(key, value) => {
+                query = query.bind(key).bind(value);
+            }
+            TagPredicate::In(key, values) => {
+                query = query.bind(key);
+                for value in values {
+                    query = query.bind(value);
+                }
+            }
+        }
+    }
+
+    query.fetch_all(executor).await
+}
+
 /// 获取所有 total_vector_count 记录
 ///
 /// NOTE: 此处假定了 total_vector_count 一定是连续的，中间没有缺失记录
@@ -240,3 +485,228 @@ pub async fn get_all_total_vector_count(executor: &SqlitePool) -> Result<Vec<i64
 
     Ok(result.into_iter().map(|row| row.total_vector_count).collect())
 }
+
+/// 写入/更新一张图片的 MinHash 签名
+pub async fn upsert_minhash(executor: &SqlitePool, image_id: i64, signature: &[u8]) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO image_minhash (image_id, signature) VALUES (?, ?)
+        ON CONFLICT (image_id) DO UPDATE SET signature = excluded.signature
+        "#,
+        image_id,
+        signature
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// 获取所有已计算的 MinHash 签名，用于在内存中构建粗筛候选集
+pub async fn get_all_minhash(executor: &SqlitePool) -> Result<Vec<MinhashRecord>> {
+    sqlx::query_as!(
+        MinhashRecord,
+        r#"
+        SELECT image_id, signature FROM image_minhash
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// 写入/更新一张图片的描述符集合 Bottom-s MinHash 草图
+pub async fn upsert_dup_sketch(executor: &SqlitePool, image_id: i64, sketch: &[u8]) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO image_dup_sketch (image_id, sketch) VALUES (?, ?)
+        ON CONFLICT (image_id) DO UPDATE SET sketch = excluded.sketch
+        "#,
+        image_id,
+        sketch
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// 获取所有已计算的草图，用于在内存中构建近似重复检测的 LSH 候选索引
+pub async fn get_all_dup_sketch(executor: &SqlitePool) -> Result<Vec<DupSketchRecord>> {
+    sqlx::query_as!(
+        DupSketchRecord,
+        r#"
+        SELECT image_id, sketch FROM image_dup_sketch
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// 记录一个来源路径（包括 tar/zip 等归档内部的成员路径）已经通过 `add_image` 完整入库，
+/// 用于支持导入中断后恢复，跳过已经处理过的路径
+pub async fn mark_ingested(executor: &SqlitePool, path: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO ingested_path (path) VALUES (?)
+        ON CONFLICT (path) DO NOTHING
+        "#,
+        path
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// 获取所有已入库的来源路径，用于恢复导入时在内存中快速判断是否需要跳过
+pub async fn get_all_ingested(executor: &SqlitePool) -> Result<Vec<String>> {
+    let result = sqlx::query!(
+        r#"
+        SELECT path FROM ingested_path
+        "#,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(result.into_iter().map(|row| row.path).collect())
+}
+
+/// 清空已入库来源路径记录，用于 `--force-rescan` 放弃之前的导入进度重新开始
+pub async fn clear_ingested(executor: &SqlitePool) -> Result<()> {
+    sqlx::query!(r#"DELETE FROM ingested_path"#).execute(executor).await?;
+    Ok(())
+}
+
+/// 将一个索引任务加入持久化队列，返回任务 ID
+pub async fn enqueue_task(executor: &SqlitePool, kind: &str, payload: &str) -> Result<i64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO task (kind, payload) VALUES (?, ?)
+        RETURNING id
+        "#,
+        kind,
+        payload
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(result.id)
+}
+
+/// 查询单个任务的当前状态
+pub async fn get_task(executor: &SqlitePool, id: i64) -> Result<Option<TaskRecord>> {
+    sqlx::query_as!(
+        TaskRecord,
+        r#"
+        SELECT id, kind, payload, status, progress_done, progress_total, error
+        FROM task WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// 取出最早入队、尚未开始处理的任务，用于 worker 按入队顺序消费队列
+pub async fn fetch_next_enqueued_task(executor: &SqlitePool) -> Result<Option<TaskRecord>> {
+    sqlx::query_as!(
+        TaskRecord,
+        r#"
+        SELECT id, kind, payload, status, progress_done, progress_total, error
+        FROM task WHERE status = 'enqueued' ORDER BY id ASC LIMIT 1
+        "#,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// 将任务标记为处理中
+pub async fn mark_task_processing(executor: &SqlitePool, id: i64) -> Result<()> {
+    sqlx::query!(
+        r#"UPDATE task SET status = 'processing', updated_at = unixepoch() WHERE id = ?"#,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// 更新任务的进度计数
+pub async fn update_task_progress(
+    executor: &SqlitePool,
+    id: i64,
+    done: i64,
+    total: Option<i64>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"UPDATE task SET progress_done = ?, progress_total = ?, updated_at = unixepoch() WHERE id = ?"#,
+        done,
+        total,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// 将任务标记为执行成功
+pub async fn mark_task_succeeded(executor: &SqlitePool, id: i64) -> Result<()> {
+    sqlx::query!(
+        r#"UPDATE task SET status = 'succeeded', updated_at = unixepoch() WHERE id = ?"#,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// 将任务标记为执行失败，并记录错误信息
+pub async fn mark_task_failed(executor: &SqlitePool, id: i64, error: &str) -> Result<()> {
+    sqlx::query!(
+        r#"UPDATE task SET status = 'failed', error = ?, updated_at = unixepoch() WHERE id = ?"#,
+        error,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// 统计尚在排队、还未开始处理的任务数量，用于上报队列深度指标
+pub async fn count_enqueued_tasks(executor: &SqlitePool) -> Result<i64> {
+    let result = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as count FROM task WHERE status = 'enqueued'
+        "#,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(result.count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    #[tokio::test]
+    async fn test_mark_ingested_resume_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().join("test.db"), false).await.unwrap();
+
+        assert!(get_all_ingested(&db).await.unwrap().is_empty());
+
+        mark_ingested(&db, "a.jpg").await.unwrap();
+        mark_ingested(&db, "b.jpg").await.unwrap();
+        // 重复标记同一路径应当被 ON CONFLICT DO NOTHING 忽略，而不是报错
+        mark_ingested(&db, "a.jpg").await.unwrap();
+
+        let mut ingested = get_all_ingested(&db).await.unwrap();
+        ingested.sort();
+        assert_eq!(ingested, vec!["a.jpg".to_string(), "b.jpg".to_string()]);
+
+        clear_ingested(&db).await.unwrap();
+        assert!(get_all_ingested(&db).await.unwrap().is_empty());
+    }
+}
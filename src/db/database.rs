@@ -7,7 +7,7 @@ use crate::matrix::Matrix;
 use anyhow::Result;
 use log::debug;
 use rocksdb::{
-    BoundColumnFamily, ColumnFamilyDescriptor, IteratorMode, ReadOptions, WriteBatch, DB,
+    BoundColumnFamily, ColumnFamilyDescriptor, Direction, IteratorMode, ReadOptions, WriteBatch, DB,
 };
 
 #[derive(Debug, Hash, Eq, PartialEq)]
@@ -15,6 +15,8 @@ pub(super) enum ImageColumnFamily {
     /// HashMap<FeatureId, Box<[u8]>>
     IdToFeature,
     /// HashMap<FeatureId, ImageId>
+    ///
+    /// Deprecated in favor of `FeatureRange`, kept only so `check_db_update` can migrate old databases
     IdToImageId,
     /// HashMap<ImageId, String>
     IdToImage,
@@ -24,6 +26,12 @@ pub(super) enum ImageColumnFamily {
     MetaData,
     /// Just like IdToFeature, but only contains features which haven't been indexed
     NewFeature,
+    /// HashMap<EndFeatureId, ImageId>
+    ///
+    /// Each image occupies one contiguous range of feature ids `[start, end)`, allocated
+    /// sequentially via `total_features.fetch_add`, so storing the exclusive-end id is enough to
+    /// resolve any feature id to its image via a ceiling lookup (`seek` to the first key > id)
+    FeatureRange,
 }
 
 pub(super) enum MetaData {
@@ -40,6 +48,7 @@ impl ImageColumnFamily {
             Self::IdToFeature,
             Self::MetaData,
             Self::IdToImage,
+            Self::FeatureRange,
         ]
     }
 
@@ -60,6 +69,7 @@ impl AsRef<str> for ImageColumnFamily {
             Self::ImageList => "image_list",
             Self::MetaData => "meta_data",
             Self::NewFeature => "new_feature",
+            Self::FeatureRange => "feature_range",
         }
     }
 }
@@ -133,7 +143,7 @@ impl ImageDB {
         T: Matrix,
     {
         let new_feature = self.cf(ImageColumnFamily::NewFeature);
-        let id_to_image_id = self.cf(ImageColumnFamily::IdToImageId);
+        let feature_range = self.cf(ImageColumnFamily::FeatureRange);
         let id_to_image = self.cf(ImageColumnFamily::IdToImage);
         let image_list = self.cf(ImageColumnFamily::ImageList);
 
@@ -148,11 +158,19 @@ impl ImageDB {
         batch.put_cf(&id_to_image, image_id.to_le_bytes(), path.as_ref());
 
         // insert feature_id => feature to NewFeature
-        // insert feature_id => image_id
+        let mut feature_count = 0u64;
         for feature in features.iter_lines() {
             let id = self.total_features.fetch_add(1, Ordering::SeqCst);
             batch.put_cf(&new_feature, id.to_le_bytes(), feature);
-            batch.put_cf(&id_to_image_id, id.to_le_bytes(), image_id.to_le_bytes());
+            feature_count += 1;
+        }
+        // this image's features occupy the contiguous range `[prev total_features, total_features)`,
+        // so a single entry keyed by the exclusive-end id is enough to resolve any feature in it;
+        // skip images with no features to avoid overwriting the previous image's range with a
+        // duplicate key
+        if feature_count > 0 {
+            let total_features = self.total_features.load(Ordering::SeqCst);
+            batch.put_cf(&feature_range, total_features.to_le_bytes(), image_id.to_le_bytes());
         }
         // insert image_hash => image_id
         batch.put_cf(&image_list, hash, image_id.to_le_bytes());
@@ -189,12 +207,16 @@ impl ImageDB {
             .map(|item| (bytes_to_u64(item.0), item.1))
     }
 
+    /// Resolve a feature id to its image id via a ceiling lookup on `FeatureRange`:
+    /// seek to the first `end_feature_id` that is strictly greater than `feature_id`
     fn find_image_id_by_id(&self, feature_id: u64) -> Result<Option<i32>> {
-        let id_to_image_id = self.cf(ImageColumnFamily::IdToImageId);
-        Ok(self
-            .db
-            .get_cf(&id_to_image_id, feature_id.to_le_bytes())?
-            .map(bytes_to_i32))
+        let feature_range = self.cf(ImageColumnFamily::FeatureRange);
+        let mut iter = self.db.iterator_cf_opt(
+            &feature_range,
+            Self::read_opts(),
+            IteratorMode::From(&(feature_id + 1).to_le_bytes(), Direction::Forward),
+        );
+        Ok(iter.next().transpose()?.map(|(_, image_id)| bytes_to_i32(image_id)))
     }
 
     /// Find image according to feature id
@@ -28,3 +28,50 @@ pub async fn init_db(filename: impl AsRef<Path>, wal: bool) -> Result<Database,
 
     Ok(pool)
 }
+
+/// 对运行中的数据库做一次在线快照，拷贝期间其他连接仍可正常读写
+///
+/// 底层使用 SQLite 自带的 `VACUUM INTO`：它在一个只读事务的快照下把数据库完整拷贝到
+/// `dest`，不需要像冷拷贝数据库文件那样先停掉所有写者；`dest` 不能已经存在，否则 SQLite
+/// 会报错
+pub async fn backup(db: &Database, dest: impl AsRef<Path>) -> Result<(), sqlx::Error> {
+    let dest = dest
+        .as_ref()
+        .to_str()
+        .ok_or_else(|| sqlx::Error::Configuration("dest 必须是合法的 UTF-8 路径".into()))?;
+    sqlx::query("VACUUM INTO ?").bind(dest).execute(db).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::crud::{get_all_ingested, mark_ingested};
+
+    #[tokio::test]
+    async fn test_backup_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().join("src.db"), false).await.unwrap();
+        mark_ingested(&db, "a.jpg").await.unwrap();
+
+        let dest = dir.path().join("backup.db");
+        backup(&db, &dest).await.unwrap();
+        assert!(dest.exists());
+
+        let restored = init_db(&dest, false).await.unwrap();
+        assert_eq!(get_all_ingested(&restored).await.unwrap(), vec!["a.jpg".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_backup_rejects_non_utf8_dest() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().join("src.db"), false).await.unwrap();
+
+        let dest = dir.path().join(OsStr::from_bytes(&[0xff, 0xfe]));
+        assert!(backup(&db, &dest).await.is_err());
+    }
+}
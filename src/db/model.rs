@@ -33,3 +33,66 @@ pub struct VectorIdxRecord {
     pub vector: Vec<u8>,
     pub total_vector_count: i64,
 }
+
+/// 被删除的特征向量 ID 区间（墓碑记录）
+///
+/// 记录 `delete_image` 时回收的 `[start_id, end_id)` 全局特征 ID 区间，
+/// 在下一次 `compact` 重写倒排列表之前，搜索路径需要据此过滤掉这些 ID
+pub struct TombstoneRecord {
+    pub start_id: i64,
+    pub end_id: i64,
+}
+
+/// 图片标签记录
+pub struct TagRecord {
+    pub image_id: i64,
+    pub key: String,
+    pub value: String,
+}
+
+/// 索引任务记录
+pub struct TaskRecord {
+    pub id: i64,
+    /// 任务种类，取值见 [`crate::server::TaskRequest`]
+    pub kind: String,
+    /// 任务参数，JSON 编码后的 [`crate::server::TaskRequest`]
+    pub payload: String,
+    /// enqueued / processing / succeeded / failed
+    pub status: String,
+    pub progress_done: i64,
+    pub progress_total: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// 标签过滤条件
+#[derive(Debug, Clone)]
+pub enum TagPredicate {
+    /// `key = value`
+    Eq(String, String),
+    /// `key IN (values)`
+    In(String, Vec<String>),
+}
+
+/// 图片的 MinHash 视觉词签名
+pub struct MinhashRecord {
+    pub image_id: i64,
+    /// `Vec<u32>` 按小端序打包后的字节
+    pub signature: Vec<u8>,
+}
+
+/// 图片的描述符集合 Bottom-s MinHash 草图，用于入库前的近似重复检测
+pub struct DupSketchRecord {
+    pub image_id: i64,
+    /// `Vec<u64>` 按小端序打包后的字节，参见 [`crate::dedup::sketch`]
+    pub sketch: Vec<u8>,
+}
+
+/// 搜索时使用的标签过滤配置
+///
+/// `pre` 在候选图片进入计分前过滤（缩小参与 Hamming 距离统计的图片 ID 集合），
+/// `post` 在最终结果排序、截断之后过滤，两者可以同时使用
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    pub pre: Vec<TagPredicate>,
+    pub post: Vec<TagPredicate>,
+}
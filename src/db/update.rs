@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
+
 use super::database::{ImageColumnFamily, MetaData};
 use crate::config::ConfDir;
-use crate::db::utils::{default_options, init_column_family};
+use crate::db::utils::{bytes_to_i32, bytes_to_u64, default_options, init_column_family};
 use crate::utils::hash_file;
 use anyhow::Result;
 use log::info;
@@ -21,13 +23,16 @@ pub fn check_db_update(path: &ConfDir) -> Result<()> {
     }
     if !version_file.exists() {
         std::fs::create_dir_all(path.path())?;
-        std::fs::write(path.version(), "3")?;
+        std::fs::write(path.version(), "4")?;
     }
 
     let version = std::fs::read_to_string(version_file)?;
 
     match version.as_str() {
-        "3" => {}
+        "3" => {
+            update_from_3_to_4(path)?;
+            std::fs::write(path.version(), "4")?;
+        }
         _ => {}
     }
 
@@ -40,6 +45,51 @@ pub fn check_db_update(path: &ConfDir) -> Result<()> {
     Ok(())
 }
 
+/// Fold the per-feature `IdToImageId` entries into compact `FeatureRange` ranges
+///
+/// `IdToImageId` stores one row per feature even though every image's features form a
+/// contiguous run allocated via `total_features.fetch_add`, so a database with millions of
+/// features carries millions of tiny rows. This rewrites them as one `end_feature_id -> image_id`
+/// entry per image and drops the old rows.
+fn update_from_3_to_4(path: &ConfDir) -> Result<()> {
+    if !path.database().exists() {
+        return Ok(());
+    }
+
+    let mut opts = default_options();
+    // FeatureRange didn't exist prior to v4, so it must be created on open
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf_descriptors(&opts, path.database(), ImageColumnFamily::descriptors())?;
+
+    let id_to_image_id = db.cf_handle(ImageColumnFamily::IdToImageId.as_ref()).unwrap();
+    let feature_range = db.cf_handle(ImageColumnFamily::FeatureRange.as_ref()).unwrap();
+
+    // feature ids aren't stored in a byte order that sorts numerically, so collect and sort first
+    let mut entries: Vec<(u64, i32)> = db
+        .iterator_cf(&id_to_image_id, IteratorMode::Start)
+        .map(|item| {
+            let (feature_id, image_id) = item?;
+            Ok((bytes_to_u64(feature_id), bytes_to_i32(image_id)))
+        })
+        .collect::<Result<Vec<_>, rocksdb::Error>>()?;
+    entries.sort_unstable_by_key(|(feature_id, _)| *feature_id);
+
+    info!("folding {} legacy feature->image entries into ranges", entries.len());
+
+    let mut ranges = BTreeMap::new();
+    for (feature_id, image_id) in &entries {
+        ranges.insert(*image_id, feature_id + 1);
+    }
+    for (image_id, end_feature_id) in ranges {
+        db.put_cf(&feature_range, end_feature_id.to_le_bytes(), image_id.to_le_bytes())?;
+    }
+    for (feature_id, _) in entries {
+        db.delete_cf(&id_to_image_id, feature_id.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
 #[allow(unused)]
 fn update_from_2_to_3(path: &ConfDir) -> Result<()> {
     let mut opts = default_options();
@@ -39,6 +39,7 @@ pub fn init_column_family(db: &DB) -> Result<(), Error> {
     db.create_cf(ImageColumnFamily::IdToImage, &opts)?;
     db.create_cf(ImageColumnFamily::ImageList, &opts)?;
     db.create_cf(ImageColumnFamily::MetaData, &opts)?;
+    db.create_cf(ImageColumnFamily::FeatureRange, &opts)?;
 
     let meta_data = db.cf_handle(ImageColumnFamily::MetaData.as_ref()).unwrap();
     db.put_cf(&meta_data, MetaData::TotalImages, 0u64.to_le_bytes())?;
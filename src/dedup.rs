@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use xxhash_rust::xxh3::xxh3_64;
+
+/// 计算一组描述符的 Bottom-s MinHash 草图：对每个描述符求 xxh3 哈希，排序后取最小的 `s`
+/// 个，草图本身就是一组集合元素（而非像 [`crate::minhash::MinHashSeeds`] 那样每个哈希函数
+/// 只保留一个最小值），用于在图片入库前快速估计两组描述符集合的 Jaccard 相似度
+pub fn sketch<const N: usize>(descriptors: &[[u8; N]], s: usize) -> Vec<u64> {
+    let mut hashes: Vec<u64> = descriptors.iter().map(|d| xxh3_64(&d[..])).collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes.truncate(s);
+    hashes
+}
+
+/// 估计两个 Bottom-s MinHash 草图对应描述符集合的 Jaccard 相似度
+///
+/// 两个草图的并集中取并集前 s 个元素，其中同时属于两个草图的比例即为估计值；
+/// 要求两个草图使用相同的 `s` 生成，否则估计会有偏
+pub fn jaccard(a: &[u64], b: &[u64]) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.;
+    }
+    let s = a.len().min(b.len());
+
+    let mut merged: Vec<u64> = a.iter().chain(b).copied().collect();
+    merged.sort_unstable();
+    merged.dedup();
+    merged.truncate(s);
+
+    if merged.is_empty() {
+        return 0.;
+    }
+
+    let a_set: std::collections::HashSet<u64> = a.iter().copied().collect();
+    let b_set: std::collections::HashSet<u64> = b.iter().copied().collect();
+    let intersection = merged.iter().filter(|h| a_set.contains(h) && b_set.contains(h)).count();
+    intersection as f32 / merged.len() as f32
+}
+
+/// 基于 LSH 分桶的草图候选索引
+///
+/// 将每个草图切分成 `bands` 个条带，每个条带取 `rows` 个哈希元素再整体哈希一次，
+/// 落在同一个条带哈希桶中的图片即视为候选；只有在某一条带上完全一致，才会被同一个
+/// 桶收录，从而把需要精确计算 Jaccard 相似度的候选数量从全量压缩到一个很小的子集
+pub struct LshIndex {
+    bands: usize,
+    rows: usize,
+    tables: Vec<HashMap<u64, Vec<i64>>>,
+}
+
+impl LshIndex {
+    pub fn new(bands: usize, rows: usize) -> Self {
+        Self { bands, rows, tables: vec![HashMap::new(); bands] }
+    }
+
+    fn band_hashes(&self, sketch: &[u64]) -> Vec<u64> {
+        (0..self.bands)
+            .map(|i| {
+                let start = i * self.rows;
+                let end = (start + self.rows).min(sketch.len());
+                if start >= sketch.len() {
+                    return 0;
+                }
+                xxh3_64(bytemuck::cast_slice(&sketch[start..end]))
+            })
+            .collect()
+    }
+
+    /// 将一张图片的草图插入索引
+    pub fn insert(&mut self, image_id: i64, sketch: &[u64]) {
+        for (table, band_hash) in self.tables.iter_mut().zip(self.band_hashes(sketch)) {
+            table.entry(band_hash).or_default().push(image_id);
+        }
+    }
+
+    /// 返回与给定草图至少有一个条带相同的候选图片 ID（去重）
+    pub fn candidates(&self, sketch: &[u64]) -> Vec<i64> {
+        let mut candidates: Vec<i64> = self
+            .tables
+            .iter()
+            .zip(self.band_hashes(sketch))
+            .filter_map(|(table, band_hash)| table.get(&band_hash))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
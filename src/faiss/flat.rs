@@ -0,0 +1,136 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use ndarray::Array2;
+use opencv::prelude::*;
+
+use super::types::{FaissSearchParams, Neighbor};
+use crate::hamming::batch_knn_hamming;
+
+/// 纯 Rust 实现的暴力检索索引
+///
+/// 直接把描述符存成 `Vec<[u8; 32]>`，搜索时用 [`batch_knn_hamming`] 在 rayon 上并行做精确
+/// 暴力匹配，没有训练步骤，也没有 `nlist`/`nprobe` 这些 IVF 概念：用于向量规模较小、不值得
+/// 为训练/加载一个真正 IVF 索引承担开销的场景。方法名与 [`super::FaissIndex`] 保持一致，
+/// 让调用方可以通过 [`super::Index`] 透明地在两种后端之间切换
+pub struct FlatIndex {
+    descriptors: Vec<[u8; 32]>,
+    ids: Vec<i64>,
+}
+
+impl FlatIndex {
+    /// 创建一个空的暴力检索索引
+    pub fn new() -> Self {
+        Self { descriptors: vec![], ids: vec![] }
+    }
+
+    /// 文件头的 4 字节魔数，用于和 Faiss 自己的索引文件格式区分开，让 [`super::Index::from_file`]
+    /// 可以靠嗅探文件头自动选择后端，而不需要调用方另外记录索引是用哪种后端构建的
+    const MAGIC: &'static [u8; 4] = b"FLAT";
+
+    /// 从文件加载索引，文件格式为 `[魔数: 4 字节][数量: u64 LE][描述符: 数量*32 字节][ID: 数量*8 字节]`，
+    /// 与 [`Bloom`](crate::bloom::Bloom) 等其它持久化结构一样手工打包，不引入额外的序列化依赖
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let data = fs::read(path)?;
+        anyhow::ensure!(data.starts_with(Self::MAGIC), "不是 FlatIndex 格式的索引文件");
+
+        let n = u64::from_le_bytes(data[4..12].try_into()?) as usize;
+
+        let descriptors_end = 12 + n * 32;
+        let descriptors: Vec<[u8; 32]> = data[12..descriptors_end]
+            .chunks_exact(32)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect();
+        let ids: Vec<i64> = bytemuck::cast_slice(&data[descriptors_end..descriptors_end + n * 8]).to_vec();
+
+        Ok(Self { descriptors, ids })
+    }
+
+    /// 将索引写入到文件，考虑到中途打断的情况，使用临时文件写入再重命名
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+
+        let mut buf =
+            Vec::with_capacity(4 + 8 + self.descriptors.len() * 32 + self.ids.len() * 8);
+        buf.extend_from_slice(Self::MAGIC);
+        buf.extend_from_slice(&(self.descriptors.len() as u64).to_le_bytes());
+        for d in &self.descriptors {
+            buf.extend_from_slice(d);
+        }
+        buf.extend_from_slice(bytemuck::cast_slice(&self.ids));
+
+        fs::write(&tmp_path, buf)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// 该索引中的向量数量
+    pub fn ntotal(&self) -> i64 {
+        self.descriptors.len() as i64
+    }
+
+    /// 使用自定义 ID 添加向量到索引中
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - 向量，大小为 (n, 32)
+    /// * `ids` - 向量 id 列表，长度为 n
+    pub fn add_with_ids(&mut self, v: &Array2<u8>, ids: &[i64]) -> Result<()> {
+        assert_eq!(v.dim().1, 32);
+        assert_eq!(v.dim().0, ids.len());
+        for row in v.rows() {
+            let d: [u8; 32] = row.as_slice().expect("descriptor 行不连续").try_into()?;
+            self.descriptors.push(d);
+        }
+        self.ids.extend_from_slice(ids);
+        Ok(())
+    }
+
+    /// 批量搜索 points 中的向量，对每个向量用 [`batch_knn_hamming`] 精确计算出最近的 knn 个邻居
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - 需要搜索的向量数组，大小为 (n, 32)
+    /// * `knn` - 每个向量需要返回的最近邻数量
+    pub fn search(&self, points: &Mat, knn: usize, _params: FaissSearchParams) -> Result<Vec<Vec<Neighbor>>> {
+        assert_eq!(points.cols() as usize, 32);
+
+        let queries: Vec<[u8; 32]> = (0..points.rows())
+            .map(|i| {
+                let row = points.at_row::<u8>(i)?;
+                Ok(row.try_into()?)
+            })
+            .collect::<Result<_>>()?;
+
+        let results = batch_knn_hamming::<32>(&queries, &self.descriptors, knn);
+        Ok(results
+            .into_iter()
+            .map(|neighbors| {
+                neighbors
+                    .into_iter()
+                    .map(|(idx, dis)| Neighbor { index: self.ids[idx], distance: dis as i32 })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// 合并索引
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - 需要合并的索引
+    /// * `add_id` - 合并是在原 ID 基础上增加的 ID
+    pub fn merge_from(&mut self, other: &Self, add_id: i64) -> Result<()> {
+        self.descriptors.extend_from_slice(&other.descriptors);
+        self.ids.extend(other.ids.iter().map(|id| id + add_id));
+        Ok(())
+    }
+}
+
+impl Default for FlatIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,13 +1,74 @@
+mod flat;
 mod index;
 mod invlists;
 mod types;
 
 use std::ffi::CStr;
+use std::path::Path;
 
+use anyhow::Result;
+use ndarray::Array2;
+use opencv::prelude::*;
+
+pub use flat::*;
 pub use index::*;
 pub use invlists::*;
 pub use types::*;
 
+/// 索引后端，根据描述字符串选择 Faiss FFI 后端或纯 Rust 暴力后端
+///
+/// 描述字符串为 `"Flat"` 时构造 [`FlatIndex`]，跳过 Faiss 训练/加载整块二进制文件的开销，
+/// 适合小规模数据集；其余描述符透传给 [`FaissIndex::new`] 交给 Faiss 处理
+pub enum Index {
+    Faiss(FaissIndex),
+    Flat(FlatIndex),
+}
+
+impl Index {
+    pub fn new(d: i32, description: &str) -> Result<Self> {
+        if description == "Flat" {
+            Ok(Self::Flat(FlatIndex::new()))
+        } else {
+            Ok(Self::Faiss(FaissIndex::new(d, description)?))
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P, mmap: bool) -> Result<Self> {
+        match FlatIndex::from_file(&path) {
+            Ok(index) => Ok(Self::Flat(index)),
+            Err(_) => Ok(Self::Faiss(FaissIndex::from_file(path, mmap)?)),
+        }
+    }
+
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        match self {
+            Self::Faiss(index) => index.write_file(path),
+            Self::Flat(index) => index.write_file(path),
+        }
+    }
+
+    pub fn ntotal(&self) -> i64 {
+        match self {
+            Self::Faiss(index) => index.ntotal(),
+            Self::Flat(index) => index.ntotal(),
+        }
+    }
+
+    pub fn add_with_ids(&mut self, v: &Array2<u8>, ids: &[i64]) -> Result<()> {
+        match self {
+            Self::Faiss(index) => index.add_with_ids(v, ids),
+            Self::Flat(index) => index.add_with_ids(v, ids),
+        }
+    }
+
+    pub fn search(&self, points: &Mat, knn: usize, params: FaissSearchParams) -> Result<Vec<Vec<Neighbor>>> {
+        match self {
+            Self::Faiss(index) => Ok(index.search(points, knn, params)),
+            Self::Flat(index) => index.search(points, knn, params),
+        }
+    }
+}
+
 pub fn get_faiss_stats() -> faiss_sys::FaissIndexIVFStats {
     unsafe {
         let stats = faiss_sys::faiss_get_indexIVF_stats();
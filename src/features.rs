@@ -0,0 +1,117 @@
+//! 可插拔的特征提取后端
+//!
+//! [`ORBDetector`] 一直是整条流水线唯一的特征提取器，产出 32 字节二进制描述符，索引、
+//! 搜索全部围绕 Hamming 距离设计。这里先抽出一个 [`FeatureExtractor`] trait，方便未来
+//! 接入 SIFT/SURF 这类浮点描述符算法；但浮点描述符要真正可用，还需要 `faiss`/`ivf`
+//! 支持 L2 浮点索引，这部分尚未实现，因此 [`SiftExtractor`] 目前只能独立提取描述符，
+//! 还不能接到 `add`/`build`/`search` 子命令上
+
+use std::mem::size_of;
+
+use clap::ValueEnum;
+use opencv::core::{KeyPoint, Mat, Vector};
+use opencv::features2d::Feature2DTrait;
+use opencv::prelude::*;
+use opencv::{Result, features2d};
+
+use crate::config::OrbOptions;
+use crate::orb::ORBDetector;
+use crate::utils;
+
+/// CLI 可选的特征提取后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FeatureExtractorKind {
+    Orb,
+    Sift,
+}
+
+/// 特征提取器，统一 ORB 与浮点描述符算法（如 SIFT）的调用方式
+///
+/// 方法签名对齐 [`ORBDetector`] 现有的 `detect_file`/`detect_bytes`/`detect_image`，
+/// 这样上层代码可以在不同后端之间切换而不需要改动调用方式
+pub trait FeatureExtractor {
+    /// 单个特征点对应的描述符类型
+    type Descriptor;
+
+    fn detect_file(&mut self, path: &str) -> Result<(Mat, Vec<KeyPoint>, Vec<Self::Descriptor>)>;
+    fn detect_bytes(&mut self, bytes: &[u8]) -> Result<(Vec<KeyPoint>, Vec<Self::Descriptor>)>;
+    fn detect_image(&mut self, image: Mat) -> Result<(Vec<KeyPoint>, Vec<Self::Descriptor>)>;
+}
+
+impl FeatureExtractor for ORBDetector {
+    type Descriptor = [u8; 32];
+
+    fn detect_file(&mut self, path: &str) -> Result<(Mat, Vec<KeyPoint>, Vec<[u8; 32]>)> {
+        ORBDetector::detect_file(self, path)
+    }
+
+    fn detect_bytes(&mut self, bytes: &[u8]) -> Result<(Vec<KeyPoint>, Vec<[u8; 32]>)> {
+        ORBDetector::detect_bytes(self, bytes)
+    }
+
+    fn detect_image(&mut self, image: Mat) -> Result<(Vec<KeyPoint>, Vec<[u8; 32]>)> {
+        ORBDetector::detect_image(self, image)
+    }
+}
+
+/// 基于 OpenCV `SIFT` 的特征提取器，描述符为 128 维浮点向量
+pub struct SiftExtractor {
+    sift: features2d::SIFT,
+    max_size: (i32, i32),
+}
+
+impl SiftExtractor {
+    pub fn create(opts: OrbOptions) -> Result<Self> {
+        let sift = features2d::SIFT::create(
+            opts.max_features as i32,
+            3,
+            0.04,
+            10.,
+            1.6,
+            opencv::core::CV_8U,
+            false,
+        )?;
+        Ok(Self { sift, max_size: opts.max_size })
+    }
+
+    fn detect_and_compute(&mut self, image: &Mat) -> Result<(Vec<KeyPoint>, Vec<[f32; 128]>)> {
+        let mask = Mat::default();
+        let mut kps = Vector::<KeyPoint>::new();
+        let mut des = Mat::default();
+        self.sift.detect_and_compute(image, &mask, &mut kps, &mut des, false)?;
+
+        let kps = kps.to_vec();
+        let row_bytes = des.data_bytes()?;
+        let descriptors = row_bytes
+            .chunks_exact(128 * size_of::<f32>())
+            .map(|chunk| {
+                let mut des = [0f32; 128];
+                for (d, c) in des.iter_mut().zip(chunk.chunks_exact(size_of::<f32>())) {
+                    *d = f32::from_ne_bytes(c.try_into().unwrap());
+                }
+                des
+            })
+            .collect();
+        Ok((kps, descriptors))
+    }
+}
+
+impl FeatureExtractor for SiftExtractor {
+    type Descriptor = [f32; 128];
+
+    fn detect_file(&mut self, path: &str) -> Result<(Mat, Vec<KeyPoint>, Vec<[f32; 128]>)> {
+        let image = utils::imread(path, self.max_size)?;
+        let (kps, des) = self.detect_and_compute(&image)?;
+        Ok((image, kps, des))
+    }
+
+    fn detect_bytes(&mut self, bytes: &[u8]) -> Result<(Vec<KeyPoint>, Vec<[f32; 128]>)> {
+        let image = utils::imdecode(bytes, self.max_size)?;
+        self.detect_and_compute(&image)
+    }
+
+    fn detect_image(&mut self, image: Mat) -> Result<(Vec<KeyPoint>, Vec<[f32; 128]>)> {
+        let image = utils::adjust_image_size(image, self.max_size)?;
+        self.detect_and_compute(&image)
+    }
+}
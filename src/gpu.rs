@@ -0,0 +1,128 @@
+//! 基于 CUDA 的汉明距离批量 kNN 后端，由 `gpu` feature 开启
+//!
+//! 把查询向量和数据库向量一次性上传到显存，每个线程负责一个查询向量并遍历全部数据库向量，用 `__popcll`
+//! 计算异或后的汉明距离，并在设备端维护一个长度为 k 的有序数组做 top-k 归约，只把最终的
+//! `(index, distance)` 结果拷回主机，避免把完整的距离矩阵搬过 PCIe
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Result, anyhow};
+use cudarc::driver::{CudaDevice, CudaSlice, LaunchAsync, LaunchConfig};
+use cudarc::nvrtc::compile_ptx;
+
+/// GPU 一次调用支持的最大 k，对应 kernel 里 `best_dist`/`best_idx` 的栈上数组长度
+const MAX_K: usize = 64;
+
+const KERNEL_SRC: &str = r#"
+extern "C" __global__ void hamming_knn(
+    const unsigned long long* queries,
+    const unsigned long long* database,
+    unsigned int* out_idx,
+    unsigned int* out_dist,
+    int n_query,
+    int n_db,
+    int words_per_vec,
+    int k
+) {
+    int q = blockIdx.x * blockDim.x + threadIdx.x;
+    if (q >= n_query) return;
+
+    unsigned int best_dist[64];
+    unsigned int best_idx[64];
+    for (int i = 0; i < k; i++) {
+        best_dist[i] = 0xFFFFFFFFu;
+        best_idx[i] = 0;
+    }
+
+    const unsigned long long* qv = queries + (size_t)q * words_per_vec;
+    for (int d = 0; d < n_db; d++) {
+        const unsigned long long* dv = database + (size_t)d * words_per_vec;
+        unsigned int dist = 0;
+        for (int w = 0; w < words_per_vec; w++) {
+            dist += __popcll(qv[w] ^ dv[w]);
+        }
+        if (dist < best_dist[k - 1]) {
+            int pos = k - 1;
+            while (pos > 0 && best_dist[pos - 1] > dist) {
+                best_dist[pos] = best_dist[pos - 1];
+                best_idx[pos] = best_idx[pos - 1];
+                pos--;
+            }
+            best_dist[pos] = dist;
+            best_idx[pos] = d;
+        }
+    }
+
+    for (int i = 0; i < k; i++) {
+        out_idx[(size_t)q * k + i] = best_idx[i];
+        out_dist[(size_t)q * k + i] = best_dist[i];
+    }
+}
+"#;
+
+/// 惰性初始化的设备句柄：没有 CUDA 设备或驱动初始化失败时为 `None`，后续调用直接回退 CPU
+fn device() -> Option<&'static Arc<CudaDevice>> {
+    static DEVICE: OnceLock<Option<Arc<CudaDevice>>> = OnceLock::new();
+    DEVICE.get_or_init(|| CudaDevice::new(0).ok()).as_ref()
+}
+
+/// 运行时检测当前机器是否有可用的 CUDA 设备
+pub fn is_available() -> bool {
+    device().is_some()
+}
+
+/// 在 GPU 上批量计算汉明距离 kNN，返回的近邻集合和 CPU 版 [`crate::hamming::batch_knn_hamming`] 相同
+///
+/// 每个查询最多返回 `min(k, vb.len())` 个结果，按距离升序排列；距离相等的候选之间取舍可能与 CPU 版不同
+pub fn batch_knn_hamming_gpu<const N: usize>(
+    va: &[[u8; N]],
+    vb: &[[u8; N]],
+    k: usize,
+) -> Result<Vec<Vec<(usize, u32)>>> {
+    assert_eq!(N % 8, 0, "GPU 后端要求 N 是 8 的倍数");
+    assert!(k <= MAX_K, "GPU 后端单次查询最多支持 {MAX_K} 个近邻");
+
+    let dev = device().ok_or_else(|| anyhow!("没有可用的 CUDA 设备"))?;
+
+    let ptx = compile_ptx(KERNEL_SRC)?;
+    dev.load_ptx(ptx, "imsearch_hamming", &["hamming_knn"])?;
+    let kernel =
+        dev.get_func("imsearch_hamming", "hamming_knn").ok_or_else(|| anyhow!("kernel 加载失败"))?;
+
+    let words_per_vec = N / 8;
+    let queries: CudaSlice<u64> = dev.htod_copy(bytemuck::cast_slice(va.as_flattened()).to_vec())?;
+    let database: CudaSlice<u64> = dev.htod_copy(bytemuck::cast_slice(vb.as_flattened()).to_vec())?;
+
+    let mut out_idx: CudaSlice<u32> = dev.alloc_zeros(va.len() * k)?;
+    let mut out_dist: CudaSlice<u32> = dev.alloc_zeros(va.len() * k)?;
+
+    let cfg = LaunchConfig::for_num_elems(va.len() as u32);
+    unsafe {
+        kernel.launch(
+            cfg,
+            (
+                &queries,
+                &database,
+                &mut out_idx,
+                &mut out_dist,
+                va.len() as i32,
+                vb.len() as i32,
+                words_per_vec as i32,
+                k as i32,
+            ),
+        )?;
+    }
+
+    let idx_host = dev.dtoh_sync_copy(&out_idx)?;
+    let dist_host = dev.dtoh_sync_copy(&out_dist)?;
+
+    // 数据库向量不足 k 个时，kernel 会用距离 0xFFFFFFFF 填满剩余位置，这里去掉这些占位结果
+    Ok((0..va.len())
+        .map(|q| {
+            (0..k)
+                .map(|i| (idx_host[q * k + i] as usize, dist_host[q * k + i]))
+                .filter(|&(_, d)| d != u32::MAX)
+                .collect()
+        })
+        .collect())
+}
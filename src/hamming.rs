@@ -2,10 +2,18 @@ use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
 use bytemuck::cast_slice;
+use rayon::prelude::*;
 
 #[inline(always)]
 pub fn hamming<const N: usize>(va: &[u8], vb: &[u8]) -> u32 {
     match N {
+        #[cfg(target_arch = "x86_64")]
+        32 if std::is_x86_feature_detected!("avx512f")
+            && std::is_x86_feature_detected!("avx512vpopcntdq") =>
+        // SAFETY: 上面已经检测过对应的 CPU 特性
+        unsafe {
+            hamming_32_avx512(va, vb)
+        },
         32 => hamming_32(va, vb),
         _ => hamming_naive::<N>(va, vb),
     }
@@ -33,6 +41,21 @@ pub fn hamming_32(va: &[u8], vb: &[u8]) -> u32 {
         + (va[3] ^ vb[3]).count_ones()
 }
 
+/// SAFETY: 调用前需确认 CPU 支持 `avx512f` 和 `avx512vpopcntdq`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vpopcntdq")]
+unsafe fn hamming_32_avx512(va: &[u8], vb: &[u8]) -> u32 {
+    use std::arch::x86_64::*;
+
+    unsafe {
+        let qa = _mm512_zextsi256_si512(_mm256_loadu_si256(va.as_ptr() as *const __m256i));
+        let cb = _mm512_zextsi256_si512(_mm256_loadu_si256(vb.as_ptr() as *const __m256i));
+        let x = _mm512_xor_si512(qa, cb);
+        let popcnt = _mm512_popcnt_epi64(x);
+        _mm512_reduce_add_epi64(popcnt) as u32
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct KNNResult {
     /// 注意此处 dis 排在前面，保证自动 derive 的 Ord 正确
@@ -41,7 +64,15 @@ pub struct KNNResult {
 }
 
 pub fn knn_hamming<const N: usize>(va: &[u8; N], vb: &[[u8; N]], k: usize) -> Vec<(usize, u32)> {
-    return knn_hamming_heap::<N>(va, vb, k);
+    #[cfg(feature = "gpu")]
+    if crate::gpu::is_available() {
+        match crate::gpu::batch_knn_hamming_gpu::<N>(std::slice::from_ref(va), vb, k) {
+            Ok(mut result) => return result.pop().unwrap_or_default(),
+            Err(e) => log::warn!("GPU 汉明距离计算失败，回退到 CPU：{e}"),
+        }
+    }
+
+    knn_hamming_heap::<N>(va, vb, k)
 }
 
 /// 计算向量 va 和 vb 的汉明距离，并返回距离最小的 k 个索引和距离
@@ -50,9 +81,11 @@ pub fn knn_hamming_heap<const N: usize>(
     vb: &[[u8; N]],
     k: usize,
 ) -> Vec<(usize, u32)> {
+    let mut dis = vec![0u32; vb.len()];
+    batch_hamming_into(va, vb, &mut dis);
+
     let mut heap = BinaryHeap::new();
-    for (i, chunk) in vb.iter().enumerate() {
-        let d = hamming::<N>(va, chunk);
+    for (i, &d) in dis.iter().enumerate() {
         if heap.len() < k {
             heap.push(Reverse(KNNResult { idx: i, dis: d }));
         } else {
@@ -73,10 +106,12 @@ pub fn knn_hamming_array<const N: usize>(
 ) -> Vec<(usize, u32)> {
     // 考虑到 k 通常很小，为了最大化性能，此处开辟一个栈上的固定数组来存储 KNN 结果
     assert!(k <= 8, "k must be less than 8");
+    let mut all_dis = vec![0u32; vb.len()];
+    batch_hamming_into(va, vb, &mut all_dis);
+
     let mut dis = [u32::MAX; 8];
     let mut idx = [0; 8];
-    for (i, chunk) in vb.iter().enumerate() {
-        let d = hamming::<N>(va, chunk);
+    for (i, &d) in all_dis.iter().enumerate() {
         if d >= dis[0] {
             continue;
         }
@@ -98,18 +133,118 @@ pub fn knn_hamming_array<const N: usize>(
     idx.into_iter().zip(dis).filter(|(_, d)| *d != u32::MAX).rev().take(k).collect()
 }
 
+/// 计算 va 与一批候选描述符 vb 的汉明距离，写入等长的 `out`
+///
+/// 运行时检测 CPU 特性选择最快的实现：x86_64 上支持 AVX-512 VPOPCNTDQ 时用
+/// `_mm512_popcnt_epi64` 把每个 32 字节的候选描述符零扩展到 512 位寄存器后一次处理一个；否则若支持 AVX2，用 Muła 的
+/// 查表法做批量 popcount；两者都不可用时（包括非 x86_64 平台）退回 [`hamming_32`] 的标量
+/// 实现；`N != 32` 时退回逐个调用 [`hamming`] 的标量实现
+pub fn batch_hamming_into<const N: usize>(va: &[u8; N], vb: &[[u8; N]], out: &mut [u32]) {
+    assert_eq!(vb.len(), out.len());
+
+    if N == 32 {
+        // SAFETY: 刚确认 N == 32，`va`/`vb` 的内存布局与 `[u8; 32]` 完全一致
+        let va32: &[u8; 32] = unsafe { &*(va.as_ptr() as *const [u8; 32]) };
+        let vb32: &[[u8; 32]] =
+            unsafe { std::slice::from_raw_parts(vb.as_ptr() as *const [u8; 32], vb.len()) };
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::is_x86_feature_detected!("avx512f")
+                && std::is_x86_feature_detected!("avx512vpopcntdq")
+            {
+                // SAFETY: 上面已经检测过对应的 CPU 特性
+                unsafe { batch_hamming_avx512(va32, vb32, out) };
+                return;
+            }
+            if std::is_x86_feature_detected!("avx2") {
+                // SAFETY: 上面已经检测过对应的 CPU 特性
+                unsafe { batch_hamming_avx2(va32, vb32, out) };
+                return;
+            }
+        }
+
+        for (o, cand) in out.iter_mut().zip(vb32) {
+            *o = hamming_32(va32, cand);
+        }
+        return;
+    }
+
+    for (o, vb_i) in out.iter_mut().zip(vb) {
+        *o = hamming::<N>(va, vb_i);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vpopcntdq")]
+unsafe fn batch_hamming_avx512(va: &[u8; 32], vb: &[[u8; 32]], out: &mut [u32]) {
+    use std::arch::x86_64::*;
+
+    // 32 字节的描述符放进 512 位寄存器的低 256 位，高位补零不影响 popcount 结果
+    let qa = unsafe { _mm512_zextsi256_si512(_mm256_loadu_si256(va.as_ptr() as *const __m256i)) };
+    for (o, cand) in out.iter_mut().zip(vb) {
+        unsafe {
+            let cb = _mm512_zextsi256_si512(_mm256_loadu_si256(cand.as_ptr() as *const __m256i));
+            let x = _mm512_xor_si512(qa, cb);
+            let popcnt = _mm512_popcnt_epi64(x);
+            *o = _mm512_reduce_add_epi64(popcnt) as u32;
+        }
+    }
+}
+
+/// Muła 的 SSSE3/AVX2 nibble-lookup popcount：把每个字节拆成高低 4 位分别查表，
+/// 表中第 i 项是 i（0~15）的 popcount，两次查表结果相加后用 `_mm256_sad_epu8`
+/// 对着全零寄存器做字节求和，一次性把 32 字节水平加总进 4 个 64 位分段
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn batch_hamming_avx2(va: &[u8; 32], vb: &[[u8; 32]], out: &mut [u32]) {
+    use std::arch::x86_64::*;
+
+    unsafe {
+        let lookup = _mm256_setr_epi8(
+            0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4, 0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2,
+            3, 3, 4,
+        );
+        let low_mask = _mm256_set1_epi8(0x0f);
+        let qa = _mm256_loadu_si256(va.as_ptr() as *const __m256i);
+
+        for (o, cand) in out.iter_mut().zip(vb) {
+            let cb = _mm256_loadu_si256(cand.as_ptr() as *const __m256i);
+            let x = _mm256_xor_si256(qa, cb);
+            let lo = _mm256_and_si256(x, low_mask);
+            let hi = _mm256_and_si256(_mm256_srli_epi16(x, 4), low_mask);
+            let popcnt_lo = _mm256_shuffle_epi8(lookup, lo);
+            let popcnt_hi = _mm256_shuffle_epi8(lookup, hi);
+            let total = _mm256_add_epi8(popcnt_lo, popcnt_hi);
+            let sad = _mm256_sad_epu8(total, _mm256_setzero_si256());
+
+            let mut partial = [0u64; 4];
+            _mm256_storeu_si256(partial.as_mut_ptr() as *mut __m256i, sad);
+            *o = (partial[0] + partial[1] + partial[2] + partial[3]) as u32;
+        }
+    }
+}
+
 /// 批量计算 va 和 vb 的汉明距离，返回每个向量的 k 个最近邻居
+///
+/// 默认走 CPU + rayon 并行路径；开启 `gpu` feature 且运行时检测到可用显卡时，会转而把整批
+/// 查询一次性提交给 GPU 后端，在设备端算出全部距离并做 top-k 归约，两条路径返回的
+/// `(index, distance)` 近邻集合相同（距离相等的候选之间取舍可能不同）；GPU 路径的结果按距离升序
+/// 排列，CPU 路径不保证顺序
 pub fn batch_knn_hamming<const N: usize>(
     va: &[[u8; N]],
     vb: &[[u8; N]],
     k: usize,
 ) -> Vec<Vec<(usize, u32)>> {
-    let mut r = Vec::with_capacity(va.len());
-    for chunk in va.iter() {
-        let t = knn_hamming::<N>(chunk, vb, k);
-        r.push(t);
+    #[cfg(feature = "gpu")]
+    if crate::gpu::is_available() {
+        match crate::gpu::batch_knn_hamming_gpu::<N>(va, vb, k) {
+            Ok(result) => return result,
+            Err(e) => log::warn!("GPU 汉明距离计算失败，回退到 CPU：{e}"),
+        }
     }
-    r
+
+    va.par_iter().map(|chunk| knn_hamming_heap::<N>(chunk, vb, k)).collect()
 }
 
 #[cfg(test)]
@@ -179,4 +314,16 @@ mod tests {
         let vb = [0u8; 32];
         knn_hamming::<32>(&va, &[vb], 11); // 应该panic
     }
+
+    #[test]
+    fn test_batch_hamming_into_matches_scalar() {
+        let va = [0x5au8; 32];
+        let vb = [[0u8; 32], [255u8; 32], [0x5au8; 32], [0xa5u8; 32]];
+
+        let mut out = vec![0u32; vb.len()];
+        batch_hamming_into(&va, &vb, &mut out);
+
+        let expected: Vec<u32> = vb.iter().map(|v| hamming_32(&va, v)).collect();
+        assert_eq!(out, expected);
+    }
 }
@@ -1,20 +1,13 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
-use bytemuck::cast_slice;
 use hnsw_rs::prelude::*;
 
 struct DistHamming<const N: usize>;
 
 impl<const N: usize> Distance<u8> for DistHamming<N> {
     fn eval(&self, va: &[u8], vb: &[u8]) -> f32 {
-        let va: &[u64] = cast_slice(va);
-        let vb: &[u64] = cast_slice(vb);
-        let mut sum = 0;
-        for i in 0..N / 8 {
-            sum += (va[i] ^ vb[i]).count_ones();
-        }
-        sum as f32
+        crate::hamming::hamming::<N>(va, vb) as f32
     }
 }
 
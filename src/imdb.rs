@@ -1,6 +1,7 @@
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
-use std::time::Instant;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, Mutex as SyncMutex, RwLock};
+use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
 use futures::prelude::*;
@@ -10,11 +11,20 @@ use rayon::prelude::*;
 use tokio::sync::Mutex;
 use tokio::task::{block_in_place, spawn_blocking};
 
+use crate::bktree::BkTree;
+use crate::bloom::Bloom;
 use crate::config::{ConfDir, ScoreType};
 use crate::db::*;
+use crate::dedup::{self, LshIndex};
 use crate::faiss::{FaissIndex, FaissSearchParams, Neighbor};
 use crate::hnsw::HNSW;
 use crate::index::IndexManager;
+use crate::invlists::InvertedListsHandle;
+use crate::ivf::compact_ondisk_invlists;
+use crate::ivf::quantizer::{HnswQuantizer, Quantizer};
+use crate::lru::LruCache;
+use crate::minhash::{MinHashSeeds, estimate_similarity};
+use crate::ranking::{self, Candidate, CriterionKind};
 use crate::utils::{self, ImageHash, pb_style};
 
 #[derive(Debug, Clone)]
@@ -25,18 +35,52 @@ pub struct BuildOptions {
     pub ef_search: usize,
 }
 
+/// 是否是可以直接用 64 位 Hamming 距离比较的感知哈希算法（dhash/phash/ahash），
+/// 只有这类算法才能复用 phash HNSW/BK-tree 去重索引；blake3 是内容哈希，没有"相似"概念
+fn is_64bit_hamming_hash(hash: Option<ImageHash>) -> bool {
+    matches!(hash, Some(ImageHash::Dhash) | Some(ImageHash::Phash) | Some(ImageHash::Ahash))
+}
+
+/// [`IMDB::search_timed`] 各阶段耗时，用于 `bench` 工具上报结构化的性能数据，
+/// 避免依赖 `debug!` 日志
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchTiming {
+    /// faiss 近邻搜索耗时
+    pub index_search: Duration,
+    /// 近邻分组统计与排序耗时
+    pub process_group: Duration,
+}
+
 #[derive(Debug, Clone)]
 pub struct IMDBBuilder<const N: usize> {
     conf_dir: ConfDir,
     wal: bool,
     cache: bool,
     score_type: ScoreType,
+    criteria: Vec<CriterionKind>,
     hash: Option<ImageHash>,
+    minhash_h: Option<usize>,
+    dedup: Option<(usize, usize, usize)>,
+    invlists_addr: Option<String>,
+    bktree: bool,
+    cache_lru: Option<usize>,
 }
 
 impl<const N: usize> IMDBBuilder<N> {
     pub fn new(conf_dir: ConfDir) -> Self {
-        Self { conf_dir, wal: true, cache: false, score_type: ScoreType::Wilson, hash: None }
+        Self {
+            conf_dir,
+            wal: true,
+            cache: false,
+            score_type: ScoreType::Wilson,
+            criteria: vec![],
+            hash: None,
+            minhash_h: None,
+            dedup: None,
+            invlists_addr: None,
+            bktree: false,
+            cache_lru: None,
+        }
     }
 
     /// 数据库是否开启 WAL，开启会影响删除
@@ -45,22 +89,67 @@ impl<const N: usize> IMDBBuilder<N> {
         self
     }
 
-    /// 是否使用缓存来加速 id 查询，会导致第一次查询速度变慢
+    /// 是否使用缓存来加速 id 查询，会把整张 `total_vector_count` 表加载进内存，
+    /// 对于大型语料库会占用较多内存且首次查询会因为一次性加载而停顿；
+    /// 与 [`Self::cache_lru`] 互斥，同时设置时以 [`Self::cache_lru`] 为准
     pub fn cache(mut self, cache: bool) -> Self {
         self.cache = cache;
         self
     }
 
+    /// 使用固定容量的 LRU 缓存来加速向量 ID -> 图片 ID 的查询，内存占用不随语料库大小增长，
+    /// 命中率取决于查询的局部性（相邻向量大概率属于同一批近邻结果）；未命中时退化为单次 SQL 查询
+    pub fn cache_lru(mut self, capacity: usize) -> Self {
+        self.cache_lru = Some(capacity);
+        self
+    }
+
     pub fn score_type(mut self, score_type: ScoreType) -> Self {
         self.score_type = score_type;
         self
     }
 
+    /// 设置多阶段排名流水线，为空时退化为 [`Self::score_type`]
+    pub fn criteria(mut self, criteria: Vec<CriterionKind>) -> Self {
+        self.criteria = criteria;
+        self
+    }
+
     pub fn hash(mut self, hash: ImageHash) -> Self {
         self.hash = Some(hash);
         self
     }
 
+    /// 启用 MinHash 粗筛，`h` 为签名长度（哈希函数数量），仅在首次生成签名种子时生效
+    pub fn minhash(mut self, h: usize) -> Self {
+        self.minhash_h = Some(h);
+        self
+    }
+
+    /// 启用基于描述符集合 Bottom-s MinHash 草图的近似重复检测
+    ///
+    /// `s` 为草图长度，草图会被切分成 `bands` 个条带、每条带 `rows` 个哈希值用于 LSH 分桶，
+    /// 两张图片只要有任意一个条带的哈希相同就会成为候选，候选再用精确的 Jaccard 相似度复核
+    pub fn dedup(mut self, s: usize, bands: usize, rows: usize) -> Self {
+        self.dedup = Some((s, bands, rows));
+        self
+    }
+
+    /// 倒排列表后端地址，见 [`InvertedListsHandle::from_addr`]，为空时沿用现有的磁盘倒排列表格式
+    pub fn invlists_addr(mut self, addr: String) -> Self {
+        self.invlists_addr = Some(addr);
+        self
+    }
+
+    /// 额外启用 [`crate::bktree::BkTree`] 精确去重索引，与 HNSW 的 [`Self::hash`] 配合使用
+    ///
+    /// HNSW 是近似最近邻图，可能漏召半径内的匹配；启用此项后 [`IMDB::check_hash`] 会
+    /// 优先用 BK-tree 做精确查询，保证不漏召，代价是树深度较大时查询会慢于 HNSW
+    pub fn bktree(mut self, bktree: bool) -> Self {
+        self.bktree = bktree;
+        self
+    }
+
     pub async fn open(self) -> Result<IMDB<N>> {
         if !self.conf_dir.path().exists() {
             std::fs::create_dir_all(self.conf_dir.path())?;
@@ -76,9 +165,12 @@ impl<const N: usize> IMDBBuilder<N> {
             if self.hash.is_some() && self.hash.unwrap() != old_hash {
                 return Err(anyhow!("哈希算法不一致"));
             }
+        } else if let Some(hash) = self.hash {
+            // 首次建库，记录本次使用的哈希算法，避免日后误用其他算法添加图片
+            crud::set_hash_config(&db, hash).await?;
         }
 
-        let pindex = if self.hash == Some(ImageHash::Dhash) {
+        let pindex = if is_64bit_hamming_hash(self.hash) {
             let mut index = if self.conf_dir.path().join("phash.hnsw.graph").exists() {
                 HNSW::load(self.conf_dir.path())?
             } else {
@@ -106,14 +198,104 @@ impl<const N: usize> IMDBBuilder<N> {
             None
         };
 
+        let bkindex = if self.bktree && is_64bit_hamming_hash(self.hash) {
+            let mut index = if self.conf_dir.path().join("phash.bktree").exists() {
+                BkTree::open(self.conf_dir.path())?
+            } else {
+                BkTree::new(self.conf_dir.path())?
+            };
+
+            if let Ok((count, _)) = crud::get_count(&db).await {
+                if count != index.ntotal() as i64 {
+                    warn!(
+                        "BK-tree 索引大小不一致（{} != {}），正在重新构建……",
+                        count,
+                        index.ntotal()
+                    );
+                    index = BkTree::new(self.conf_dir.path())?;
+                    let hashes = crud::get_all_hash(&db).await?;
+                    debug!("正在添加 {} 条向量到 BK-tree 索引……", hashes.len());
+                    for (id, hash) in hashes {
+                        index.add(&hash, id as usize);
+                    }
+                    debug!("BK-tree 索引添加完成，大小：{}", index.ntotal());
+                }
+            }
+            Some(index)
+        } else {
+            None
+        };
+
+        let bloom_path = self.conf_dir.index_bloom();
+        let mut bloom = Bloom::open(&bloom_path)?;
+        if !bloom_path.exists() {
+            if let Ok(hashes) = crud::get_all_image_hash(&db).await {
+                debug!("正在添加 {} 条图片哈希到布隆过滤器……", hashes.len());
+                for hash in &hashes {
+                    bloom.insert(hash);
+                }
+            }
+        }
+
+        let tombstones = crud::get_tombstones(&db).await.unwrap_or_default();
+
+        let ingested: HashSet<String> =
+            crud::get_all_ingested(&db).await.unwrap_or_default().into_iter().collect();
+
+        let minhash = match self.minhash_h {
+            Some(h) if self.conf_dir.quantizer().exists() => {
+                let quantizer = HnswQuantizer::<N>::open(self.conf_dir.quantizer())?;
+                let seeds = MinHashSeeds::open_or_create(self.conf_dir.minhash_seeds(), h)?;
+                let signatures = crud::get_all_minhash(&db)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|r| (r.image_id, bytemuck::cast_slice::<u8, u32>(&r.signature).to_vec()))
+                    .collect();
+                Some(MinhashState { quantizer, seeds, signatures: RwLock::new(signatures) })
+            }
+            Some(_) => {
+                warn!("量化器文件不存在，MinHash 粗筛已禁用");
+                None
+            }
+            None => None,
+        };
+
+        let dup_sketch = if let Some((s, bands, rows)) = self.dedup {
+            let records = crud::get_all_dup_sketch(&db).await.unwrap_or_default();
+            let mut lsh = LshIndex::new(bands, rows);
+            let mut sketches = HashMap::new();
+            for record in records {
+                let sketch: Vec<u64> = bytemuck::cast_slice(&record.sketch).to_vec();
+                lsh.insert(record.image_id, &sketch);
+                sketches.insert(record.image_id, sketch);
+            }
+            Some(DupSketchState { s, lsh: RwLock::new(lsh), sketches: RwLock::new(sketches) })
+        } else {
+            None
+        };
+
+        // cache_lru 与 cache 互斥，同时设置时以 cache_lru 为准
+        let id_cache = self.cache_lru.map(|capacity| SyncMutex::new(LruCache::new(capacity)));
+        let cache = self.cache && id_cache.is_none();
+
         let imdb = IMDB {
             db,
             conf_dir: self.conf_dir.clone(),
             total_vector_count: RwLock::new(vec![]),
-            cache: self.cache,
+            cache,
+            id_cache,
             index: IndexManager::new(self.conf_dir),
             score_type: self.score_type,
+            criteria: self.criteria,
             pindex: pindex.map(Arc::new),
+            bkindex: bkindex.map(Arc::new),
+            bloom: RwLock::new(bloom),
+            tombstones: RwLock::new(tombstones),
+            minhash,
+            dup_sketch,
+            ingested: RwLock::new(ingested),
+            invlists_addr: self.invlists_addr,
         };
 
         imdb.load_total_vector_count().await?;
@@ -121,6 +303,24 @@ impl<const N: usize> IMDBBuilder<N> {
     }
 }
 
+/// MinHash 粗筛所需的状态：量化器、哈希函数种子与内存中的签名缓存
+struct MinhashState<const N: usize> {
+    quantizer: HnswQuantizer<N>,
+    seeds: MinHashSeeds,
+    /// 图片 ID -> 签名，随着 [`IMDB::build_index`] 的执行增量更新
+    signatures: RwLock<HashMap<i64, Vec<u32>>>,
+}
+
+/// 描述符集合近似重复检测所需的状态：草图长度、LSH 分桶索引与内存中的草图缓存
+struct DupSketchState {
+    /// 草图长度（保留的最小哈希数量）
+    s: usize,
+    /// LSH 分桶索引，随着 [`IMDB::add_image`] 的执行增量更新
+    lsh: RwLock<LshIndex>,
+    /// 图片 ID -> 草图，用于对 LSH 候选复核精确的 Jaccard 相似度
+    sketches: RwLock<HashMap<i64, Vec<u64>>>,
+}
+
 pub struct IMDB<const N: usize> {
     conf_dir: ConfDir,
     db: Database,
@@ -128,12 +328,30 @@ pub struct IMDB<const N: usize> {
     cache: bool,
     /// 每张图片特征点 ID 的累加数量，用于加速计算
     total_vector_count: RwLock<Vec<i64>>,
+    /// 向量 ID -> 图片 ID 的固定容量 LRU 缓存，为 `None` 时表示未启用 [`IMDBBuilder::cache_lru`]
+    id_cache: Option<SyncMutex<LruCache<i64, i64>>>,
     /// 特征点索引
     index: IndexManager<N>,
     /// phash 索引
     pindex: Option<Arc<HNSW>>,
+    /// BK-tree 精确去重索引，为 `None` 时表示未启用 [`IMDBBuilder::bktree`]
+    bkindex: Option<Arc<BkTree>>,
     /// 评分方式
     score_type: ScoreType,
+    /// 多阶段排名流水线，为空时退化为 `score_type`
+    criteria: Vec<CriterionKind>,
+    /// 图片哈希布隆过滤器，用于在精确查询前快速排除不存在的图片
+    bloom: RwLock<Bloom>,
+    /// 已删除但尚未 compact 的特征 ID 区间，搜索时需要据此过滤结果
+    tombstones: RwLock<Vec<TombstoneRecord>>,
+    /// MinHash 粗筛状态，为 `None` 时表示未启用
+    minhash: Option<MinhashState<N>>,
+    /// 描述符集合近似重复检测状态，为 `None` 时表示未启用
+    dup_sketch: Option<DupSketchState>,
+    /// 已经通过 `add_image` 完整入库的来源路径，用于恢复导入时跳过
+    ingested: RwLock<HashSet<String>>,
+    /// 可插拔倒排列表后端地址，见 [`InvertedListsHandle::from_addr`]，为空时沿用现有的磁盘倒排列表格式
+    invlists_addr: Option<String>,
 }
 
 impl<const N: usize> IMDB<N> {
@@ -150,19 +368,71 @@ impl<const N: usize> IMDB<N> {
         crud::add_vector_stats(&mut *tx, id, descriptors.len() as i64).await?;
         // 尽快提交事务，避免锁住数据库
         tx.commit().await?;
+        self.bloom.write().unwrap().insert(hash);
         if let Some(index) = self.pindex.clone() {
             let hash = hash.to_vec();
             spawn_blocking(move || index.add(&hash, id as usize)).await?;
         }
+        if let Some(index) = self.bkindex.clone() {
+            let hash = hash.to_vec();
+            spawn_blocking(move || index.add(&hash, id as usize)).await?;
+        }
+        if let Some(dup) = &self.dup_sketch {
+            let sketch = dedup::sketch(descriptors, dup.s);
+            if !sketch.is_empty() {
+                crud::upsert_dup_sketch(&self.db, id, bytemuck::cast_slice(&sketch)).await?;
+                dup.lsh.write().unwrap().insert(id, &sketch);
+                dup.sketches.write().unwrap().insert(id, sketch);
+            }
+        }
         Ok(id)
     }
 
+    /// 对一组描述符计算 Bottom-s MinHash 草图，通过 LSH 分桶找出候选近似重复图片，
+    /// 再对候选复核精确的 Jaccard 相似度，返回相似度超过 `threshold` 且最相似的图片 ID
+    ///
+    /// 未启用 [`IMDBBuilder::dedup`] 时始终返回 `None`
+    pub fn check_duplicate_descriptors(
+        &self,
+        descriptors: &[[u8; N]],
+        threshold: f32,
+    ) -> Option<i64> {
+        let dup = self.dup_sketch.as_ref()?;
+        let sketch = dedup::sketch(descriptors, dup.s);
+        if sketch.is_empty() {
+            return None;
+        }
+
+        let candidates = dup.lsh.read().unwrap().candidates(&sketch);
+        let sketches = dup.sketches.read().unwrap();
+        candidates
+            .into_iter()
+            .filter_map(|id| sketches.get(&id).map(|s| (id, dedup::jaccard(&sketch, s))))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id)
+    }
+
     /// 检查图片是否存在
     pub async fn check_hash(&self, hash: &[u8], distance: u32) -> Result<Option<i64>> {
-        if let Some(id) = crud::check_image_hash(&self.db, hash).await? {
-            return Ok(Some(id));
+        // 布隆过滤器为阴性时，图片一定不存在，可以跳过一次数据库查询
+        if self.bloom.read().unwrap().contains(hash) {
+            if let Some(id) = crud::check_image_hash(&self.db, hash).await? {
+                return Ok(Some(id));
+            }
         }
         // 由于 phash 检查较慢，因此放到后面检查
+        // 启用了 BK-tree 时优先用它做精确查询，保证半径内的匹配不会被 HNSW 的近似搜索漏召
+        if let Some(index) = self.bkindex.clone() {
+            if distance > 0 {
+                let hash = hash.to_vec();
+                let result = spawn_blocking(move || index.search(&hash, distance)).await?;
+                if let Some((id, _)) = result.into_iter().min_by_key(|(_, d)| *d) {
+                    return Ok(Some(id as i64));
+                }
+            }
+            return Ok(None);
+        }
         if let Some(index) = self.pindex.clone() {
             if distance > 0 {
                 let hash = hash.to_vec();
@@ -175,11 +445,141 @@ impl<const N: usize> IMDB<N> {
         Ok(None)
     }
 
+    /// 判断一个来源路径是否已经通过 [`Self::mark_ingested`] 标记为完整入库
+    ///
+    /// 只用于恢复导入时在读取文件/归档成员字节之前跳过已处理的条目，不代表图片本身
+    /// 一定还存在于数据库中（例如被 [`Self::delete_image`] 删除后也不会清除这个标记）
+    pub fn is_ingested(&self, path: &str) -> bool {
+        self.ingested.read().unwrap().contains(path)
+    }
+
+    /// 标记一个来源路径已经通过 [`Self::add_image`] 完整入库
+    pub async fn mark_ingested(&self, path: &str) -> Result<()> {
+        crud::mark_ingested(&self.db, path).await?;
+        self.ingested.write().unwrap().insert(path.to_owned());
+        Ok(())
+    }
+
+    /// 清空入库进度标记，用于 `--force-rescan` 放弃之前的导入进度重新开始
+    pub async fn clear_ingested(&self) -> Result<()> {
+        crud::clear_ingested(&self.db).await?;
+        self.ingested.write().unwrap().clear();
+        Ok(())
+    }
+
+    /// 将一个索引任务加入持久化队列，返回任务 ID
+    pub async fn enqueue_task(&self, kind: &str, payload: &str) -> Result<i64> {
+        Ok(crud::enqueue_task(&self.db, kind, payload).await?)
+    }
+
+    /// 查询单个任务的当前状态
+    pub async fn get_task(&self, id: i64) -> Result<Option<TaskRecord>> {
+        Ok(crud::get_task(&self.db, id).await?)
+    }
+
+    /// 取出最早入队、尚未开始处理的任务
+    pub async fn fetch_next_task(&self) -> Result<Option<TaskRecord>> {
+        Ok(crud::fetch_next_enqueued_task(&self.db).await?)
+    }
+
+    /// 将任务标记为处理中
+    pub async fn mark_task_processing(&self, id: i64) -> Result<()> {
+        Ok(crud::mark_task_processing(&self.db, id).await?)
+    }
+
+    /// 更新任务的进度计数
+    pub async fn update_task_progress(&self, id: i64, done: i64, total: Option<i64>) -> Result<()> {
+        Ok(crud::update_task_progress(&self.db, id, done, total).await?)
+    }
+
+    /// 当前已入库的图片数量，用于近似反映长耗时任务（如 `AddDirectory`）的实时进度
+    pub async fn image_count(&self) -> Result<i64> {
+        let (image_count, _) = crud::get_count(&self.db).await?;
+        Ok(image_count)
+    }
+
+    /// 将任务标记为执行成功
+    pub async fn mark_task_succeeded(&self, id: i64) -> Result<()> {
+        Ok(crud::mark_task_succeeded(&self.db, id).await?)
+    }
+
+    /// 将任务标记为执行失败，并记录错误信息
+    pub async fn mark_task_failed(&self, id: i64, error: &str) -> Result<()> {
+        Ok(crud::mark_task_failed(&self.db, id, error).await?)
+    }
+
+    /// 统计尚在排队、还未开始处理的任务数量，用于上报队列深度指标
+    pub async fn count_enqueued_tasks(&self) -> Result<i64> {
+        Ok(crud::count_enqueued_tasks(&self.db).await?)
+    }
+
+    /// 删除一张图片及其所有特征向量
+    ///
+    /// 回收的特征 ID 区间只记为墓碑，倒排列表本身不会立即重写，
+    /// 在此之前的搜索结果需要靠 [`Self::is_tombstoned`] 过滤，直到下一次 [`Self::compact`]
+    pub async fn delete_image(&self, id: i64) -> Result<()> {
+        crud::delete_image(&self.db, id).await?;
+        let tombstones = crud::get_tombstones(&self.db).await?;
+        *self.tombstones.write().unwrap() = tombstones;
+        Ok(())
+    }
+
+    /// 判断一个特征 ID 是否落在已删除但尚未 compact 的区间内
+    fn is_tombstoned(&self, id: i64) -> bool {
+        self.tombstones.read().unwrap().iter().any(|t| id >= t.start_id && id < t.end_id)
+    }
+
+    /// 压缩数据库与倒排列表，彻底清理已删除的特征向量
+    ///
+    /// 重新计算连续的 `total_vector_count`，并将存活的特征 ID 重新映射后重写倒排列表文件
+    pub async fn compact(&self) -> Result<()> {
+        let tombstones = crud::get_tombstones(&self.db).await?;
+        if tombstones.is_empty() {
+            return Ok(());
+        }
+
+        let invlists_path = self.conf_dir.invlists();
+        if invlists_path.exists() {
+            let is_dead = |id: u64| {
+                tombstones.iter().any(|t| id as i64 >= t.start_id && (id as i64) < t.end_id)
+            };
+            let remap = |id: u64| {
+                let removed_before = tombstones
+                    .iter()
+                    .filter(|t| t.end_id as u64 <= id)
+                    .map(|t| (t.end_id - t.start_id) as u64)
+                    .sum::<u64>();
+                id - removed_before
+            };
+            block_in_place(|| compact_ondisk_invlists::<N>(&invlists_path, is_dead, remap))?;
+        }
+
+        crud::compact_vector_stats(&self.db).await?;
+        crud::clear_tombstones(&self.db).await?;
+        *self.tombstones.write().unwrap() = vec![];
+        Ok(())
+    }
+
     /// 更新图片路径
     pub async fn update_image_path(&self, id: i64, path: &str) -> Result<()> {
         Ok(crud::update_image_path(&self.db, id, path).await?)
     }
 
+    /// 根据图片 ID 获取其路径，用于在命中近似重复时向用户展示匹配到的已有图片
+    pub async fn get_image_path(&self, id: i64) -> Result<String> {
+        Ok(crud::get_image_path(&self.db, id).await?)
+    }
+
+    /// 批量添加/更新图片标签
+    pub async fn add_tags(&self, id: i64, tags: &[(String, String)]) -> Result<()> {
+        Ok(crud::add_tags(&self.db, id, tags).await?)
+    }
+
+    /// 获取一张图片的所有标签
+    pub async fn get_tags(&self, id: i64) -> Result<Vec<TagRecord>> {
+        Ok(crud::get_tags(&self.db, id).await?)
+    }
+
     /// 追加图片路径
     pub async fn append_image_path(&self, id: i64, path: &str) -> Result<bool> {
         Ok(crud::append_image_path(&self.db, id, path).await?)
@@ -215,8 +615,23 @@ impl<const N: usize> IMDB<N> {
     }
 
     /// 获取用于搜索的索引
-    pub fn get_index(&self, mmap: bool) -> Result<FaissIndex<N>> {
-        self.index.get_aggregate_index(mmap)
+    ///
+    /// `ondisk` 为 true 时以磁盘倒排列表模式打开，常驻内存的部分只有索引骨架，
+    /// 适合索引大小超出内存容量的场景，与 `mmap` 互斥时以 `ondisk` 为准
+    pub fn get_index(&self, mmap: bool, ondisk: bool) -> Result<FaissIndex<N>> {
+        self.index.get_aggregate_index(mmap, ondisk)
+    }
+
+    /// 按构建时设置的 `invlists_addr` 解析出可插拔倒排列表后端，未设置时返回 `None`
+    pub async fn invlists_handle(
+        &self,
+        nlist: u32,
+        code_size: u32,
+    ) -> Result<Option<InvertedListsHandle>> {
+        match &self.invlists_addr {
+            Some(addr) => Ok(Some(InvertedListsHandle::from_addr(addr, nlist, code_size).await?)),
+            None => Ok(None),
+        }
     }
 
     /// 在索引中搜索多组描述符，返回 Vec<Vec<(分数, 图片路径)>>
@@ -229,6 +644,15 @@ impl<const N: usize> IMDB<N> {
     /// * `max_distance` - 最大距离
     /// * `max_result` - 最大结果数量
     /// * `params` - 搜索参数
+    /// * `shards` - 当 `index` 由多个分片索引通过 HStack 联合而成时，按偏移升序排列的
+    ///   `(分片名称, 起始偏移)` 列表，用于在结果中标记命中来自哪个分片；传入空切片表示不标记
+    /// * `tags` - 标签过滤条件，传入 `None` 表示不做标签过滤
+    /// * `minhash_top_n` - 启用 MinHash 粗筛时保留的候选图片数量，传入 `None` 表示不做粗筛
+    ///   （未调用过 [`IMDBBuilder::minhash`] 时此参数无效）
+    /// * `ratio_test` - 启用 Lowe's ratio test 时使用的比率（通常取 0.7），传入 `None` 表示
+    ///   改用固定的 `max_distance` 阈值；每个查询描述符只有在最近邻距离小于
+    ///   `ratio * 次近邻距离` 时才计为一次命中，只返回不足两个近邻时直接放行
+    #[allow(clippy::too_many_arguments)]
     pub async fn search(
         &self,
         index: Arc<FaissIndex<N>>,
@@ -237,7 +661,44 @@ impl<const N: usize> IMDB<N> {
         max_distance: u32,
         max_result: usize,
         params: FaissSearchParams,
+        shards: &[(String, i64)],
+        tags: Option<&TagFilter>,
+        minhash_top_n: Option<usize>,
+        ratio_test: Option<f32>,
     ) -> Result<Vec<Vec<(f32, String)>>> {
+        let (result, _) = self
+            .search_timed(
+                index,
+                descriptors,
+                knn,
+                max_distance,
+                max_result,
+                params,
+                shards,
+                tags,
+                minhash_top_n,
+                ratio_test,
+            )
+            .await?;
+        Ok(result)
+    }
+
+    /// 与 [`Self::search`] 等价，额外返回 faiss 近邻搜索与近邻分组处理各自的耗时，
+    /// 用于 `bench` 工具上报结构化的性能数据，而不是翻找 `debug!` 日志
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_timed(
+        &self,
+        index: Arc<FaissIndex<N>>,
+        descriptors: &[Vec<[u8; N]>],
+        knn: usize,
+        max_distance: u32,
+        max_result: usize,
+        params: FaissSearchParams,
+        shards: &[(String, i64)],
+        tags: Option<&TagFilter>,
+        minhash_top_n: Option<usize>,
+        ratio_test: Option<f32>,
+    ) -> Result<(Vec<Vec<(f32, String)>>, SearchTiming)> {
         let mat = descriptors.concat();
 
         info!(
@@ -248,13 +709,40 @@ impl<const N: usize> IMDB<N> {
             params
         );
         if mat.is_empty() {
-            return Ok(vec![vec![]; descriptors.len()]);
+            return Ok((vec![vec![]; descriptors.len()], SearchTiming::default()));
         }
 
+        // 预过滤：限制参与计分的候选图片 ID 集合
+        let mut allowed_images: Option<HashSet<i64>> = match tags.map(|f| f.pre.as_slice()) {
+            Some(pre) if !pre.is_empty() => {
+                Some(crud::find_image_ids_by_tags(&self.db, pre).await?.into_iter().collect())
+            }
+            _ => None,
+        };
+
+        // MinHash 粗筛：用所有查询描述符对应的视觉词集合构造一个签名，与已缓存的签名比较相似度，
+        // 只保留相似度最高的 top_n 张图片；与标签预过滤是交集关系
+        if let (Some(top_n), Some(minhash)) = (minhash_top_n, &self.minhash) {
+            if let Some(candidates) = block_in_place(|| self.minhash_candidates(minhash, &mat, top_n))? {
+                allowed_images = Some(match allowed_images {
+                    Some(allowed) => allowed.intersection(&candidates).copied().collect(),
+                    None => candidates,
+                });
+            }
+        }
+        // 后过滤：限制最终返回的图片 ID 集合
+        let post_images: Option<HashSet<i64>> = match tags.map(|f| f.post.as_slice()) {
+            Some(post) if !post.is_empty() => {
+                Some(crud::find_image_ids_by_tags(&self.db, post).await?.into_iter().collect())
+            }
+            _ => None,
+        };
+
         let mut instant = Instant::now();
 
         let neighbors = spawn_blocking(move || index.search(&mat, knn, Some(params))).await?;
-        debug!("搜索耗时    ：{}ms", instant.elapsed().as_millis());
+        let index_search = instant.elapsed();
+        debug!("搜索耗时    ：{}ms", index_search.as_millis());
         instant = Instant::now();
 
         let mut result = vec![];
@@ -262,57 +750,147 @@ impl<const N: usize> IMDB<N> {
         let mut cur;
         for item in descriptors {
             (cur, res) = res.split_at(item.len());
-            result.push(self.process_neighbor_group(cur, max_distance as i32, max_result).await?);
+            result.push(
+                self.process_neighbor_group(
+                    cur,
+                    max_distance as i32,
+                    max_result,
+                    shards,
+                    allowed_images.as_ref(),
+                    post_images.as_ref(),
+                    ratio_test,
+                )
+                .await?,
+            );
         }
 
-        debug!("处理结果耗时：{:.2}ms", instant.elapsed().as_millis());
+        let process_group = instant.elapsed();
+        debug!("处理结果耗时：{:.2}ms", process_group.as_millis());
 
-        Ok(result)
+        Ok((result, SearchTiming { index_search, process_group }))
+    }
+
+    /// 根据查询描述符计算 MinHash 签名，并返回与其最相似的 top_n 张图片的 ID 集合
+    ///
+    /// 查询图片没有任何描述符可以量化为视觉词（或量化器没有命中任何中心点）时返回 `None`，
+    /// 表示无法进行粗筛，调用方应当跳过这一步，而不是误当作“没有任何候选图片”
+    fn minhash_candidates(
+        &self,
+        minhash: &MinhashState<N>,
+        mat: &[[u8; N]],
+        top_n: usize,
+    ) -> Result<Option<HashSet<i64>>> {
+        let words = minhash.quantizer.search(mat, 1)?;
+        let Some(query_sig) = minhash.seeds.signature(&words) else {
+            return Ok(None);
+        };
+
+        let signatures = minhash.signatures.read().unwrap();
+        let mut ranked = signatures
+            .iter()
+            .map(|(&id, sig)| (estimate_similarity(&query_sig, sig), id))
+            .collect::<Vec<_>>();
+        ranked.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        ranked.truncate(top_n);
+
+        Ok(Some(ranked.into_iter().map(|(_, id)| id).collect()))
     }
 
     /// 处理一个搜索结果分组
+    #[allow(clippy::too_many_arguments)]
     async fn process_neighbor_group(
         &self,
         neighbors: &[Vec<Neighbor>],
         max_distance: i32,
         max_result: usize,
+        shards: &[(String, i64)],
+        allowed_images: Option<&HashSet<i64>>,
+        post_images: Option<&HashSet<i64>>,
+        ratio_test: Option<f32>,
     ) -> Result<Vec<(f32, String)>> {
+        let filtered;
+        let neighbors = match ratio_test {
+            Some(ratio) => {
+                filtered = apply_ratio_test(neighbors, ratio);
+                filtered.as_slice()
+            }
+            None => neighbors,
+        };
+
         let counter = Mutex::new(HashMap::new());
 
-        // 遍历所有结果，并统计每个图片 ID 的出现次数
-        stream::iter(neighbors.iter().flatten())
-            .filter(|neighbor| future::ready(neighbor.distance <= max_distance))
-            .for_each(|neighbor| async {
+        // 遍历所有结果，并统计每个图片 ID 的出现次数；顺带记录该图片命中的分片（如果有）
+        stream::iter(neighbors.iter().enumerate().flat_map(|(fi, ns)| ns.iter().map(move |n| (fi, n))))
+            .filter(|(_, neighbor)| {
+                future::ready(
+                    neighbor.distance <= max_distance && !self.is_tombstoned(neighbor.index),
+                )
+            })
+            .for_each(|(fi, neighbor)| async move {
                 if let Ok(id) = self.find_image_id(neighbor.index).await {
+                    if allowed_images.is_some_and(|allowed| !allowed.contains(&id)) {
+                        return;
+                    }
+                    let shard = shard_name_for(shards, neighbor.index);
                     let mut counter = counter.lock().await;
-                    counter
-                        .entry(id)
-                        .or_insert_with(Vec::new)
-                        .push(1. - neighbor.distance as f32 / 256.);
+                    let entry = counter.entry(id).or_insert_with(|| Candidate {
+                        image_id: id,
+                        shard,
+                        scores: Vec::new(),
+                        query_indices: Vec::new(),
+                    });
+                    entry.scores.push(1. - neighbor.distance as f32 / 256.);
+                    entry.query_indices.push(fi);
                 }
             })
             .await;
 
-        // 计算得分，并取前 10 个结果
-        let counter = counter.into_inner();
-        let mut result = match self.score_type {
-            ScoreType::Wilson => counter
-                .into_iter()
-                .map(|(id, scores)| (100. * utils::wilson_score(&scores), id))
-                .collect::<Vec<_>>(),
-            ScoreType::Count => counter
-                .into_iter()
-                .map(|(id, scores)| (scores.len() as f32, id))
-                .collect::<Vec<_>>(),
+        // 没有配置排名流水线时，沿用 score_type 的单一评分方式，并用容量为 max_result 的
+        // 小顶堆做 top-k 选择：每个候选算分、应用后过滤后压入堆，超出容量时弹出分数最低的
+        // 一个，把排序成本从 O(n log n) 降到 O(n log k)；配置了流水线时维持原来的全量排序，
+        // 展示分数取流水线第一条（最高优先级）规则的打分
+        let candidates = counter.into_inner().into_values().collect::<Vec<_>>();
+        let mut result = if self.criteria.is_empty() {
+            let score_type = self.score_type;
+            let score_of = |c: &Candidate| match score_type {
+                ScoreType::Wilson => 100. * utils::wilson_score(&c.scores),
+                ScoreType::Count => c.scores.len() as f32,
+            };
+
+            let mut heap: BinaryHeap<Reverse<(Score, i64, Option<String>)>> =
+                BinaryHeap::with_capacity(max_result + 1);
+            for c in candidates {
+                if post_images.is_some_and(|post| !post.contains(&c.image_id)) {
+                    continue;
+                }
+                heap.push(Reverse((Score(score_of(&c)), c.image_id, c.shard)));
+                if heap.len() > max_result {
+                    heap.pop();
+                }
+            }
+            heap.into_sorted_vec().into_iter().map(|Reverse((Score(s), id, shard))| (s, id, shard)).collect::<Vec<_>>()
+        } else {
+            let pipeline = ranking::build_pipeline(&self.criteria);
+            let ranked = ranking::run_pipeline(candidates, &pipeline);
+            let primary = pipeline.into_iter().next().unwrap();
+            ranked.into_iter().map(|c| (primary.score(&c), c.image_id, c.shard)).collect::<Vec<_>>()
         };
-        result.sort_unstable_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        if let Some(post_images) = post_images {
+            result.retain(|(_, id, _)| post_images.contains(id));
+        }
         result.truncate(max_result);
 
-        // 查询实际的图片路径
+        // 查询实际的图片路径，如果命中了某个分片，则在路径前加上 "分片名:" 前缀
         let futures = result
             .into_iter()
-            .map(|(score, id)| async move {
-                crud::get_image_path(&self.db, id).await.map(|path| (score, path))
+            .map(|(score, id, shard)| async move {
+                crud::get_image_path(&self.db, id).await.map(|path| {
+                    let path = match shard {
+                        Some(shard) => format!("{shard}:{path}"),
+                        None => path,
+                    };
+                    (score, path)
+                })
             })
             .collect::<Vec<_>>();
         let result = futures::future::try_join_all(futures).await?;
@@ -321,13 +899,22 @@ impl<const N: usize> IMDB<N> {
 
     /// 根据向量 ID 查找图片 ID
     async fn find_image_id(&self, id: i64) -> Result<i64> {
-        if !self.cache {
-            Ok(crud::get_image_id_by_vector_id(&self.db, id).await?)
-        } else {
+        if self.cache {
             let lock = self.total_vector_count.read().unwrap();
             let index = lock.partition_point(|&x| x < id) + 1;
-            Ok(index as i64)
+            return Ok(index as i64);
         }
+
+        if let Some(id_cache) = &self.id_cache {
+            if let Some(image_id) = id_cache.lock().unwrap().get(&id) {
+                return Ok(*image_id);
+            }
+            let image_id = crud::get_image_id_by_vector_id(&self.db, id).await?;
+            id_cache.lock().unwrap().put(id, image_id);
+            return Ok(image_id);
+        }
+
+        Ok(crud::get_image_id_by_vector_id(&self.db, id).await?)
     }
 
     pub async fn load_total_vector_count(&self) -> Result<()> {
@@ -363,7 +950,7 @@ impl<const N: usize> IMDB<N> {
             let mut ids = Vec::with_capacity(chunk.len() * N);
             let mut features = Vec::with_capacity(chunk.len() * N);
 
-            for record in chunk {
+            for record in &chunk {
                 for (i, feature) in record.vector.chunks_exact(N).enumerate() {
                     features.push(feature.try_into().unwrap());
                     // total_vector_count 记录了截止到这张图片的特征点数量累加和
@@ -380,6 +967,24 @@ impl<const N: usize> IMDB<N> {
 
             crud::set_indexed_batch(&self.db, &images).await?;
 
+            if let Some(minhash) = &self.minhash {
+                for record in &chunk {
+                    let image: Vec<[u8; N]> =
+                        record.vector.chunks_exact(N).map(|f| f.try_into().unwrap()).collect();
+                    let Some(sig) =
+                        block_in_place(|| minhash.quantizer.search(&image, 1)).ok().and_then(
+                            |words| minhash.seeds.signature(&words),
+                        )
+                    else {
+                        continue;
+                    };
+
+                    let bytes = bytemuck::cast_slice::<u32, u8>(&sig).to_vec();
+                    crud::upsert_minhash(&self.db, record.id, &bytes).await?;
+                    minhash.signatures.write().unwrap().insert(record.id, sig);
+                }
+            }
+
             processed += images.len();
             pb.set_position(processed as u64);
         }
@@ -400,6 +1005,62 @@ impl<const N: usize> IMDB<N> {
             debug!("正在保存 phash 索引，大小：{}……", index.ntotal());
             index.write(self.conf_dir.path())?;
         }
+        if let Some(index) = &self.bkindex {
+            debug!("正在保存 BK-tree 索引，大小：{}……", index.ntotal());
+            index.write()?;
+        }
         Ok(())
     }
+
+    /// 保存布隆过滤器
+    pub fn save_bloom_index(&self) -> Result<()> {
+        self.bloom.read().unwrap().write()
+    }
+}
+
+/// 根据向量在联合索引中的全局 ID，找到它所属的分片名称
+///
+/// `shards` 必须按偏移升序排列；传入空切片时始终返回 `None`
+fn shard_name_for(shards: &[(String, i64)], id: i64) -> Option<String> {
+    if shards.is_empty() {
+        return None;
+    }
+    let idx = shards.partition_point(|(_, offset)| *offset <= id).saturating_sub(1);
+    Some(shards[idx].0.clone())
+}
+
+/// 对每个查询描述符的近邻列表做 Lowe's ratio test：只有最近邻距离小于
+/// `ratio * 次近邻距离` 时才保留（且只保留最近邻一个结果），避免近邻区分度不够的
+/// 描述符引入歧义匹配；近邻数量不足两个时直接放行，不做过滤
+///
+/// `neighbors` 的每个子列表要求已经按距离升序排列，这是 faiss KNN 搜索的默认输出顺序
+fn apply_ratio_test(neighbors: &[Vec<Neighbor>], ratio: f32) -> Vec<Vec<Neighbor>> {
+    neighbors
+        .iter()
+        .map(|ns| match ns.as_slice() {
+            [best, second, ..] if (best.distance as f32) < ratio * second.distance as f32 => {
+                vec![best.clone()]
+            }
+            [_, _, ..] => vec![],
+            other => other.to_vec(),
+        })
+        .collect()
+}
+
+/// 可排序的得分包装，用于在 [`BinaryHeap`] 中按得分比较候选项（`f32` 本身不是 `Ord`）
+#[derive(PartialEq)]
+struct Score(f32);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
 }
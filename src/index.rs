@@ -66,7 +66,15 @@ impl IndexManager {
     }
 
     /// 获取聚合索引
-    pub fn get_aggregate_index(&self, mmap: bool) -> FaissIndex {
+    ///
+    /// `ondisk` 为 true 时，会先把所有子索引与主索引一次性合并进磁盘倒排列表文件
+    /// （同 [`Self::merge_index_on_disk`]），再以磁盘模式加载合并后的主索引，常驻内存的
+    /// 部分只有索引骨架；已经合并过的索引重复调用不会再触发合并
+    pub fn get_aggregate_index(&self, mmap: bool, ondisk: bool) -> FaissIndex {
+        if ondisk && !self.conf_dir.all_sub_index().is_empty() {
+            self.merge_index_on_disk().unwrap();
+        }
+
         if self.conf_dir.all_sub_index().is_empty() {
             self.get_main_index(mmap)
         } else {
@@ -103,6 +111,52 @@ impl IndexManager {
         }
     }
 
+    /// 按名称加载一个或多个索引
+    ///
+    /// 当传入多个名称时，通过 `FaissHStackInvLists` 在查询时联合各个磁盘索引的倒排列表，
+    /// 而不需要事先在磁盘上合并，这样各分片可以独立重建/清理，又能合并查询
+    ///
+    /// 返回联合后的索引，以及每个分片在联合 ID 空间中的起始偏移（按偏移升序排列）。
+    /// 只传入一个名称时，分片列表为空，表示调用方无需为结果添加分片标记
+    pub fn get_named_indexes(
+        &self,
+        names: &[String],
+        mmap: bool,
+        ondisk: bool,
+    ) -> (FaissIndex, Vec<(String, i64)>) {
+        if names.len() <= 1 {
+            let name = names.first().cloned().unwrap_or_else(|| "index".to_string());
+            let mut conf_dir = self.conf_dir.clone();
+            conf_dir.set_default(name);
+            return (IndexManager::new(conf_dir).get_aggregate_index(mmap, ondisk), vec![]);
+        }
+
+        info!("正在联合查询 {} 个分片索引：{:?}", names.len(), names);
+        let mut template = self.get_template_index();
+
+        let mut invfs = vec![];
+        let mut shards = vec![];
+        let mut ntotal = 0i64;
+        for name in names {
+            let mut conf_dir = self.conf_dir.clone();
+            conf_dir.set_default(name.clone());
+            let mut index = IndexManager::new(conf_dir).get_aggregate_index(mmap, ondisk);
+            shards.push((name.clone(), ntotal));
+            ntotal += index.ntotal();
+            index.set_own_invlists(false);
+            invfs.push(index.invlists());
+        }
+
+        let htack = FaissHStackInvLists::new(invfs);
+        template.replace_invlists(htack, true);
+        template.set_ntotal(ntotal);
+
+        info!("已联合特征点 : {}", template.ntotal());
+        info!("倒排列表数量 : {}", template.nlist());
+
+        (template, shards)
+    }
+
     /// 在内存中合并所有子索引
     pub fn merge_index_on_memory(&self) -> Result<()> {
         info!("在内存中合并所有索引……");
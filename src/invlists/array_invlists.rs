@@ -1,6 +1,8 @@
 use std::borrow::Cow;
 
 use anyhow::Result;
+use async_trait::async_trait;
+use tokio::task::block_in_place;
 
 use super::{InvertedLists, InvertedListsReader, InvertedListsWriter};
 
@@ -26,6 +28,7 @@ impl ArrayInvertedLists {
     }
 }
 
+#[async_trait]
 impl InvertedLists for ArrayInvertedLists {
     type Reader<'a>
         = ArrayInvertedListsReader<'a>
@@ -36,15 +39,16 @@ impl InvertedLists for ArrayInvertedLists {
     where
         Self: 'a;
 
-    fn reader(&self) -> Result<Self::Reader<'_>> {
+    async fn reader(&self) -> Result<Self::Reader<'_>> {
         Ok(ArrayInvertedListsReader(self))
     }
 
-    fn writer(&mut self) -> Result<Self::Writer<'_>> {
+    async fn writer(&mut self) -> Result<Self::Writer<'_>> {
         Ok(ArrayInvertedListsWriter(self))
     }
 }
 
+#[async_trait]
 impl InvertedListsReader for ArrayInvertedListsReader<'_> {
     fn nlist(&self) -> u32 {
         self.0.nlist
@@ -58,12 +62,13 @@ impl InvertedListsReader for ArrayInvertedListsReader<'_> {
         self.0.ids[list_no as usize].len()
     }
 
-    fn get_list(&self, list_no: u32) -> (Cow<[u64]>, Cow<[u8]>) {
+    async fn get_list(&self, list_no: u32) -> (Cow<'_, [u64]>, Cow<'_, [u8]>) {
         let list_no = list_no as usize;
         (Cow::Borrowed(&self.0.ids[list_no]), Cow::Borrowed(&self.0.codes[list_no]))
     }
 }
 
+#[async_trait]
 impl InvertedListsReader for ArrayInvertedListsWriter<'_> {
     fn nlist(&self) -> u32 {
         self.0.nlist
@@ -77,23 +82,28 @@ impl InvertedListsReader for ArrayInvertedListsWriter<'_> {
         self.0.codes[list_no as usize].len() / self.0.code_size as usize
     }
 
-    fn get_list(&self, list_no: u32) -> (Cow<[u64]>, Cow<[u8]>) {
+    async fn get_list(&self, list_no: u32) -> (Cow<'_, [u64]>, Cow<'_, [u8]>) {
         let list_no = list_no as usize;
         (Cow::Borrowed(&self.0.ids[list_no]), Cow::Borrowed(&self.0.codes[list_no]))
     }
 }
 
+#[async_trait]
 impl InvertedListsWriter for ArrayInvertedListsWriter<'_> {
-    fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64 {
-        let list_no = list_no as usize;
-        self.0.ids[list_no].extend_from_slice(ids);
-        self.0.codes[list_no].extend_from_slice(codes);
-        ids.len() as u64
+    async fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64 {
+        block_in_place(|| {
+            let list_no = list_no as usize;
+            self.0.ids[list_no].extend_from_slice(ids);
+            self.0.codes[list_no].extend_from_slice(codes);
+            ids.len() as u64
+        })
     }
 
-    fn truncate(&mut self, list_no: u32, new_size: usize) {
-        let list_no = list_no as usize;
-        self.0.ids[list_no].truncate(new_size);
-        self.0.codes[list_no].truncate(new_size * self.0.code_size as usize);
+    async fn truncate(&mut self, list_no: u32, new_size: usize) {
+        block_in_place(|| {
+            let list_no = list_no as usize;
+            self.0.ids[list_no].truncate(new_size);
+            self.0.codes[list_no].truncate(new_size * self.0.code_size as usize);
+        })
     }
 }
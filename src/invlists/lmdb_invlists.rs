@@ -2,13 +2,19 @@ use std::borrow::Cow;
 use std::path::Path;
 
 use anyhow::Result;
+use async_trait::async_trait;
 use byteorder::NativeEndian;
-use heed::types::{Bytes, SerdeBincode, Str, U32};
+use heed::types::{Bytes, SerdeBincode, Str, U32, U64};
 use heed::{Database, Env, EnvOpenOptions, RoTxn, RwTxn, WithTls};
 use serde::{Deserialize, Serialize};
+use tokio::task::block_in_place;
 
 use super::{InvertedLists, InvertedListsReader, InvertedListsWriter};
 
+/// 每个 segment 最多容纳的元素数量。`add_entries` 写满一个 segment 后才会分配下一个，
+/// 而不是像旧版那样每次都整体重写一个列表，使得批量追加的开销摊销为 O(1)
+const SEGMENT_CAPACITY: usize = 1024;
+
 #[derive(Serialize, Deserialize)]
 struct Meta {
     nlist: u32,
@@ -16,11 +22,81 @@ struct Meta {
     list_len: Vec<usize>,
 }
 
+/// 把 `(list_no, segment_no)` 打包成 `db_list` 的 key：高 32 位是 `list_no`，低 32 位是
+/// `segment_no`，这样同一个列表的所有 segment 在 key 空间里连续排列，`get_list` 可以用
+/// 顺序的 cursor 扫描依次取出
+fn segment_key(list_no: u32, segment_no: u32) -> u64 {
+    ((list_no as u64) << 32) | segment_no as u64
+}
+
+/// 给定列表长度和 segment 容量，计算已分配的 segment 数量；除最后一个 segment 外，
+/// 每个 segment 都恰好装满 `capacity` 个元素
+fn segment_count(list_len: usize, capacity: usize) -> u32 {
+    ((list_len + capacity - 1) / capacity) as u32
+}
+
+/// 读取一个列表的全部 segment 并按顺序拼接
+fn read_list(
+    db_list: Database<U64<NativeEndian>, Bytes>,
+    txn: &RoTxn<'_, WithTls>,
+    meta: &Meta,
+    list_no: u32,
+) -> (Vec<u64>, Vec<u8>) {
+    let len = meta.list_len[list_no as usize];
+    if len == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let nsegs = segment_count(len, SEGMENT_CAPACITY);
+    let mut ids = Vec::with_capacity(len);
+    let mut codes = Vec::with_capacity(len * meta.code_size as usize);
+    for seg in 0..nsegs {
+        let data = db_list.get(txn, &segment_key(list_no, seg)).unwrap().unwrap();
+        let (seg_ids, seg_codes): (Vec<u64>, Vec<u8>) = bincode::deserialize(data).unwrap();
+        ids.extend(seg_ids);
+        codes.extend(seg_codes);
+    }
+    (ids, codes)
+}
+
+/// 把旧版单 blob 格式迁移成新版的分段存储
+///
+/// 旧版把整个列表存成 `db_list` 下以 `U32` 编码的 `list_no` 为键的一个不限大小的 blob；
+/// 新版按 [`SEGMENT_CAPACITY`] 把同一份数据切成若干 `(list_no, segment_no)` 为键的定长
+/// segment。每个列表只在数据库第一次被这个版本打开时迁移一次：迁移后旧键被删除，之后
+/// `get_list`/`add_entries`/`truncate` 都只需要认识新格式，不需要再区分新旧
+fn migrate_legacy_lists(
+    txn: &mut RwTxn,
+    db_list: Database<U64<NativeEndian>, Bytes>,
+    meta: &Meta,
+) -> heed::Result<()> {
+    let legacy = db_list.remap_key_type::<U32<NativeEndian>>();
+    for list_no in 0..meta.nlist {
+        let len = meta.list_len[list_no as usize];
+        if len == 0 || db_list.get(txn, &segment_key(list_no, 0))?.is_some() {
+            continue;
+        }
+        let Some(data) = legacy.get(txn, &list_no)?.map(<[u8]>::to_vec) else { continue };
+
+        let (ids, codes): (Vec<u64>, Vec<u8>) = bincode::deserialize(&data).unwrap();
+        let code_size = meta.code_size as usize;
+        for (seg_no, chunk_start) in (0..len).step_by(SEGMENT_CAPACITY).enumerate() {
+            let chunk_end = (chunk_start + SEGMENT_CAPACITY).min(len);
+            let seg_ids = &ids[chunk_start..chunk_end];
+            let seg_codes = &codes[chunk_start * code_size..chunk_end * code_size];
+            let data = bincode::serialize(&(seg_ids, seg_codes)).unwrap();
+            db_list.put(txn, &segment_key(list_no, seg_no as u32), &data)?;
+        }
+        legacy.delete(txn, &list_no)?;
+    }
+    Ok(())
+}
+
 pub struct LmdbInvertedLists {
     env: Env<WithTls>,
     meta: Meta,
     db_meta: Database<Str, SerdeBincode<Meta>>,
-    db_list: Database<U32<NativeEndian>, Bytes>,
+    db_list: Database<U64<NativeEndian>, Bytes>,
 }
 
 impl LmdbInvertedLists {
@@ -36,18 +112,22 @@ impl LmdbInvertedLists {
         };
         let mut txn = env.write_txn()?;
         let db_meta = env.create_database::<Str, SerdeBincode<Meta>>(&mut txn, Some("meta"))?;
-        let db_list = env.create_database::<U32<NativeEndian>, Bytes>(&mut txn, Some("list"))?;
+        let db_list = env.create_database::<U64<NativeEndian>, Bytes>(&mut txn, Some("list"))?;
         let meta = match db_meta.get(&mut txn, &"meta")? {
             Some(meta) => meta,
             None => Meta { nlist, code_size, list_len: vec![0; nlist as usize] },
         };
         assert_eq!(meta.nlist, nlist, "nlist mismatch");
         assert_eq!(meta.code_size, code_size, "code_size mismatch");
+
+        migrate_legacy_lists(&mut txn, db_list, &meta)?;
+
         txn.commit()?;
         Ok(Self { env, meta, db_meta, db_list })
     }
 }
 
+#[async_trait]
 impl InvertedLists for LmdbInvertedLists {
     type Reader<'a>
         = LmdbInvertedListsReader<'a>
@@ -58,18 +138,22 @@ impl InvertedLists for LmdbInvertedLists {
     where
         Self: 'a;
 
-    fn reader(&self) -> Result<Self::Reader<'_>> {
-        let txn = self.env.read_txn()?;
-        Ok(LmdbInvertedListsReader { txn, meta: &self.meta, db_list: self.db_list })
+    async fn reader(&self) -> Result<Self::Reader<'_>> {
+        block_in_place(|| {
+            let txn = self.env.read_txn()?;
+            Ok(LmdbInvertedListsReader { txn, meta: &self.meta, db_list: self.db_list })
+        })
     }
 
-    fn writer(&mut self) -> Result<Self::Writer<'_>> {
-        let txn = self.env.write_txn()?;
-        Ok(LmdbInvertedListsWriter {
-            txn,
-            meta: &mut self.meta,
-            db_meta: self.db_meta,
-            db_list: self.db_list,
+    async fn writer(&mut self) -> Result<Self::Writer<'_>> {
+        block_in_place(|| {
+            let txn = self.env.write_txn()?;
+            Ok(LmdbInvertedListsWriter {
+                txn,
+                meta: &mut self.meta,
+                db_meta: self.db_meta,
+                db_list: self.db_list,
+            })
         })
     }
 }
@@ -77,9 +161,10 @@ impl InvertedLists for LmdbInvertedLists {
 pub struct LmdbInvertedListsReader<'a> {
     txn: RoTxn<'a, WithTls>,
     meta: &'a Meta,
-    db_list: Database<U32<NativeEndian>, Bytes>,
+    db_list: Database<U64<NativeEndian>, Bytes>,
 }
 
+#[async_trait]
 impl InvertedListsReader for LmdbInvertedListsReader<'_> {
     fn nlist(&self) -> u32 {
         self.meta.nlist
@@ -94,15 +179,11 @@ impl InvertedListsReader for LmdbInvertedListsReader<'_> {
         self.meta.list_len[list_no]
     }
 
-    fn get_list(&self, list_no: u32) -> (Cow<[u64]>, Cow<[u8]>) {
-        let len = self.list_len(list_no);
-        if len == 0 {
-            return (Cow::Borrowed(&[]), Cow::Borrowed(&[]));
-        }
-        let data = self.db_list.get(&self.txn, &list_no).unwrap().unwrap();
-        // NOTE: 由于 LMDB 不保证数据是对齐的，这里使用 bincode 来反序列化，而不是直接 cast_slice
-        let (ids, codes) = bincode::deserialize(data).unwrap();
-        (ids, codes)
+    async fn get_list(&self, list_no: u32) -> (Cow<'_, [u64]>, Cow<'_, [u8]>) {
+        block_in_place(|| {
+            let (ids, codes) = read_list(self.db_list, &self.txn, self.meta, list_no);
+            (Cow::Owned(ids), Cow::Owned(codes))
+        })
     }
 }
 
@@ -110,7 +191,7 @@ pub struct LmdbInvertedListsWriter<'a> {
     txn: RwTxn<'a>,
     meta: &'a mut Meta,
     db_meta: Database<Str, SerdeBincode<Meta>>,
-    db_list: Database<U32<NativeEndian>, Bytes>,
+    db_list: Database<U64<NativeEndian>, Bytes>,
 }
 
 impl LmdbInvertedListsWriter<'_> {
@@ -121,6 +202,7 @@ impl LmdbInvertedListsWriter<'_> {
     }
 }
 
+#[async_trait]
 impl InvertedListsReader for LmdbInvertedListsWriter<'_> {
     fn nlist(&self) -> u32 {
         self.meta.nlist
@@ -134,36 +216,87 @@ impl InvertedListsReader for LmdbInvertedListsWriter<'_> {
         self.meta.list_len[list_no as usize]
     }
 
-    fn get_list(&self, list_no: u32) -> (Cow<[u64]>, Cow<[u8]>) {
-        let len = self.list_len(list_no);
-        if len == 0 {
-            return (Cow::Borrowed(&[]), Cow::Borrowed(&[]));
-        }
-        let data = self.db_list.get(&self.txn, &list_no).unwrap().unwrap();
-        let (ids, codes) = bincode::deserialize(data).unwrap();
-        (ids, codes)
+    async fn get_list(&self, list_no: u32) -> (Cow<'_, [u64]>, Cow<'_, [u8]>) {
+        block_in_place(|| {
+            let (ids, codes) = read_list(self.db_list, &self.txn, self.meta, list_no);
+            (Cow::Owned(ids), Cow::Owned(codes))
+        })
     }
 }
 
+#[async_trait]
 impl InvertedListsWriter for LmdbInvertedListsWriter<'_> {
-    fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64 {
-        let (ids_, codes_) = self.get_list(list_no);
-        let (mut ids_, mut codes_) = (ids_.to_vec(), codes_.to_vec());
-        ids_.extend_from_slice(ids);
-        codes_.extend_from_slice(codes);
-        let data = bincode::serialize(&(ids_, codes_)).unwrap();
-        self.db_list.put(&mut self.txn, &list_no, &data).unwrap();
-        self.meta.list_len[list_no as usize] += ids.len();
-        ids.len() as u64
+    async fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64 {
+        block_in_place(|| {
+            let code_size = self.meta.code_size as usize;
+            let mut len = self.meta.list_len[list_no as usize];
+            let mut consumed = 0;
+
+            // 只重写尾部 segment，填满后才分配新的 segment；单次调用的元素数超过一个
+            // segment 容量时会跨多个新 segment 写入，但已经写满的历史 segment 不会被触碰
+            while consumed < ids.len() {
+                let seg_no = (len / SEGMENT_CAPACITY) as u32;
+                let occupancy = len % SEGMENT_CAPACITY;
+                let take = (SEGMENT_CAPACITY - occupancy).min(ids.len() - consumed);
+                let key = segment_key(list_no, seg_no);
+
+                let (mut seg_ids, mut seg_codes) = if occupancy > 0 {
+                    let data = self.db_list.get(&self.txn, &key).unwrap().unwrap();
+                    bincode::deserialize::<(Vec<u64>, Vec<u8>)>(data).unwrap()
+                } else {
+                    (
+                        Vec::with_capacity(SEGMENT_CAPACITY),
+                        Vec::with_capacity(SEGMENT_CAPACITY * code_size),
+                    )
+                };
+                seg_ids.extend_from_slice(&ids[consumed..consumed + take]);
+                seg_codes
+                    .extend_from_slice(&codes[consumed * code_size..(consumed + take) * code_size]);
+
+                let data = bincode::serialize(&(seg_ids, seg_codes)).unwrap();
+                self.db_list.put(&mut self.txn, &key, &data).unwrap();
+
+                consumed += take;
+                len += take;
+            }
+
+            self.meta.list_len[list_no as usize] = len;
+            ids.len() as u64
+        })
     }
 
-    fn truncate(&mut self, list_no: u32, new_size: usize) {
-        let (ids, codes) = self.get_list(list_no);
-        let data =
-            bincode::serialize(&(&ids[..new_size], &codes[..new_size * self.code_size() as usize]))
-                .unwrap();
-        self.db_list.put(&mut self.txn, &list_no, &data).unwrap();
-        self.meta.list_len[list_no as usize] = new_size;
+    async fn truncate(&mut self, list_no: u32, new_size: usize) {
+        block_in_place(|| {
+            let old_len = self.meta.list_len[list_no as usize];
+            let new_size = new_size.min(old_len);
+            let code_size = self.meta.code_size as usize;
+
+            let old_nsegs = segment_count(old_len, SEGMENT_CAPACITY);
+            let new_nsegs = segment_count(new_size, SEGMENT_CAPACITY);
+
+            // 整段丢弃边界之后的 segment
+            for seg in new_nsegs..old_nsegs {
+                self.db_list.delete(&mut self.txn, &segment_key(list_no, seg)).unwrap();
+            }
+
+            // 只重写边界所在的 segment
+            if new_size > 0 {
+                let boundary = new_nsegs - 1;
+                let keep = new_size - boundary as usize * SEGMENT_CAPACITY;
+                let key = segment_key(list_no, boundary);
+
+                let data = self.db_list.get(&self.txn, &key).unwrap().unwrap();
+                let (mut seg_ids, mut seg_codes): (Vec<u64>, Vec<u8>) =
+                    bincode::deserialize(data).unwrap();
+                seg_ids.truncate(keep);
+                seg_codes.truncate(keep * code_size);
+
+                let data = bincode::serialize(&(seg_ids, seg_codes)).unwrap();
+                self.db_list.put(&mut self.txn, &key, &data).unwrap();
+            }
+
+            self.meta.list_len[list_no as usize] = new_size;
+        })
     }
 }
 
@@ -173,29 +306,29 @@ mod tests {
 
     use super::*;
 
-    #[test]
-    fn test_new_lmdb_inverted_lists() {
+    #[tokio::test]
+    async fn test_new_lmdb_inverted_lists() {
         let temp_dir = tempdir().unwrap();
         let nlist = 10;
         let code_size = 32;
 
         let invlists = LmdbInvertedLists::new(temp_dir.path(), nlist, code_size).unwrap();
 
-        let reader = invlists.reader().unwrap();
+        let reader = invlists.reader().await.unwrap();
         assert_eq!(reader.nlist(), nlist);
         assert_eq!(reader.code_size(), code_size);
 
         // 测试初始状态下所有列表为空
         for i in 0..nlist {
             assert_eq!(reader.list_len(i), 0);
-            let (ids, codes) = reader.get_list(i);
+            let (ids, codes) = reader.get_list(i).await;
             assert!(ids.is_empty());
             assert!(codes.is_empty());
         }
     }
 
-    #[test]
-    fn test_add_entries() {
+    #[tokio::test]
+    async fn test_add_entries() {
         let temp_dir = tempdir().unwrap();
         let nlist = 5;
         let code_size = 16;
@@ -207,22 +340,22 @@ mod tests {
         let codes = vec![0u8; 3 * code_size as usize]; // 3个向量，每个向量16字节
 
         {
-            let mut writer = invlists.writer().unwrap();
-            let added = writer.add_entries(0, &ids, &codes);
+            let mut writer = invlists.writer().await.unwrap();
+            let added = writer.add_entries(0, &ids, &codes).await;
             assert_eq!(added, 3);
             writer.commit().unwrap();
         }
 
         // 验证添加的数据
-        let reader = invlists.reader().unwrap();
+        let reader = invlists.reader().await.unwrap();
         assert_eq!(reader.list_len(0), 3);
-        let (retrieved_ids, retrieved_codes) = reader.get_list(0);
+        let (retrieved_ids, retrieved_codes) = reader.get_list(0).await;
         assert_eq!(retrieved_ids.as_ref(), &ids);
         assert_eq!(retrieved_codes.as_ref(), &codes);
     }
 
-    #[test]
-    fn test_multiple_add_entries() {
+    #[tokio::test]
+    async fn test_multiple_add_entries() {
         let temp_dir = tempdir().unwrap();
         let nlist = 3;
         let code_size = 8;
@@ -238,16 +371,16 @@ mod tests {
         let codes2 = vec![2u8; 2 * code_size as usize];
 
         {
-            let mut writer = invlists.writer().unwrap();
-            writer.add_entries(0, &ids1, &codes1);
-            writer.add_entries(0, &ids2, &codes2);
+            let mut writer = invlists.writer().await.unwrap();
+            writer.add_entries(0, &ids1, &codes1).await;
+            writer.add_entries(0, &ids2, &codes2).await;
             writer.commit().unwrap();
         }
 
         // 验证合并后的数据
-        let reader = invlists.reader().unwrap();
+        let reader = invlists.reader().await.unwrap();
         assert_eq!(reader.list_len(0), 4);
-        let (retrieved_ids, retrieved_codes) = reader.get_list(0);
+        let (retrieved_ids, retrieved_codes) = reader.get_list(0).await;
 
         let expected_ids = [&ids1[..], &ids2[..]].concat();
         let expected_codes = [&codes1[..], &codes2[..]].concat();
@@ -256,8 +389,8 @@ mod tests {
         assert_eq!(retrieved_codes.as_ref(), &expected_codes);
     }
 
-    #[test]
-    fn test_truncate() {
+    #[tokio::test]
+    async fn test_truncate() {
         let temp_dir = tempdir().unwrap();
         let nlist = 2;
         let code_size = 4;
@@ -269,25 +402,25 @@ mod tests {
         let codes = vec![0u8; 5 * code_size as usize];
 
         {
-            let mut writer = invlists.writer().unwrap();
-            writer.add_entries(0, &ids, &codes);
+            let mut writer = invlists.writer().await.unwrap();
+            writer.add_entries(0, &ids, &codes).await;
 
             // 截断为3个条目
-            writer.truncate(0, 3);
+            writer.truncate(0, 3).await;
             writer.commit().unwrap();
         }
 
         // 验证截断后的数据
-        let reader = invlists.reader().unwrap();
+        let reader = invlists.reader().await.unwrap();
         assert_eq!(reader.list_len(0), 3);
-        let (retrieved_ids, retrieved_codes) = reader.get_list(0);
+        let (retrieved_ids, retrieved_codes) = reader.get_list(0).await;
 
         assert_eq!(retrieved_ids.as_ref(), &ids[..3]);
         assert_eq!(retrieved_codes.as_ref(), &codes[..3 * code_size as usize]);
     }
 
-    #[test]
-    fn test_different_lists() {
+    #[tokio::test]
+    async fn test_different_lists() {
         let temp_dir = tempdir().unwrap();
         let nlist = 3;
         let code_size = 8;
@@ -302,22 +435,22 @@ mod tests {
         let codes1 = vec![1u8; 3 * code_size as usize];
 
         {
-            let mut writer = invlists.writer().unwrap();
-            writer.add_entries(0, &ids0, &codes0);
-            writer.add_entries(1, &ids1, &codes1);
+            let mut writer = invlists.writer().await.unwrap();
+            writer.add_entries(0, &ids0, &codes0).await;
+            writer.add_entries(1, &ids1, &codes1).await;
             writer.commit().unwrap();
         }
 
         // 验证不同列表的数据
-        let reader = invlists.reader().unwrap();
+        let reader = invlists.reader().await.unwrap();
 
         assert_eq!(reader.list_len(0), 2);
         assert_eq!(reader.list_len(1), 3);
         assert_eq!(reader.list_len(2), 0);
 
-        let (retrieved_ids0, retrieved_codes0) = reader.get_list(0);
-        let (retrieved_ids1, retrieved_codes1) = reader.get_list(1);
-        let (retrieved_ids2, retrieved_codes2) = reader.get_list(2);
+        let (retrieved_ids0, retrieved_codes0) = reader.get_list(0).await;
+        let (retrieved_ids1, retrieved_codes1) = reader.get_list(1).await;
+        let (retrieved_ids2, retrieved_codes2) = reader.get_list(2).await;
 
         assert_eq!(retrieved_ids0.as_ref(), &ids0);
         assert_eq!(retrieved_codes0.as_ref(), &codes0);
@@ -327,8 +460,8 @@ mod tests {
         assert!(retrieved_codes2.is_empty());
     }
 
-    #[test]
-    fn test_persistence() {
+    #[tokio::test]
+    async fn test_persistence() {
         let temp_dir = tempdir().unwrap();
         let nlist = 2;
         let code_size = 16;
@@ -339,28 +472,28 @@ mod tests {
         // 创建并添加数据
         {
             let mut invlists = LmdbInvertedLists::new(temp_dir.path(), nlist, code_size).unwrap();
-            let mut writer = invlists.writer().unwrap();
-            writer.add_entries(0, &ids, &codes);
+            let mut writer = invlists.writer().await.unwrap();
+            writer.add_entries(0, &ids, &codes).await;
             writer.commit().unwrap();
         }
 
         // 重新打开并验证数据持久化
         {
             let invlists = LmdbInvertedLists::new(temp_dir.path(), nlist, code_size).unwrap();
-            let reader = invlists.reader().unwrap();
+            let reader = invlists.reader().await.unwrap();
 
             assert_eq!(reader.nlist(), nlist);
             assert_eq!(reader.code_size(), code_size);
             assert_eq!(reader.list_len(0), 3);
 
-            let (retrieved_ids, retrieved_codes) = reader.get_list(0);
+            let (retrieved_ids, retrieved_codes) = reader.get_list(0).await;
             assert_eq!(retrieved_ids.as_ref(), &ids);
             assert_eq!(retrieved_codes.as_ref(), &codes);
         }
     }
 
-    #[test]
-    fn test_writer_clear() {
+    #[tokio::test]
+    async fn test_writer_clear() {
         let temp_dir = tempdir().unwrap();
         let nlist = 4;
         let code_size = 8;
@@ -369,18 +502,18 @@ mod tests {
 
         // 在多个列表中添加数据
         {
-            let mut writer = invlists.writer().unwrap();
+            let mut writer = invlists.writer().await.unwrap();
             for i in 0..nlist {
                 let ids = vec![i as u64 + 1, i as u64 + 2];
                 let codes = vec![i as u8; 2 * code_size as usize];
-                writer.add_entries(i, &ids, &codes);
+                writer.add_entries(i, &ids, &codes).await;
             }
             writer.commit().unwrap();
         }
 
         // 验证所有列表都有数据
         {
-            let reader = invlists.reader().unwrap();
+            let reader = invlists.reader().await.unwrap();
             for i in 0..nlist {
                 assert_eq!(reader.list_len(i), 2);
             }
@@ -388,33 +521,33 @@ mod tests {
 
         // 清空所有列表
         {
-            let mut writer = invlists.writer().unwrap();
-            writer.clear();
+            let mut writer = invlists.writer().await.unwrap();
+            writer.clear().await;
             writer.commit().unwrap();
         }
 
         // 验证所有列表都已清空
         {
-            let reader = invlists.reader().unwrap();
+            let reader = invlists.reader().await.unwrap();
             for i in 0..nlist {
                 assert_eq!(reader.list_len(i), 0);
-                let (ids, codes) = reader.get_list(i);
+                let (ids, codes) = reader.get_list(i).await;
                 assert!(ids.is_empty());
                 assert!(codes.is_empty());
             }
         }
     }
 
-    #[test]
+    #[tokio::test]
     #[should_panic(expected = "nlist mismatch")]
-    fn test_nlist_mismatch() {
+    async fn test_nlist_mismatch() {
         let temp_dir = tempdir().unwrap();
 
         // 创建 nlist=5 的索引，然后销毁
         {
             let mut invlists1 = LmdbInvertedLists::new(temp_dir.path(), 5, 32).unwrap();
             // 确保 meta 被写入数据库
-            let writer = invlists1.writer().unwrap();
+            let writer = invlists1.writer().await.unwrap();
             writer.commit().unwrap();
         } // 这里 invlists1 被销毁
 
@@ -422,16 +555,16 @@ mod tests {
         let _invlists2 = LmdbInvertedLists::new(temp_dir.path(), 10, 32).unwrap();
     }
 
-    #[test]
+    #[tokio::test]
     #[should_panic(expected = "code_size mismatch")]
-    fn test_code_size_mismatch() {
+    async fn test_code_size_mismatch() {
         let temp_dir = tempdir().unwrap();
 
         // 创建 code_size=32 的索引，然后销毁
         {
             let mut invlists1 = LmdbInvertedLists::new(temp_dir.path(), 5, 32).unwrap();
             // 确保 meta 被写入数据库
-            let writer = invlists1.writer().unwrap();
+            let writer = invlists1.writer().await.unwrap();
             writer.commit().unwrap();
         } // 这里 invlists1 被销毁
 
@@ -439,8 +572,8 @@ mod tests {
         let _invlists2 = LmdbInvertedLists::new(temp_dir.path(), 5, 64).unwrap();
     }
 
-    #[test]
-    fn test_merge_from() {
+    #[tokio::test]
+    async fn test_merge_from() {
         let temp_dir1 = tempdir().unwrap();
         let temp_dir2 = tempdir().unwrap();
         let nlist = 3;
@@ -451,35 +584,35 @@ mod tests {
 
         // 在第一个索引中添加数据
         {
-            let mut writer1 = invlists1.writer().unwrap();
-            writer1.add_entries(0, &[1, 2], &vec![1u8; 2 * code_size as usize]);
-            writer1.add_entries(1, &[3], &vec![2u8; code_size as usize]);
+            let mut writer1 = invlists1.writer().await.unwrap();
+            writer1.add_entries(0, &[1, 2], &vec![1u8; 2 * code_size as usize]).await;
+            writer1.add_entries(1, &[3], &vec![2u8; code_size as usize]).await;
             writer1.commit().unwrap();
         }
 
         // 在第二个索引中添加数据
         {
-            let mut writer2 = invlists2.writer().unwrap();
-            writer2.add_entries(0, &[4, 5], &vec![3u8; 2 * code_size as usize]);
-            writer2.add_entries(2, &[6], &vec![4u8; code_size as usize]);
+            let mut writer2 = invlists2.writer().await.unwrap();
+            writer2.add_entries(0, &[4, 5], &vec![3u8; 2 * code_size as usize]).await;
+            writer2.add_entries(2, &[6], &vec![4u8; code_size as usize]).await;
             writer2.commit().unwrap();
         }
 
         // 合并第二个索引到第一个
         {
-            let mut writer1 = invlists1.writer().unwrap();
-            let mut writer2 = invlists2.writer().unwrap();
-            writer1.merge_from(&mut writer2, 100); // 添加 100 的偏移量
+            let mut writer1 = invlists1.writer().await.unwrap();
+            let mut writer2 = invlists2.writer().await.unwrap();
+            writer1.merge_from(&mut writer2, 100).await; // 添加 100 的偏移量
             writer1.commit().unwrap();
             writer2.commit().unwrap();
         }
 
         // 验证合并结果
-        let reader1 = invlists1.reader().unwrap();
+        let reader1 = invlists1.reader().await.unwrap();
 
         // 列表 0: [1, 2] + [104, 105] (4+100, 5+100)
         assert_eq!(reader1.list_len(0), 4);
-        let (ids0, codes0) = reader1.get_list(0);
+        let (ids0, codes0) = reader1.get_list(0).await;
         assert_eq!(ids0.as_ref(), &[1, 2, 104, 105]);
         let expected_codes0 =
             [&vec![1u8; 2 * code_size as usize][..], &vec![3u8; 2 * code_size as usize][..]]
@@ -488,20 +621,77 @@ mod tests {
 
         // 列表 1: [3] (没有变化)
         assert_eq!(reader1.list_len(1), 1);
-        let (ids1, codes1) = reader1.get_list(1);
+        let (ids1, codes1) = reader1.get_list(1).await;
         assert_eq!(ids1.as_ref(), &[3]);
         assert_eq!(codes1.as_ref(), &vec![2u8; code_size as usize]);
 
         // 列表 2: [106] (6+100)
         assert_eq!(reader1.list_len(2), 1);
-        let (ids2, codes2) = reader1.get_list(2);
+        let (ids2, codes2) = reader1.get_list(2).await;
         assert_eq!(ids2.as_ref(), &[106]);
         assert_eq!(codes2.as_ref(), &vec![4u8; code_size as usize]);
 
         // 验证第二个索引已被清空
-        let reader2 = invlists2.reader().unwrap();
+        let reader2 = invlists2.reader().await.unwrap();
         for i in 0..nlist {
             assert_eq!(reader2.list_len(i), 0);
         }
     }
+
+    #[tokio::test]
+    async fn test_add_entries_spans_multiple_segments() {
+        let temp_dir = tempdir().unwrap();
+        let nlist = 1;
+        let code_size = 4;
+
+        // 插入的条目数超过一个 segment 的容量，验证跨 segment 追加和拼接是正确的
+        let total = SEGMENT_CAPACITY * 2 + 10;
+        let ids: Vec<u64> = (0..total as u64).collect();
+        let codes: Vec<u8> = ids.iter().flat_map(|id| (*id as u32).to_le_bytes()).collect();
+
+        let mut invlists = LmdbInvertedLists::new(temp_dir.path(), nlist, code_size).unwrap();
+
+        {
+            let mut writer = invlists.writer().await.unwrap();
+            // 分两次写入，第二次跨越多个 segment 边界
+            writer.add_entries(0, &ids[..SEGMENT_CAPACITY / 2], &codes[..SEGMENT_CAPACITY / 2 * code_size as usize]).await;
+            writer
+                .add_entries(0, &ids[SEGMENT_CAPACITY / 2..], &codes[SEGMENT_CAPACITY / 2 * code_size as usize..])
+                .await;
+            writer.commit().unwrap();
+        }
+
+        let reader = invlists.reader().await.unwrap();
+        assert_eq!(reader.list_len(0), total);
+        let (retrieved_ids, retrieved_codes) = reader.get_list(0).await;
+        assert_eq!(retrieved_ids.as_ref(), &ids);
+        assert_eq!(retrieved_codes.as_ref(), &codes);
+    }
+
+    #[tokio::test]
+    async fn test_truncate_across_segment_boundary() {
+        let temp_dir = tempdir().unwrap();
+        let nlist = 1;
+        let code_size = 4;
+
+        let total = SEGMENT_CAPACITY * 2 + 10;
+        let ids: Vec<u64> = (0..total as u64).collect();
+        let codes: Vec<u8> = ids.iter().flat_map(|id| (*id as u32).to_le_bytes()).collect();
+
+        let mut invlists = LmdbInvertedLists::new(temp_dir.path(), nlist, code_size).unwrap();
+        {
+            let mut writer = invlists.writer().await.unwrap();
+            writer.add_entries(0, &ids, &codes).await;
+            // 截断到第一个 segment 内部，后面整段的 segment 应该被整段丢弃
+            writer.truncate(0, SEGMENT_CAPACITY / 2).await;
+            writer.commit().unwrap();
+        }
+
+        let reader = invlists.reader().await.unwrap();
+        let new_len = SEGMENT_CAPACITY / 2;
+        assert_eq!(reader.list_len(0), new_len);
+        let (retrieved_ids, retrieved_codes) = reader.get_list(0).await;
+        assert_eq!(retrieved_ids.as_ref(), &ids[..new_len]);
+        assert_eq!(retrieved_codes.as_ref(), &codes[..new_len * code_size as usize]);
+    }
 }
@@ -1,10 +1,18 @@
 mod array_invlists;
 mod lmdb_invlists;
+mod object_invlists;
 
 use anyhow::Result;
+use async_trait::async_trait;
 pub use array_invlists::*;
 pub use lmdb_invlists::*;
+pub use object_invlists::*;
 
+/// `reader`/`writer` 均为 `async fn`，方便未来接入需要走网络的远程倒排列表实现
+/// （例如 gRPC 或对象存储后端）；现有的内存/LMDB 实现本身不需要异步 IO，
+/// 在 [`array_invlists`]/[`lmdb_invlists`] 中通过 `tokio::task::block_in_place`
+/// 包一层同步实现即可满足这个签名
+#[async_trait]
 pub trait InvertedLists {
     type Reader<'a>: InvertedListsReader
     where
@@ -13,11 +21,12 @@ pub trait InvertedLists {
     where
         Self: 'a;
 
-    fn reader(&self) -> Result<Self::Reader<'_>>;
+    async fn reader(&self) -> Result<Self::Reader<'_>>;
 
-    fn writer(&mut self) -> Result<Self::Writer<'_>>;
+    async fn writer(&mut self) -> Result<Self::Writer<'_>>;
 }
 
+#[async_trait]
 pub trait InvertedListsReader {
     /// 返回倒排表的列表数量
     fn nlist(&self) -> u32;
@@ -29,38 +38,205 @@ pub trait InvertedListsReader {
     fn list_len(&self, list_no: u32) -> usize;
 
     /// 返回指定倒排表中向量的 ID 列表和数据
-    fn get_list(&self, list_no: u32) -> (&[u64], &[u8]);
+    async fn get_list(&self, list_no: u32) -> (std::borrow::Cow<'_, [u64]>, std::borrow::Cow<'_, [u8]>);
 }
 
+#[async_trait]
 pub trait InvertedListsWriter: InvertedListsReader {
     /// 往指定倒排表中添加元素
     ///
     /// 返回添加的元素数量
-    fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64;
+    async fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64;
 
     /// 调整指定倒排表大小
-    fn truncate(&mut self, list_no: u32, new_size: usize);
+    async fn truncate(&mut self, list_no: u32, new_size: usize);
 
     /// 清空整个倒排表
-    fn clear(&mut self) {
+    async fn clear(&mut self) {
         for i in 0..self.nlist() {
-            self.truncate(i, 0);
+            self.truncate(i, 0).await;
         }
     }
 
     /// 合并另一个倒排列表，并给元素编号添加一个偏移量
     ///
     /// 被合并的倒排列表会被清空
-    fn merge_from(&mut self, other: &mut impl InvertedListsWriter, add_id: u64) {
+    async fn merge_from(&mut self, other: &mut impl InvertedListsWriter + Send, add_id: u64) {
         for i in 0..self.nlist() {
-            let (ids, codes) = other.get_list(i);
+            let (ids, codes) = other.get_list(i).await;
             if add_id == 0 {
-                self.add_entries(i, ids, codes);
+                self.add_entries(i, &ids, &codes).await;
             } else {
                 let new_ids = ids.iter().map(|id| id + add_id).collect::<Vec<_>>();
-                self.add_entries(i, &new_ids, codes);
+                self.add_entries(i, &new_ids, &codes).await;
             }
-            other.truncate(i, 0);
+            other.truncate(i, 0).await;
+        }
+    }
+}
+
+/// 可插拔的倒排列表后端，按地址字符串的 scheme 选择具体实现并构造
+///
+/// [`InvertedLists::Reader`]/[`InvertedLists::Writer`] 是关联类型（GAT），这决定了
+/// `InvertedLists` 无法做成 trait object（`dyn InvertedLists`），因此这里用一个枚举
+/// 来做运行时后端选择，而不是返回 `Box<dyn InvertedLists>`
+pub enum InvertedListsHandle {
+    Memory(ArrayInvertedLists),
+    Lmdb(LmdbInvertedLists),
+    Object(ObjectInvertedLists),
+}
+
+pub enum InvertedListsHandleReader<'a> {
+    Memory(ArrayInvertedListsReader<'a>),
+    Lmdb(LmdbInvertedListsReader<'a>),
+    Object(ObjectInvertedListsReader<'a>),
+}
+
+pub enum InvertedListsHandleWriter<'a> {
+    Memory(ArrayInvertedListsWriter<'a>),
+    Lmdb(LmdbInvertedListsWriter<'a>),
+    Object(ObjectInvertedListsWriter<'a>),
+}
+
+impl InvertedListsHandle {
+    /// 解析 scheme 前缀的地址字符串并构造对应后端
+    ///
+    /// - `memory://`：纯内存实现，进程退出后数据丢失
+    /// - `lmdb:///path/to/dir`：本地 LMDB 环境
+    /// - `s3://bucket/prefix`：S3 兼容对象存储，按 `AmazonS3Builder::from_env` 的方式读取连接凭据
+    pub async fn from_addr(addr: &str, nlist: u32, code_size: u32) -> Result<Self> {
+        if let Some(rest) = addr.strip_prefix("memory://") {
+            let _ = rest;
+            Ok(Self::Memory(ArrayInvertedLists::new(nlist, code_size)))
+        } else if let Some(path) = addr.strip_prefix("lmdb://") {
+            Ok(Self::Lmdb(LmdbInvertedLists::new(path, nlist, code_size)?))
+        } else if let Some(rest) = addr.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            let store = object_store::aws::AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()?;
+            let store: std::sync::Arc<dyn object_store::ObjectStore> = std::sync::Arc::new(store);
+            let prefix = object_store::path::Path::from(prefix);
+            Ok(Self::Object(ObjectInvertedLists::open(store, prefix, nlist, code_size).await?))
+        } else {
+            Err(anyhow::anyhow!("不支持的倒排列表地址：{addr}"))
+        }
+    }
+}
+
+#[async_trait]
+impl InvertedLists for InvertedListsHandle {
+    type Reader<'a>
+        = InvertedListsHandleReader<'a>
+    where
+        Self: 'a;
+    type Writer<'a>
+        = InvertedListsHandleWriter<'a>
+    where
+        Self: 'a;
+
+    async fn reader(&self) -> Result<Self::Reader<'_>> {
+        Ok(match self {
+            Self::Memory(invlists) => InvertedListsHandleReader::Memory(invlists.reader().await?),
+            Self::Lmdb(invlists) => InvertedListsHandleReader::Lmdb(invlists.reader().await?),
+            Self::Object(invlists) => InvertedListsHandleReader::Object(invlists.reader().await?),
+        })
+    }
+
+    async fn writer(&mut self) -> Result<Self::Writer<'_>> {
+        Ok(match self {
+            Self::Memory(invlists) => InvertedListsHandleWriter::Memory(invlists.writer().await?),
+            Self::Lmdb(invlists) => InvertedListsHandleWriter::Lmdb(invlists.writer().await?),
+            Self::Object(invlists) => InvertedListsHandleWriter::Object(invlists.writer().await?),
+        })
+    }
+}
+
+#[async_trait]
+impl InvertedListsReader for InvertedListsHandleReader<'_> {
+    fn nlist(&self) -> u32 {
+        match self {
+            Self::Memory(reader) => reader.nlist(),
+            Self::Lmdb(reader) => reader.nlist(),
+            Self::Object(reader) => reader.nlist(),
+        }
+    }
+
+    fn code_size(&self) -> u32 {
+        match self {
+            Self::Memory(reader) => reader.code_size(),
+            Self::Lmdb(reader) => reader.code_size(),
+            Self::Object(reader) => reader.code_size(),
+        }
+    }
+
+    fn list_len(&self, list_no: u32) -> usize {
+        match self {
+            Self::Memory(reader) => reader.list_len(list_no),
+            Self::Lmdb(reader) => reader.list_len(list_no),
+            Self::Object(reader) => reader.list_len(list_no),
+        }
+    }
+
+    async fn get_list(&self, list_no: u32) -> (std::borrow::Cow<'_, [u64]>, std::borrow::Cow<'_, [u8]>) {
+        match self {
+            Self::Memory(reader) => reader.get_list(list_no).await,
+            Self::Lmdb(reader) => reader.get_list(list_no).await,
+            Self::Object(reader) => reader.get_list(list_no).await,
+        }
+    }
+}
+
+#[async_trait]
+impl InvertedListsReader for InvertedListsHandleWriter<'_> {
+    fn nlist(&self) -> u32 {
+        match self {
+            Self::Memory(writer) => writer.nlist(),
+            Self::Lmdb(writer) => writer.nlist(),
+            Self::Object(writer) => writer.nlist(),
+        }
+    }
+
+    fn code_size(&self) -> u32 {
+        match self {
+            Self::Memory(writer) => writer.code_size(),
+            Self::Lmdb(writer) => writer.code_size(),
+            Self::Object(writer) => writer.code_size(),
+        }
+    }
+
+    fn list_len(&self, list_no: u32) -> usize {
+        match self {
+            Self::Memory(writer) => writer.list_len(list_no),
+            Self::Lmdb(writer) => writer.list_len(list_no),
+            Self::Object(writer) => writer.list_len(list_no),
+        }
+    }
+
+    async fn get_list(&self, list_no: u32) -> (std::borrow::Cow<'_, [u64]>, std::borrow::Cow<'_, [u8]>) {
+        match self {
+            Self::Memory(writer) => writer.get_list(list_no).await,
+            Self::Lmdb(writer) => writer.get_list(list_no).await,
+            Self::Object(writer) => writer.get_list(list_no).await,
+        }
+    }
+}
+
+#[async_trait]
+impl InvertedListsWriter for InvertedListsHandleWriter<'_> {
+    async fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64 {
+        match self {
+            Self::Memory(writer) => writer.add_entries(list_no, ids, codes).await,
+            Self::Lmdb(writer) => writer.add_entries(list_no, ids, codes).await,
+            Self::Object(writer) => writer.add_entries(list_no, ids, codes).await,
+        }
+    }
+
+    async fn truncate(&mut self, list_no: u32, new_size: usize) {
+        match self {
+            Self::Memory(writer) => writer.truncate(list_no, new_size).await,
+            Self::Lmdb(writer) => writer.truncate(list_no, new_size).await,
+            Self::Object(writer) => writer.truncate(list_no, new_size).await,
         }
     }
 }
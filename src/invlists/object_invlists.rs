@@ -0,0 +1,167 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use serde::{Deserialize, Serialize};
+
+use super::{InvertedLists, InvertedListsReader, InvertedListsWriter};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Meta {
+    nlist: u32,
+    code_size: u32,
+    list_len: Vec<usize>,
+}
+
+/// 基于 `object_store` 的倒排列表实现，每个列表作为前缀下的一个独立 blob 存储，
+/// 元数据（`nlist`/`code_size`/每个列表的长度）缓存在内存中，并镜像写入前缀下的 `meta` 对象
+pub struct ObjectInvertedLists {
+    store: Arc<dyn ObjectStore>,
+    prefix: ObjectPath,
+    meta: Meta,
+}
+
+impl ObjectInvertedLists {
+    fn meta_path(&self) -> ObjectPath {
+        self.prefix.child("meta")
+    }
+
+    fn list_path(&self, list_no: u32) -> ObjectPath {
+        self.prefix.child("list").child(list_no.to_string())
+    }
+
+    async fn load_meta(store: &dyn ObjectStore, prefix: &ObjectPath) -> Result<Option<Meta>> {
+        match store.get(&prefix.child("meta")).await {
+            Ok(result) => {
+                let bytes = result.bytes().await?;
+                Ok(Some(bincode::deserialize(&bytes)?))
+            }
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 打开或创建一个基于对象存储的倒排列表，`nlist`/`code_size` 必须与已存在的元数据一致
+    pub async fn open(
+        store: Arc<dyn ObjectStore>,
+        prefix: ObjectPath,
+        nlist: u32,
+        code_size: u32,
+    ) -> Result<Self> {
+        let meta = match Self::load_meta(store.as_ref(), &prefix).await? {
+            Some(meta) => {
+                assert_eq!(meta.nlist, nlist, "nlist mismatch");
+                assert_eq!(meta.code_size, code_size, "code_size mismatch");
+                meta
+            }
+            None => {
+                let meta = Meta { nlist, code_size, list_len: vec![0; nlist as usize] };
+                store.put(&prefix.child("meta"), bincode::serialize(&meta)?.into()).await?;
+                meta
+            }
+        };
+        Ok(Self { store, prefix, meta })
+    }
+
+    async fn get_list_blob(&self, list_no: u32) -> (Vec<u64>, Vec<u8>) {
+        if self.meta.list_len[list_no as usize] == 0 {
+            return (vec![], vec![]);
+        }
+        let result = self.store.get(&self.list_path(list_no)).await.unwrap();
+        let bytes = result.bytes().await.unwrap();
+        bincode::deserialize(&bytes).unwrap()
+    }
+
+    async fn put_list_blob(&self, list_no: u32, ids: &[u64], codes: &[u8]) {
+        let data = bincode::serialize(&(ids, codes)).unwrap();
+        self.store.put(&self.list_path(list_no), data.into()).await.unwrap();
+    }
+}
+
+pub struct ObjectInvertedListsReader<'a>(&'a ObjectInvertedLists);
+
+pub struct ObjectInvertedListsWriter<'a>(&'a mut ObjectInvertedLists);
+
+#[async_trait]
+impl InvertedLists for ObjectInvertedLists {
+    type Reader<'a>
+        = ObjectInvertedListsReader<'a>
+    where
+        Self: 'a;
+    type Writer<'a>
+        = ObjectInvertedListsWriter<'a>
+    where
+        Self: 'a;
+
+    async fn reader(&self) -> Result<Self::Reader<'_>> {
+        Ok(ObjectInvertedListsReader(self))
+    }
+
+    async fn writer(&mut self) -> Result<Self::Writer<'_>> {
+        Ok(ObjectInvertedListsWriter(self))
+    }
+}
+
+#[async_trait]
+impl InvertedListsReader for ObjectInvertedListsReader<'_> {
+    fn nlist(&self) -> u32 {
+        self.0.meta.nlist
+    }
+
+    fn code_size(&self) -> u32 {
+        self.0.meta.code_size
+    }
+
+    fn list_len(&self, list_no: u32) -> usize {
+        self.0.meta.list_len[list_no as usize]
+    }
+
+    async fn get_list(&self, list_no: u32) -> (Cow<'_, [u64]>, Cow<'_, [u8]>) {
+        let (ids, codes) = self.0.get_list_blob(list_no).await;
+        (Cow::Owned(ids), Cow::Owned(codes))
+    }
+}
+
+#[async_trait]
+impl InvertedListsReader for ObjectInvertedListsWriter<'_> {
+    fn nlist(&self) -> u32 {
+        self.0.meta.nlist
+    }
+
+    fn code_size(&self) -> u32 {
+        self.0.meta.code_size
+    }
+
+    fn list_len(&self, list_no: u32) -> usize {
+        self.0.meta.list_len[list_no as usize]
+    }
+
+    async fn get_list(&self, list_no: u32) -> (Cow<'_, [u64]>, Cow<'_, [u8]>) {
+        let (ids, codes) = self.0.get_list_blob(list_no).await;
+        (Cow::Owned(ids), Cow::Owned(codes))
+    }
+}
+
+#[async_trait]
+impl InvertedListsWriter for ObjectInvertedListsWriter<'_> {
+    async fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[u8]) -> u64 {
+        let (mut ids_, mut codes_) = self.0.get_list_blob(list_no).await;
+        ids_.extend_from_slice(ids);
+        codes_.extend_from_slice(codes);
+        self.0.put_list_blob(list_no, &ids_, &codes_).await;
+        self.0.meta.list_len[list_no as usize] += ids.len();
+        self.0.store.put(&self.0.meta_path(), bincode::serialize(&self.0.meta).unwrap().into()).await.unwrap();
+        ids.len() as u64
+    }
+
+    async fn truncate(&mut self, list_no: u32, new_size: usize) {
+        let (ids, codes) = self.0.get_list_blob(list_no).await;
+        let code_size = self.0.meta.code_size as usize;
+        self.0.put_list_blob(list_no, &ids[..new_size], &codes[..new_size * code_size]).await;
+        self.0.meta.list_len[list_no as usize] = new_size;
+        self.0.store.put(&self.0.meta_path(), bincode::serialize(&self.0.meta).unwrap().into()).await.unwrap();
+    }
+}
@@ -1,8 +1,9 @@
 use std::borrow::Cow;
+use std::path::Path;
 
 use anyhow::Result;
 
-use super::InvertedLists;
+use super::{Codec, CompressionOptions, InvertedLists, MmapInvlists, save_invlists};
 
 /// 完全存储在内存中的倒排列表
 pub struct ArrayInvertedLists<const N: usize> {
@@ -15,6 +16,17 @@ impl<const N: usize> ArrayInvertedLists<N> {
     pub fn new(nlist: usize) -> Self {
         Self { nlist, codes: vec![vec![]; nlist], ids: vec![vec![]; nlist] }
     }
+
+    /// 将当前倒排列表写入磁盘，并冻结成只读的 [`MmapInvlists`] 供查询服务使用
+    ///
+    /// 写入过程只在内存变体（本类型）上进行，构建完成、不再变化后才调用本方法落盘：固定使用
+    /// [`Codec::None`]，保证落盘后的数据与内存布局一致，`MmapInvlists::get_list` 能直接在
+    /// mmap 上做零拷贝切片，而不必先解压一份到堆上
+    pub fn freeze(&self, path: impl AsRef<Path>, cache_capacity: usize) -> Result<MmapInvlists<N>> {
+        let options = CompressionOptions { codec: Codec::None, ..Default::default() };
+        save_invlists(self, &path, options)?;
+        MmapInvlists::load(path, cache_capacity)
+    }
 }
 
 impl<const N: usize> InvertedLists<N> for ArrayInvertedLists<N> {
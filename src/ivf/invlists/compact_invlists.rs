@@ -0,0 +1,98 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::{CompressionOptions, InvertedLists, OnDiskInvlists, save_invlists};
+
+/// 只读包装，压缩时丢弃已删除（墓碑）ID，并把剩余 ID 重新映射为新的连续编号
+struct CompactingInvlists<'a, const N: usize, T, D, R> {
+    inner: &'a T,
+    is_dead: D,
+    remap: R,
+}
+
+impl<'a, const N: usize, T, D, R> InvertedLists<N> for CompactingInvlists<'a, N, T, D, R>
+where
+    T: InvertedLists<N>,
+    D: Fn(u64) -> bool + Sync,
+    R: Fn(u64) -> u64 + Sync,
+{
+    fn nlist(&self) -> usize {
+        self.inner.nlist()
+    }
+
+    fn list_len(&self, list_no: usize) -> usize {
+        // 压缩后的长度只能通过实际过滤得到，调用频率很低，直接复用 get_list
+        self.get_list(list_no).map(|(ids, _)| ids.len()).unwrap_or(0)
+    }
+
+    fn get_list(&self, list_no: usize) -> Result<(Cow<'_, [u64]>, Cow<'_, [[u8; N]]>)> {
+        let (ids, codes) = self.inner.get_list(list_no)?;
+
+        let mut new_ids = Vec::with_capacity(ids.len());
+        let mut new_codes = Vec::with_capacity(codes.len());
+        for (&id, code) in ids.iter().zip(codes.iter()) {
+            if !(self.is_dead)(id) {
+                new_ids.push((self.remap)(id));
+                new_codes.push(*code);
+            }
+        }
+        Ok((Cow::Owned(new_ids), Cow::Owned(new_codes)))
+    }
+
+    fn add_entry(&mut self, _list_no: usize, _id: u64, _code: &[u8; N]) -> Result<()> {
+        unimplemented!("CompactingInvlists 只读，不支持写入")
+    }
+}
+
+/// 压缩磁盘倒排列表：丢弃 `is_dead` 判定为已删除的 ID，并用 `remap` 给剩余 ID 重新编号
+///
+/// 写入采用临时文件 + rename 的方式，避免中途失败导致磁盘文件损坏
+pub fn compact_ondisk_invlists<const N: usize>(
+    path: impl AsRef<Path>,
+    is_dead: impl Fn(u64) -> bool + Sync,
+    remap: impl Fn(u64) -> u64 + Sync,
+) -> Result<()> {
+    let path = path.as_ref();
+    let invlists = OnDiskInvlists::<N>::load(path)?;
+    let compacting = CompactingInvlists { inner: &invlists, is_dead, remap };
+
+    let tmp_path = path.with_extension("tmp");
+    save_invlists::<N, _, _>(&compacting, &tmp_path, CompressionOptions::default())?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ivf::ArrayInvertedLists;
+
+    #[test]
+    fn test_compact_ondisk_invlists_drops_dead_and_remaps_survivors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("invlists.bin");
+
+        // 特征 ID 2、3 属于待删除的图片（墓碑区间 [2, 4)），压缩后应当被丢弃，
+        // 且之后的 ID（4、5）整体前移 2 位
+        let mut array = ArrayInvertedLists::<4>::new(1);
+        for id in 0..6u64 {
+            array.add_entry(0, id, &[id as u8; 4]).unwrap();
+        }
+        save_invlists::<4, _, _>(&array, &path, CompressionOptions::default()).unwrap();
+
+        compact_ondisk_invlists::<4>(
+            &path,
+            |id| (2..4).contains(&id),
+            |id| if id >= 4 { id - 2 } else { id },
+        )
+        .unwrap();
+
+        let compacted = OnDiskInvlists::<4>::load(&path).unwrap();
+        let (ids, codes) = compacted.get_list(0).unwrap();
+        assert_eq!(ids.as_ref(), &[0, 1, 2, 3]);
+        // code 内容应当随原始 ID 搬运，而不是重新生成，remap 只影响 ID 本身
+        assert_eq!(codes.as_ref(), &[[0; 4], [1; 4], [4; 4], [5; 4]]);
+    }
+}
@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::{ArrayInvertedLists, CompressionOptions, InvertedLists, OnDiskInvlists, save_invlists};
+
+/// 在只读的 `OnDiskInvlists` 之上叠加一层可写的内存缓冲区（memtable）
+///
+/// 新增的向量只写入内存层，不需要重写磁盘文件；读取时则把磁盘层和内存层的数据拼接起来返回，
+/// 类似 LSM 存储引擎在读取时合并多个 memtable 的做法。调用 [`flush`](Self::flush) 可以把
+/// 内存层合并进一个新的磁盘文件，之后内存层会被清空，后续写入继续积累到新的 memtable 中
+pub struct LayeredInvlists<const N: usize> {
+    disk: OnDiskInvlists<N>,
+    memtable: ArrayInvertedLists<N>,
+}
+
+impl<const N: usize> LayeredInvlists<N> {
+    pub fn new(disk: OnDiskInvlists<N>) -> Self {
+        let memtable = ArrayInvertedLists::new(disk.nlist());
+        Self { disk, memtable }
+    }
+
+    /// 将内存层合并进一个新的磁盘文件，完成后清空内存层
+    ///
+    /// 合并后的每个倒排列表都会重新整体压缩写入，`list_len`/`list_offset`/`list_size`/
+    /// `list_split` 也会基于合并后的数据重新计算；写入采用临时文件 + rename 的方式，
+    /// 避免中途失败导致磁盘文件损坏
+    pub fn flush(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        if (0..self.memtable.nlist()).all(|i| self.memtable.list_len(i) == 0) {
+            return Ok(());
+        }
+
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        save_invlists::<N, _, _>(self, &tmp_path, CompressionOptions::default())?;
+        std::fs::rename(&tmp_path, path)?;
+
+        self.disk = OnDiskInvlists::load(path)?;
+        self.memtable = ArrayInvertedLists::new(self.disk.nlist());
+        Ok(())
+    }
+}
+
+impl<const N: usize> InvertedLists<N> for LayeredInvlists<N> {
+    fn nlist(&self) -> usize {
+        self.disk.nlist()
+    }
+
+    fn list_len(&self, list_no: usize) -> usize {
+        self.disk.list_len(list_no) + self.memtable.list_len(list_no)
+    }
+
+    fn get_list(&self, list_no: usize) -> Result<(Cow<'_, [u64]>, Cow<'_, [[u8; N]]>)> {
+        let (disk_ids, disk_codes) = self.disk.get_list(list_no)?;
+        let (mem_ids, mem_codes) = self.memtable.get_list(list_no)?;
+
+        if mem_ids.is_empty() {
+            return Ok((disk_ids, disk_codes));
+        }
+
+        let ids = [&*disk_ids, &*mem_ids].concat();
+        let codes = [&*disk_codes, &*mem_codes].concat();
+        Ok((Cow::Owned(ids), Cow::Owned(codes)))
+    }
+
+    fn add_entry(&mut self, list_no: usize, id: u64, code: &[u8; N]) -> Result<()> {
+        self.memtable.add_entry(list_no, id, code)
+    }
+}
@@ -19,6 +19,11 @@ struct Meta {
     code_size: u32,
     /// 每个倒排列表的元素数量，用于快速统计
     list_len: Vec<usize>,
+    /// 每个倒排列表当前的分段数量
+    ///
+    /// 每次 `add_entries` 都会追加一个新分段而不是重写整个列表，
+    /// 避免 `O(n^2)` 的读出-拼接-写回开销；读取时再把所有分段拼接起来
+    segments: Vec<u32>,
 }
 
 #[derive(Archive, Deserialize, Serialize)]
@@ -27,6 +32,13 @@ struct Entry<const N: usize> {
     codes: Vec<[u8; N]>,
 }
 
+/// 将 `(list_no, segment_no)` 编码为单个 u64 key
+///
+/// 高 32 位为列表编号，低 32 位为分段编号，编码后的 key 仍满足 8 字节对齐要求
+fn segment_key(list_no: u32, segment_no: u32) -> u64 {
+    ((list_no as u64) << 32) | segment_no as u64
+}
+
 pub struct LmdbInvertedLists<const N: usize> {
     /// lmdb env，此处使用了 Thread Local Storage 提升速度
     env: Env<WithTls>,
@@ -34,7 +46,7 @@ pub struct LmdbInvertedLists<const N: usize> {
     meta: Meta,
     /// 元数据数据库
     db_meta: Database<Str, Bytes>,
-    /// 倒排列表数据库
+    /// 倒排列表数据库，key 为 `segment_key(list_no, segment_no)`
     db_list: Database<U64<NativeEndian>, Bytes, IntegerComparator>,
 }
 
@@ -66,7 +78,12 @@ impl<const N: usize> LmdbInvertedLists<N> {
             .create(&mut txn)?;
         let meta = match db_meta.get(&mut txn, &"metadata")? {
             Some(meta) => rkyv::from_bytes::<Meta, rkyvError>(meta)?,
-            None => Meta { nlist, code_size: N as u32, list_len: vec![0; nlist as usize] },
+            None => Meta {
+                nlist,
+                code_size: N as u32,
+                list_len: vec![0; nlist as usize],
+                segments: vec![0; nlist as usize],
+            },
         };
         assert_eq!(meta.nlist, nlist, "nlist mismatch");
         assert_eq!(meta.code_size, N as u32, "code_size mismatch");
@@ -101,6 +118,36 @@ impl<const N: usize> InvertedLists<N> for LmdbInvertedLists<N> {
     }
 }
 
+/// 读取指定列表的所有分段并拼接为一个连续的结果
+///
+/// 只有一个分段时 codes 直接借用底层数据，避免不必要的拷贝；ids 需要转换字节序，总是拷贝一份
+fn read_segments<const N: usize>(
+    txn: &RoTxn<'_, WithTls>,
+    db_list: &Database<U64<NativeEndian>, Bytes, IntegerComparator>,
+    list_no: u32,
+    segments: u32,
+) -> Result<(Cow<'_, [u64]>, Cow<'_, [[u8; N]]>)> {
+    if segments == 0 {
+        return Ok((Cow::Borrowed(&[]), Cow::Borrowed(&[])));
+    }
+    if segments == 1 {
+        let data = db_list.get(txn, &segment_key(list_no, 0))?.unwrap();
+        let entry = rkyv::access::<ArchivedEntry<N>, rkyvError>(data)?;
+        let ids = entry.ids.iter().map(|x| x.to_native()).collect();
+        return Ok((Cow::Owned(ids), Cow::Borrowed(entry.codes.as_slice())));
+    }
+
+    let mut ids = Vec::new();
+    let mut codes = Vec::new();
+    for segment_no in 0..segments {
+        let data = db_list.get(txn, &segment_key(list_no, segment_no))?.unwrap();
+        let entry = rkyv::access::<ArchivedEntry<N>, rkyvError>(data)?;
+        ids.extend(entry.ids.iter().map(|x| x.to_native()));
+        codes.extend_from_slice(entry.codes.as_slice());
+    }
+    Ok((Cow::Owned(ids), Cow::Owned(codes)))
+}
+
 pub struct LmdbInvertedListsReader<'a, const N: usize> {
     txn: RoTxn<'a, WithTls>,
     meta: &'a Meta,
@@ -117,14 +164,7 @@ impl<const N: usize> InvertedListsReader<N> for LmdbInvertedListsReader<'_, N> {
     }
 
     fn get_list(&self, list_no: u32) -> Result<(Cow<[u64]>, Cow<[[u8; N]]>)> {
-        let len = self.list_len(list_no);
-        if len == 0 {
-            return Ok((Cow::Borrowed(&[]), Cow::Borrowed(&[])));
-        }
-        let data = self.db_list.get(&self.txn, &(list_no as u64))?.unwrap();
-        let entry = rkyv::access::<ArchivedEntry<N>, rkyvError>(data)?;
-        let ids = entry.ids.iter().map(|x| x.to_native()).collect();
-        Ok((Cow::Owned(ids), Cow::Borrowed(entry.codes.as_slice())))
+        read_segments(&self.txn, &self.db_list, list_no, self.meta.segments[list_no as usize])
     }
 }
 
@@ -156,45 +196,84 @@ impl<const N: usize> InvertedListsReader<N> for LmdbInvertedListsWriter<'_, N> {
     }
 
     fn get_list(&self, list_no: u32) -> Result<(Cow<[u64]>, Cow<[[u8; N]]>)> {
-        let len = self.list_len(list_no);
-        if len == 0 {
-            return Ok((Cow::Borrowed(&[]), Cow::Borrowed(&[])));
-        }
         let txn = self.txn.as_ref().unwrap();
-        let data = self.db_list.get(txn, &(list_no as u64))?.unwrap();
-        let entry = rkyv::access::<ArchivedEntry<N>, rkyvError>(data)?;
-        let ids = entry.ids.iter().map(|x| x.to_native()).collect();
-        Ok((Cow::Owned(ids), Cow::Borrowed(entry.codes.as_slice())))
+        read_segments(txn, &self.db_list, list_no, self.meta.segments[list_no as usize])
     }
 }
 
 impl<const N: usize> InvertedListsWriter<N> for LmdbInvertedListsWriter<'_, N> {
     fn add_entries(&mut self, list_no: u32, ids: &[u64], codes: &[[u8; N]]) -> Result<u64> {
         assert_eq!(ids.len(), codes.len(), "ids and codes length mismatch");
-        let (oids, ocodes) = self.get_list(list_no)?;
         let added = ids.len();
+        if added == 0 {
+            return Ok(0);
+        }
 
-        let data = rkyv::to_bytes::<rkyvError>(&Entry {
-            ids: [&*oids, ids].concat(),
-            codes: [&*ocodes, codes].concat(),
-        })?;
+        // 追加一个新分段，而不是读出整个列表再重写，避免 O(n^2) 开销
+        let segment_no = self.meta.segments[list_no as usize];
+        let data = rkyv::to_bytes::<rkyvError>(&Entry { ids: ids.to_vec(), codes: codes.to_vec() })?;
 
         let txn = self.txn.as_mut().unwrap();
-        self.db_list.put(txn, &(list_no as u64), &data)?;
+        self.db_list.put(txn, &segment_key(list_no, segment_no), &data)?;
+        self.meta.segments[list_no as usize] += 1;
         self.meta.list_len[list_no as usize] += added;
 
         Ok(added as u64)
     }
 
     fn clear(&mut self, list_no: u32) -> Result<()> {
-        let data = rkyv::to_bytes::<rkyvError>(&Entry::<N> { ids: vec![], codes: vec![] })?;
+        let segments = self.meta.segments[list_no as usize];
         let txn = self.txn.as_mut().unwrap();
-        self.db_list.put(txn, &(list_no as u64), &data)?;
+        for segment_no in 0..segments {
+            self.db_list.delete(txn, &segment_key(list_no, segment_no))?;
+        }
+        self.meta.segments[list_no as usize] = 0;
         self.meta.list_len[list_no as usize] = 0;
         Ok(())
     }
 }
 
+impl<'a, const N: usize> LmdbInvertedListsWriter<'a, N> {
+    /// 将指定列表的所有分段合并为一个分段，减少后续读取时的拼接开销
+    ///
+    /// 新增数据不断追加分段会导致分段数量线性增长，读取时需要拼接的次数也随之增加，
+    /// 因此提供该方法用于在后台或空闲时主动压缩
+    pub fn compact(&mut self, list_no: u32) -> Result<()> {
+        let segments = self.meta.segments[list_no as usize];
+        if segments <= 1 {
+            return Ok(());
+        }
+
+        let (ids, codes) = InvertedListsReader::get_list(self, list_no)?;
+        let ids = ids.into_owned();
+        let codes = codes.into_owned();
+
+        let txn = self.txn.as_mut().unwrap();
+        for segment_no in 0..segments {
+            self.db_list.delete(txn, &segment_key(list_no, segment_no))?;
+        }
+
+        let data = rkyv::to_bytes::<rkyvError>(&Entry { ids, codes })?;
+        let txn = self.txn.as_mut().unwrap();
+        self.db_list.put(txn, &segment_key(list_no, 0), &data)?;
+        self.meta.segments[list_no as usize] = 1;
+
+        Ok(())
+    }
+
+    /// 合并另一个倒排列表的所有数据，合并后源倒排列表会被清空
+    pub fn merge_from<'b>(&mut self, other: &mut LmdbInvertedListsWriter<'b, N>) -> Result<()> {
+        for list_no in 0..self.meta.nlist {
+            let (ids, codes) = InvertedListsReader::get_list(other, list_no)?;
+            if !ids.is_empty() {
+                self.add_entries(list_no, &ids, &codes)?;
+            }
+            other.clear(list_no)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tempfile::tempdir;
@@ -367,4 +446,51 @@ mod tests {
             assert_eq!(reader2.list_len(i), 0);
         }
     }
+
+    #[test]
+    fn test_segmented_add_does_not_rewrite_list() {
+        let temp_dir = tempdir().unwrap();
+        let mut invlists = LmdbInvertedLists::<16>::new(temp_dir.path(), 1).unwrap();
+
+        // 多次小批量写入，每次都只新增一个分段，而不会重写之前的数据
+        {
+            let mut writer = invlists.writer().unwrap();
+            for batch in 0..5 {
+                let (ids, codes) = create_test_data(2);
+                let ids: Vec<u64> = ids.into_iter().map(|x| x + batch * 10).collect();
+                writer.add_entries(0, &ids, &codes).unwrap();
+            }
+            assert_eq!(writer.meta.segments[0], 5);
+        }
+
+        let reader = invlists.reader().unwrap();
+        assert_eq!(reader.list_len(0), 10);
+        let (ids, _) = reader.get_list(0).unwrap();
+        assert_eq!(ids.len(), 10);
+    }
+
+    #[test]
+    fn test_compact_merges_segments() {
+        let temp_dir = tempdir().unwrap();
+        let mut invlists = LmdbInvertedLists::<16>::new(temp_dir.path(), 1).unwrap();
+
+        {
+            let mut writer = invlists.writer().unwrap();
+            for batch in 0..3 {
+                let (ids, codes) = create_test_data(2);
+                let ids: Vec<u64> = ids.into_iter().map(|x| x + batch * 10).collect();
+                writer.add_entries(0, &ids, &codes).unwrap();
+            }
+            assert_eq!(writer.meta.segments[0], 3);
+
+            writer.compact(0).unwrap();
+            assert_eq!(writer.meta.segments[0], 1);
+        }
+
+        // 压缩后数据和长度保持不变
+        let reader = invlists.reader().unwrap();
+        assert_eq!(reader.list_len(0), 6);
+        let (ids, _) = reader.get_list(0).unwrap();
+        assert_eq!(ids.as_ref(), &[1, 2, 11, 12, 21, 22]);
+    }
 }
@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use binrw::BinRead;
+use bytemuck::{cast_slice_mut, try_cast_slice};
+use memmap2::Mmap;
+use zstd::dict::DecoderDictionary;
+
+use super::decompress_block;
+use crate::ivf::{Codec, InvertedLists, OnDiskIvfMetadata};
+use crate::lru::LruCache;
+
+/// 单个倒排列表解压后的数据，以 `Arc` 缓存在 LRU 中以便廉价地克隆给调用方
+struct CachedList<const N: usize> {
+    ids: Vec<u64>,
+    codes: Vec<[u8; N]>,
+}
+
+/// 基于 mmap 的磁盘倒排列表读取器
+///
+/// 与 [`super::OnDiskInvlists`] 使用 `pread` 不同，这里直接在整个文件的 mmap 映射上按
+/// `list_offset`/`list_split`/`list_size` 切片：`codec` 为 [`Codec::None`] 时可以直接用
+/// `bytemuck` 把映射区间转换成目标类型的切片原地返回，是探测命中列表这条常见路径下真正的
+/// 零拷贝读取；其余 codec 需要先解压，解压结果按 `list_no` 缓存在一个有界 LRU 中，避免热门
+/// 聚类中心在每次查询时都被重新解压
+pub struct MmapInvlists<const N: usize> {
+    metadata: OnDiskIvfMetadata,
+    codec: Codec,
+    dict: Option<DecoderDictionary<'static>>,
+    mmap: Mmap,
+    // mmap 已经持有了文件映射，这里保留句柄只是为了让文件描述符和 mmap 的生命周期绑定一致
+    _file: File,
+    cache: Mutex<LruCache<usize, Arc<CachedList<N>>>>,
+}
+
+impl<const N: usize> MmapInvlists<N> {
+    /// 加载磁盘倒排列表，`cache_capacity` 为解压结果 LRU 缓存能容纳的列表数量
+    pub fn load(path: impl AsRef<Path>, cache_capacity: usize) -> Result<Self> {
+        let file = File::options().read(true).open(path)?;
+
+        // mmap 映射在 Self 中持有，生命周期覆盖所有借用自 mmap 的返回值
+        let mmap = unsafe { Mmap::map(&file)? };
+        let metadata = OnDiskIvfMetadata::read(&mut Cursor::new(&mmap))?;
+
+        assert_eq!(metadata.code_size, N as u64, "code_size mismatch");
+        let codec = Codec::from_id(metadata.codec)?;
+        let dict = (!metadata.dict.is_empty()).then(|| DecoderDictionary::copy(&metadata.dict));
+
+        Ok(Self {
+            metadata,
+            codec,
+            dict,
+            mmap,
+            _file: file,
+            cache: Mutex::new(LruCache::new(cache_capacity)),
+        })
+    }
+
+    // 加载一个倒排列表的长度、偏移量、大小和分割点
+    fn list_info(&self, list_no: usize) -> (usize, usize, usize, usize) {
+        let len = self.metadata.list_len[list_no] as usize;
+        let offset = self.metadata.list_offset[list_no] as usize;
+        let size = self.metadata.list_size[list_no] as usize;
+        let split = self.metadata.list_split[list_no] as usize;
+        (len, offset, size, split)
+    }
+}
+
+impl<const N: usize> InvertedLists<N> for MmapInvlists<N> {
+    #[inline(always)]
+    fn nlist(&self) -> usize {
+        self.metadata.nlist as usize
+    }
+
+    #[inline(always)]
+    fn list_len(&self, list_no: usize) -> usize {
+        self.metadata.list_len[list_no] as usize
+    }
+
+    fn get_list(&self, list_no: usize) -> Result<(Cow<'_, [u64]>, Cow<'_, [[u8; N]]>)> {
+        let (len, offset, size, split) = self.list_info(list_no);
+        let region = &self.mmap[offset..offset + size];
+        let (ids_region, codes_region) = region.split_at(split);
+
+        // None codec 的数据就是原始字节，优先直接转换成目标类型的切片零拷贝返回；只有前面
+        // 列表的字节数导致这个区间没有按对应类型的对齐要求对齐时，才退化成拷贝一份
+        if self.codec == Codec::None {
+            let ids = match try_cast_slice::<u8, u64>(ids_region) {
+                Ok(ids) => Cow::Borrowed(ids),
+                Err(_) => Cow::Owned(
+                    ids_region
+                        .chunks_exact(8)
+                        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+                        .collect(),
+                ),
+            };
+            let codes = match try_cast_slice::<u8, [u8; N]>(codes_region) {
+                Ok(codes) => Cow::Borrowed(codes),
+                Err(_) => Cow::Owned(
+                    codes_region
+                        .chunks_exact(N)
+                        .map(|c| {
+                            let mut code = [0u8; N];
+                            code.copy_from_slice(c);
+                            code
+                        })
+                        .collect(),
+                ),
+            };
+            return Ok((ids, codes));
+        }
+
+        // 非 None codec：先查缓存，命中就直接克隆缓存的解压结果，避免重复解压
+        let cached = self.cache.lock().unwrap().get(&list_no).cloned();
+        if let Some(cached) = cached {
+            return Ok((Cow::Owned(cached.ids.clone()), Cow::Owned(cached.codes.clone())));
+        }
+
+        let mut ids = vec![0u64; len];
+        let mut codes = vec![[0u8; N]; len];
+        decompress_block(self.codec, ids_region, cast_slice_mut(&mut ids), None)?;
+        decompress_block(self.codec, codes_region, codes.as_flattened_mut(), self.dict.as_ref())?;
+
+        let cached = Arc::new(CachedList { ids: ids.clone(), codes: codes.clone() });
+        self.cache.lock().unwrap().put(list_no, cached);
+
+        Ok((Cow::Owned(ids), Cow::Owned(codes)))
+    }
+
+    fn add_entry(&mut self, _list_no: usize, _id: u64, _code: &[u8; N]) -> Result<()> {
+        unimplemented!("MmapInvlists 不支持更新操作")
+    }
+}
@@ -1,5 +1,10 @@
 mod array_invlists;
+mod compact_invlists;
+mod layered_invlists;
+mod mmap_invlists;
 mod ondisk_invlists;
+mod pq_invlists;
+mod varint_invlists;
 mod vstack_invlists;
 
 use std::borrow::Cow;
@@ -7,18 +12,108 @@ use std::fs::File;
 use std::io::{BufWriter, Seek, SeekFrom, Write};
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 pub use array_invlists::*;
 use binrw::{BinWrite, binrw};
 use bytemuck::cast_slice;
+pub use compact_invlists::*;
 use itertools::izip;
+pub use layered_invlists::*;
+pub use mmap_invlists::*;
 pub use ondisk_invlists::*;
+pub use pq_invlists::*;
 use rayon::prelude::*;
+pub use varint_invlists::*;
 pub use vstack_invlists::*;
-use zstd::bulk::compress;
+use zstd::bulk::{Compressor, Decompressor, compress, decompress_to_buffer};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
 
 use crate::kmodes::imbalance_factor;
 
+/// 倒排列表数据块使用的压缩算法，持久化为 [`OnDiskIvfMetadata::codec`] 中的一个字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// 不压缩，原样存储
+    None,
+    /// zstd，压缩比更高，适合很少访问的冷数据归档
+    Zstd,
+    /// lz4，压缩/解压速度快，适合对延迟敏感的查询节点
+    Lz4,
+}
+
+impl Codec {
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Zstd),
+            2 => Ok(Codec::Lz4),
+            // 文件可能长期存在，未识别的 codec id 直接报错，而不是静默当成某个默认算法处理
+            other => Err(anyhow!("未知的倒排列表压缩算法 id: {other}")),
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Zstd => 1,
+            Codec::Lz4 => 2,
+        }
+    }
+}
+
+/// [`save_invlists`] 的压缩参数
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionOptions {
+    /// 压缩算法
+    pub codec: Codec,
+    /// zstd 压缩等级，`codec` 不是 [`Codec::Zstd`] 时忽略
+    pub level: i32,
+    /// 是否训练并使用共享字典压缩 codes，仅 [`Codec::Zstd`] 支持；`nlist` 较大时大部分列表
+    /// 很小，单独压缩比例不高，字典能让这些小列表复用全局统计信息，明显提升压缩率
+    pub train_dict: bool,
+    /// 训练字典的目标大小（字节），常见取值为 64KiB ~ 256KiB
+    pub dict_size: usize,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self { codec: Codec::Zstd, level: 0, train_dict: false, dict_size: 112 * 1024 }
+    }
+}
+
+/// 从倒排列表中采样未压缩的 codes 原始字节，训练共享 zstd 字典
+///
+/// 为避免 `nlist` 很大时全量读取所有列表，采样语料上限为字典大小的 100 倍；
+/// 所有列表都为空（或 `dict_size` 为 0）时返回空字典，调用方应退化为不使用字典
+fn train_dictionary<const N: usize, T>(invlists: &T, dict_size: usize) -> Result<Vec<u8>>
+where
+    T: InvertedLists<N> + Sync,
+{
+    if dict_size == 0 {
+        return Ok(Vec::new());
+    }
+
+    let sample_budget = dict_size.saturating_mul(100);
+    let mut samples = Vec::new();
+    let mut sample_sizes = Vec::new();
+    for i in 0..invlists.nlist() {
+        if samples.len() >= sample_budget {
+            break;
+        }
+        let (_, codes) = invlists.get_list(i)?;
+        if codes.is_empty() {
+            continue;
+        }
+        let bytes = codes.as_flattened();
+        samples.extend_from_slice(bytes);
+        sample_sizes.push(bytes.len());
+    }
+    if sample_sizes.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(zstd::dict::from_continuous(&samples, &sample_sizes, dict_size)?)
+}
+
 pub trait InvertedLists<const N: usize> {
     /// 返回倒排表的列表数量
     fn nlist(&self) -> usize;
@@ -51,7 +146,11 @@ pub trait InvertedLists<const N: usize> {
 }
 
 /// 保存到文件
-pub fn save_invlists<const N: usize, P, T>(invlists: &T, path: P) -> Result<()>
+pub fn save_invlists<const N: usize, P, T>(
+    invlists: &T,
+    path: P,
+    options: CompressionOptions,
+) -> Result<()>
 where
     P: AsRef<Path>,
     T: InvertedLists<N> + Sync,
@@ -59,8 +158,21 @@ where
     let file = File::create(path)?;
     let mut writer = BufWriter::new(file);
 
+    // 字典训练需要先确定大小，必须在写入 metadata 占位之前完成，否则训练出的字典一旦非空，
+    // metadata 的实际大小就会变化，导致后续覆盖写入时与已经写入的倒排列表数据重叠
+    let dict = if options.codec == Codec::Zstd && options.train_dict {
+        train_dictionary::<N, _>(invlists, options.dict_size)?
+    } else {
+        Vec::new()
+    };
+    let encoder_dict = (!dict.is_empty()).then(|| EncoderDictionary::copy(&dict, options.level));
+
     // 提前写入 metadata 占位，后续再来覆盖
     let mut metadata = OnDiskIvfMetadata::new(invlists.nlist(), N);
+    metadata.codec = options.codec.id();
+    metadata.level = options.level;
+    metadata.dict_len = dict.len() as u64;
+    metadata.dict = dict;
     metadata.write(&mut writer)?;
 
     // 注意此处 offset 为刨去 metadata 后的偏移量
@@ -69,12 +181,19 @@ where
     // TODO: 增加写入进度条
     rayon::scope(|s| {
         let (tx, rx) = crossbeam_channel::bounded(num_cpus::get());
+        let encoder_dict = &encoder_dict;
         s.spawn(move |_| {
             (0..invlists.nlist()).into_par_iter().for_each(|i| {
                 let (ids, codes) = invlists.get_list(i).unwrap();
                 let list_len = ids.len();
-                let ids = compress(cast_slice(&ids), 0).unwrap();
-                let codes = compress(codes.as_flattened(), 0).unwrap();
+                let ids = compress_block(options.codec, cast_slice(&ids), options.level, None).unwrap();
+                let codes = compress_block(
+                    options.codec,
+                    codes.as_flattened(),
+                    options.level,
+                    encoder_dict.as_ref(),
+                )
+                .unwrap();
                 tx.send((i, list_len, ids, codes)).unwrap();
             });
         });
@@ -89,6 +208,52 @@ where
     Ok(())
 }
 
+/// 按 `codec` 压缩一个数据块；`encoder_dict` 仅在 `codec` 为 [`Codec::Zstd`] 时生效
+fn compress_block(
+    codec: Codec,
+    data: &[u8],
+    level: i32,
+    encoder_dict: Option<&EncoderDictionary>,
+) -> Result<Vec<u8>> {
+    match codec {
+        Codec::None => Ok(data.to_vec()),
+        Codec::Zstd => match encoder_dict {
+            Some(dict) => {
+                let mut compressor = Compressor::with_prepared_dictionary(dict)?;
+                Ok(compressor.compress(data)?)
+            }
+            None => Ok(compress(data, level)?),
+        },
+        Codec::Lz4 => Ok(lz4_flex::block::compress(data)),
+    }
+}
+
+/// 按 `codec` 将一个数据块解压到 `out`，`out` 的长度即为期望的解压后大小；
+/// `dict` 仅在 `codec` 为 [`Codec::Zstd`] 且该文件写入时启用了字典训练时才生效
+fn decompress_block(
+    codec: Codec,
+    data: &[u8],
+    out: &mut [u8],
+    dict: Option<&DecoderDictionary>,
+) -> Result<()> {
+    match codec {
+        Codec::None => out.copy_from_slice(data),
+        Codec::Zstd => match dict {
+            Some(dict) => {
+                let mut decompressor = Decompressor::with_prepared_dictionary(dict)?;
+                decompressor.decompress_to_buffer(data, out)?;
+            }
+            None => {
+                decompress_to_buffer(data, out)?;
+            }
+        },
+        Codec::Lz4 => {
+            lz4_flex::block::decompress_into(data, out).map_err(|e| anyhow!("lz4 解压失败: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
 #[binrw]
 #[brw(little)]
 pub struct OnDiskIvfMetadata {
@@ -108,6 +273,15 @@ pub struct OnDiskIvfMetadata {
     /// 单个倒排列表中 id 和 code 部分的分割点
     #[br(count = nlist)]
     pub list_split: Vec<u64>,
+    /// 压缩等级，记录后重新保存（compact/merge/flush）时可以复现相同的压缩参数
+    pub level: i32,
+    /// 共享字典长度，为 0 表示未启用字典训练，codes 按普通 zstd 方式压缩
+    pub dict_len: u64,
+    /// 共享字典数据，由 `codes` 的采样语料训练得到，用于压缩/解压每个倒排列表的 codes
+    #[br(count = dict_len)]
+    pub dict: Vec<u8>,
+    /// 压缩算法 id，参见 [`Codec`]；未识别的 id 在读取时会报错而不是被静默忽略
+    pub codec: u8,
 }
 
 impl OnDiskIvfMetadata {
@@ -119,6 +293,10 @@ impl OnDiskIvfMetadata {
             list_offset: vec![0; nlist],
             list_size: vec![0; nlist],
             list_split: vec![0; nlist],
+            level: 0,
+            dict_len: 0,
+            dict: Vec::new(),
+            codec: Codec::Zstd.id(),
         }
     }
 }
@@ -9,9 +9,10 @@ use anyhow::Result;
 use binrw::BinRead;
 use bytemuck::cast_slice_mut;
 use memmap2::Mmap;
-use zstd::bulk::decompress_to_buffer;
+use zstd::dict::DecoderDictionary;
 
-use crate::ivf::{InvertedLists, OnDiskIvfMetadata};
+use super::decompress_block;
+use crate::ivf::{Codec, InvertedLists, OnDiskIvfMetadata};
 
 thread_local! {
     static READ_BUFFER: RefCell<Vec<u8>> = RefCell::new(vec![0u8; 1024]);
@@ -23,6 +24,10 @@ pub struct OnDiskInvlists<const N: usize> {
     metadata: OnDiskIvfMetadata,
     /// 文件句柄
     file: File,
+    /// 写入该文件时使用的压缩算法
+    codec: Codec,
+    /// codes 共享字典，仅 `codec` 为 [`Codec::Zstd`] 且写入时启用了字典训练时才存在
+    dict: Option<DecoderDictionary<'static>>,
 }
 
 impl<const N: usize> OnDiskInvlists<N> {
@@ -35,7 +40,9 @@ impl<const N: usize> OnDiskInvlists<N> {
         let metadata = OnDiskIvfMetadata::read(&mut Cursor::new(&mmap))?;
 
         assert_eq!(metadata.code_size, N as u64, "code_size mismatch");
-        Ok(Self { metadata, file })
+        let codec = Codec::from_id(metadata.codec)?;
+        let dict = (!metadata.dict.is_empty()).then(|| DecoderDictionary::copy(&metadata.dict));
+        Ok(Self { metadata, file, codec, dict })
     }
 
     // 加载一个倒排列表的长度，偏移量、大小和分割点
@@ -80,8 +87,13 @@ impl<const N: usize> InvertedLists<N> for OnDiskInvlists<N> {
             unsafe { codes_buf.set_len(len) };
 
             // TODO: 是否需要延迟解压？
-            decompress_to_buffer(ids, cast_slice_mut(&mut ids_buf))?;
-            decompress_to_buffer(codes, codes_buf.as_flattened_mut())?;
+            decompress_block(self.codec, ids, cast_slice_mut(&mut ids_buf), None)?;
+            decompress_block(
+                self.codec,
+                codes,
+                codes_buf.as_flattened_mut(),
+                self.dict.as_ref(),
+            )?;
             Ok((Cow::Owned(ids_buf), Cow::Owned(codes_buf)))
         })
     }
@@ -91,7 +103,7 @@ impl<const N: usize> InvertedLists<N> for OnDiskInvlists<N> {
     }
 }
 
-unsafe fn reserve_and_set_len<T>(vec: &mut Vec<T>, size: usize) {
+pub(super) unsafe fn reserve_and_set_len<T>(vec: &mut Vec<T>, size: usize) {
     vec.clear();
     vec.reserve(size);
     unsafe { vec.set_len(size) };
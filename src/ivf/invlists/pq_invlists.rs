@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use anyhow::Result;
+use binrw::{BinRead, BinWrite};
+use bytemuck::{cast_slice, cast_slice_mut};
+use memmap2::Mmap;
+use zstd::bulk::{compress, decompress_to_buffer};
+
+use super::ondisk_invlists::reserve_and_set_len;
+use super::{InvertedLists, OnDiskIvfMetadata, write_one_list};
+use crate::ivf::utils::TopKNeighbors;
+use crate::ivf::{Neighbor, PqCodec};
+
+thread_local! {
+    static READ_BUFFER: RefCell<Vec<u8>> = RefCell::new(vec![0u8; 1024]);
+}
+
+/// PQ 编码的磁盘倒排列表
+///
+/// 复用和 [`super::OnDiskInvlists`] 相同的 `OnDiskIvfMetadata` 文件格式，唯一区别是每条记录
+/// 存储的是 `codec.m()` 字节的 PQ 编码，而不是完整的 N 字节残差码。由于编码宽度不是编译期常量
+/// N，这里不实现通用的 `InvertedLists<N>` trait，而是直接提供基于非对称距离计算（ADC）的检索
+/// 接口，搜索时不需要先把编码解压成完整向量
+pub struct PqOnDiskInvlists<const N: usize> {
+    metadata: OnDiskIvfMetadata,
+    file: File,
+    codec: PqCodec<N>,
+}
+
+impl<const N: usize> PqOnDiskInvlists<N> {
+    /// 加载磁盘倒排列表，`codec` 必须和写入该文件时使用的编码器一致
+    pub fn load(path: impl AsRef<Path>, codec: PqCodec<N>) -> Result<Self> {
+        let file = File::options().read(true).write(true).open(path)?;
+
+        let mmap = unsafe { Mmap::map(&file)? };
+        let metadata = OnDiskIvfMetadata::read(&mut Cursor::new(&mmap))?;
+
+        assert_eq!(metadata.code_size, codec.m() as u64, "code_size 与 PQ 编码器的 m 不匹配");
+        Ok(Self { metadata, file, codec })
+    }
+
+    pub fn nlist(&self) -> usize {
+        self.metadata.nlist as usize
+    }
+
+    pub fn list_len(&self, list_no: usize) -> usize {
+        self.metadata.list_len[list_no] as usize
+    }
+
+    fn list_info(&self, list_no: usize) -> (usize, usize, usize, usize) {
+        let len = self.metadata.list_len[list_no] as usize;
+        let offset = self.metadata.list_offset[list_no] as usize;
+        let size = self.metadata.list_size[list_no] as usize;
+        let split = self.metadata.list_split[list_no] as usize;
+        (len, offset, size, split)
+    }
+
+    /// 读取一个倒排列表，返回 ID 列表和展平的 PQ 编码（每条记录 `codec.m()` 字节）
+    fn get_list(&self, list_no: usize) -> Result<(Vec<u64>, Vec<u8>)> {
+        let (len, offset, size, split) = self.list_info(list_no);
+
+        READ_BUFFER.with(|buf| {
+            let mut buf = buf.borrow_mut();
+            unsafe { reserve_and_set_len(&mut buf, size) };
+
+            self.file.read_exact_at(&mut buf, offset as u64)?;
+
+            let (ids, codes) = buf.split_at(split);
+
+            let mut ids_buf: Vec<u64> = Vec::with_capacity(len);
+            let mut codes_buf: Vec<u8> = Vec::with_capacity(len * self.codec.m());
+            unsafe { ids_buf.set_len(len) };
+            unsafe { codes_buf.set_len(len * self.codec.m()) };
+
+            decompress_to_buffer(ids, cast_slice_mut(&mut ids_buf))?;
+            decompress_to_buffer(codes, &mut codes_buf)?;
+            Ok((ids_buf, codes_buf))
+        })
+    }
+
+    /// 用非对称距离计算（ADC）在指定倒排列表中搜索 `query` 的 `k` 个最近邻
+    ///
+    /// 先对 `query` 预计算一次 `m × 256` 的距离表，再对列表里的每条编码查表求和，不需要把编码
+    /// 还原成完整的 N 字节向量
+    pub fn search_list(&self, list_no: usize, query: &[u8; N], k: usize) -> Result<Vec<Neighbor>> {
+        let (ids, codes) = self.get_list(list_no)?;
+        let table = self.codec.distance_table(query);
+
+        let m = self.codec.m();
+        let mut topk = TopKNeighbors::new(k);
+        topk.extend(ids.iter().zip(codes.chunks_exact(m)).map(|(&id, code)| Neighbor {
+            id,
+            distance: PqCodec::<N>::asymmetric_distance(&table, code),
+        }));
+        Ok(topk.into_sorted_vec())
+    }
+}
+
+/// 用 PQ 编码器把一个现有的倒排列表（存储完整 N 字节残差码）压缩写入磁盘文件
+///
+/// 复用 [`super::save_invlists`] 同款的分段压缩格式，只是每条记录写入前先经过 `codec.encode`
+/// 编码成 `m` 字节，而不是直接压缩原始的 N 字节
+pub fn save_pq_invlists<const N: usize, T>(
+    invlists: &T,
+    codec: &PqCodec<N>,
+    path: impl AsRef<Path>,
+) -> Result<()>
+where
+    T: InvertedLists<N> + Sync,
+{
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+
+    let mut metadata = OnDiskIvfMetadata::new(invlists.nlist(), codec.m());
+    metadata.write(&mut writer)?;
+    let mut offset = writer.stream_position()?;
+
+    for i in 0..invlists.nlist() {
+        let (ids, vectors) = invlists.get_list(i)?;
+        let list_len = ids.len();
+        let codes: Vec<u8> = vectors.iter().flat_map(|v| codec.encode(v)).collect();
+
+        let ids_c = compress(cast_slice(&ids), 0)?;
+        let codes_c = compress(&codes, 0)?;
+        write_one_list(&mut writer, &mut metadata, i, list_len, &ids_c, &codes_c, &mut offset)?;
+    }
+
+    writer.seek(SeekFrom::Start(0))?;
+    metadata.write(&mut writer)?;
+    Ok(())
+}
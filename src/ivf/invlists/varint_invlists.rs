@@ -0,0 +1,164 @@
+use std::borrow::Cow;
+
+use anyhow::Result;
+
+use super::InvertedLists;
+
+/// 对 `ids` 做 delta + LEB128 varint 编码的倒排列表
+///
+/// 同一个倒排列表内的 ID 排序后只存储相邻间隔（gap），相比定长 `u64` 数组通常能省下 4~8
+/// 倍空间；`codes` 按排序后的顺序原样存储，和解码出的 ids 一一对应。因为写入时会重新排序，
+/// 同一个列表内 ID 相对于插入顺序不再保留，调用方不应依赖原始插入顺序
+pub struct VarintInvertedLists<const N: usize> {
+    nlist: usize,
+    list_len: Vec<usize>,
+    ids: Vec<Vec<u8>>,
+    codes: Vec<Vec<[u8; N]>>,
+}
+
+impl<const N: usize> VarintInvertedLists<N> {
+    pub fn new(nlist: usize) -> Self {
+        Self {
+            nlist,
+            list_len: vec![0; nlist],
+            ids: vec![Vec::new(); nlist],
+            codes: vec![Vec::new(); nlist],
+        }
+    }
+
+    /// 把一个列表截断到前 `new_len` 个元素（按 ID 升序）
+    ///
+    /// delta 编码下后面的 gap 都依赖前一个 id，不能像定长数组那样直接截断字节流，这里先完整
+    /// 解码、截断，再用剩下的 id 重新生成 varint 流
+    pub fn truncate(&mut self, list_no: usize, new_len: usize) {
+        if new_len >= self.list_len[list_no] {
+            return;
+        }
+
+        let ids = decode_ids(&self.ids[list_no], self.list_len[list_no]);
+        self.ids[list_no] = encode_ids(&ids[..new_len]);
+        self.codes[list_no].truncate(new_len);
+        self.list_len[list_no] = new_len;
+    }
+}
+
+impl<const N: usize> InvertedLists<N> for VarintInvertedLists<N> {
+    fn nlist(&self) -> usize {
+        self.nlist
+    }
+
+    fn list_len(&self, list_no: usize) -> usize {
+        self.list_len[list_no]
+    }
+
+    fn get_list(&self, list_no: usize) -> Result<(Cow<'_, [u64]>, Cow<'_, [[u8; N]]>)> {
+        let ids = decode_ids(&self.ids[list_no], self.list_len[list_no]);
+        Ok((Cow::Owned(ids), Cow::Borrowed(&self.codes[list_no])))
+    }
+
+    fn add_entry(&mut self, list_no: usize, id: u64, code: &[u8; N]) -> Result<()> {
+        self.add_entries(list_no, std::slice::from_ref(&id), std::slice::from_ref(code))
+    }
+
+    fn add_entries(&mut self, list_no: usize, ids: &[u64], codes: &[[u8; N]]) -> Result<()> {
+        // 每次写入都要重新排序，因此批量写入（一次性传入整批 ids/codes）比逐个调用
+        // add_entry 划算得多：后者每插入一个元素都要解码、排序、重新编码一整个列表
+        let old_ids = decode_ids(&self.ids[list_no], self.list_len[list_no]);
+
+        let mut merged: Vec<(u64, [u8; N])> = Vec::with_capacity(old_ids.len() + ids.len());
+        merged.extend(old_ids.into_iter().zip(self.codes[list_no].iter().copied()));
+        merged.extend(ids.iter().copied().zip(codes.iter().copied()));
+        merged.sort_unstable_by_key(|(id, _)| *id);
+
+        let (ids, codes): (Vec<u64>, Vec<[u8; N]>) = merged.into_iter().unzip();
+        self.list_len[list_no] = ids.len();
+        self.ids[list_no] = encode_ids(&ids);
+        self.codes[list_no] = codes;
+        Ok(())
+    }
+}
+
+/// 把已排序的 ids 编码为 LEB128 varint 流：首个 id 原样写入，之后依次写入与前一个 id 的差值
+fn encode_ids(ids: &[u64]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut prev = 0u64;
+    for &id in ids {
+        write_varint(&mut out, id - prev);
+        prev = id;
+    }
+    out
+}
+
+/// 解码 varint 流，还原出升序排列的 `len` 个 ids
+fn decode_ids(data: &[u8], len: usize) -> Vec<u64> {
+    let mut ids = Vec::with_capacity(len);
+    let mut pos = 0;
+    let mut prev = 0u64;
+    for _ in 0..len {
+        let (gap, n) = read_varint(&data[pos..]);
+        pos += n;
+        prev += gap;
+        ids.push(prev);
+    }
+    ids
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8]) -> (u64, usize) {
+    let mut value = 0u64;
+    let mut shift = 0;
+    for (n, &byte) in data.iter().enumerate() {
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return (value, n + 1);
+        }
+        shift += 7;
+    }
+    (value, data.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_roundtrip() {
+        let mut invlists = VarintInvertedLists::<32>::new(1);
+        let ids = [42u64, 1, 1_000_000, 7, 0];
+        let codes: Vec<[u8; 32]> = ids.iter().map(|&id| [id as u8; 32]).collect();
+        invlists.add_entries(0, &ids, &codes).unwrap();
+
+        let (got_ids, got_codes) = invlists.get_list(0).unwrap();
+        let mut expected: Vec<(u64, [u8; 32])> = ids.into_iter().zip(codes).collect();
+        expected.sort_unstable_by_key(|(id, _)| *id);
+        let expected_ids: Vec<u64> = expected.iter().map(|(id, _)| *id).collect();
+        let expected_codes: Vec<[u8; 32]> = expected.iter().map(|(_, code)| *code).collect();
+
+        assert_eq!(got_ids.into_owned(), expected_ids);
+        assert_eq!(got_codes.into_owned(), expected_codes);
+    }
+
+    #[test]
+    fn test_varint_truncate() {
+        let mut invlists = VarintInvertedLists::<32>::new(1);
+        let ids = [5u64, 2, 9, 1];
+        let codes: Vec<[u8; 32]> = ids.iter().map(|&id| [id as u8; 32]).collect();
+        invlists.add_entries(0, &ids, &codes).unwrap();
+
+        invlists.truncate(0, 2);
+        assert_eq!(invlists.list_len(0), 2);
+        let (got_ids, _) = invlists.get_list(0).unwrap();
+        assert_eq!(got_ids.into_owned(), vec![1, 2]);
+    }
+}
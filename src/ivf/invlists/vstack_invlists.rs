@@ -1,8 +1,10 @@
 use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
 
 use anyhow::Result;
 
-use super::InvertedLists;
+use super::{CompressionOptions, InvertedLists, OnDiskInvlists, save_invlists};
 
 /// 垂直堆叠多个倒排列表，通常用于合并工作
 pub struct VStackInvlists<const N: usize, T> {
@@ -55,3 +57,25 @@ where
         unimplemented!("VStackInvlists 不支持更新操作")
     }
 }
+
+/// 合并多个磁盘倒排列表文件：对每个 `list_no`，依次拼接各分片的 ids 和 codes，并重建
+/// 偏移/长度头，使合并后的文件仍然保持 `get_list` O(1) 的查找方式
+///
+/// 所有分片必须基于同一个量化器训练（即 nlist 一致），合并结果写入 `out_path`；写入采用
+/// 临时文件 + rename 的方式，即使 `out_path` 和某个分片路径相同，中途失败也不会损坏原文件
+pub fn merge_ondisk_invlists<const N: usize>(
+    shard_paths: &[impl AsRef<Path>],
+    out_path: impl AsRef<Path>,
+) -> Result<()> {
+    assert!(!shard_paths.is_empty(), "shard_paths is empty");
+
+    let shards =
+        shard_paths.iter().map(OnDiskInvlists::<N>::load).collect::<Result<Vec<_>>>()?;
+    let stacked = VStackInvlists::new(shards);
+
+    let out_path = out_path.as_ref();
+    let tmp_path = out_path.with_extension("tmp");
+    save_invlists::<N, _, _>(&stacked, &tmp_path, CompressionOptions::default())?;
+    fs::rename(&tmp_path, out_path)?;
+    Ok(())
+}
@@ -1,7 +1,9 @@
 pub mod invlists;
+mod pq;
 pub mod quantizer;
 mod utils;
 
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
@@ -12,6 +14,7 @@ use crossbeam_channel::bounded;
 pub use invlists::*;
 use itertools::izip;
 use log::debug;
+pub use pq::*;
 pub use quantizer::*;
 use rayon::ThreadPool;
 use rayon::prelude::*;
@@ -22,6 +25,10 @@ use crate::ivf::utils::TopKNeighbors;
 
 pub type IvfHnswDisk = IvfHnsw<32, HnswQuantizer<32>, OnDiskInvlists<32>>;
 pub type IvfHnswArray = IvfHnsw<32, HnswQuantizer<32>, ArrayInvertedLists<32>>;
+/// 使用 [`USearchQuantizer`] 做量化的倒排索引，与 [`IvfHnswDisk`] 二选一，通过
+/// `imsearch train --backend usearch` 训练出对应格式的 `quantizer.bin`
+pub type IvfUsearchDisk = IvfHnsw<32, USearchQuantizer<32>, OnDiskInvlists<32>>;
+pub type IvfUsearchArray = IvfHnsw<32, USearchQuantizer<32>, ArrayInvertedLists<32>>;
 
 #[derive(Debug)]
 pub struct SearchResult {
@@ -50,6 +57,17 @@ impl Default for Neighbor {
     }
 }
 
+/// 按图片聚合搜索结果时的投票选项
+#[derive(Debug, Clone, Copy)]
+pub struct VoteOptions {
+    /// 汉明距离阈值，只有不超过该阈值的邻居才计入投票
+    pub max_distance: u32,
+    /// 每个查询特征对同一张图片最多投一票，避免单张图片因命中多个邻居而虚高分数
+    pub one_vote_per_feature: bool,
+    /// Lowe 比率测试阈值：要求最近邻距离与次近邻距离的比值小于该阈值才计入投票，`None` 表示不启用
+    pub lowe_ratio: Option<f32>,
+}
+
 /// 基于 HNSW 量化器的倒排索引
 pub struct IvfHnsw<const N: usize, Q: Quantizer<N>, I: InvertedLists<N>> {
     pub quantizer: Q,
@@ -58,6 +76,48 @@ pub struct IvfHnsw<const N: usize, Q: Quantizer<N>, I: InvertedLists<N>> {
     pub pool: ThreadPool,
     // 倒排列表读取线程数
     pub threads: usize,
+    /// 全局特征 ID 到图片 ID 的边界映射，用于按图片聚合搜索结果
+    ///
+    /// 每个元素是 `(end_feature_id, image_id)`，按 `end_feature_id` 升序排列，
+    /// 每张图片的特征 ID 落在 `(上一个 end_feature_id, 当前 end_feature_id]` 区间内，
+    /// 和 SQLite 层 `total_vector_count` 的累加区间约定完全一致
+    image_boundaries: Vec<(u64, i64)>,
+}
+
+impl<const N: usize, Q: Quantizer<N>, I: InvertedLists<N>> IvfHnsw<N, Q, I> {
+    /// 设置特征 ID 到图片 ID 的边界映射，参见 [`Self::image_boundaries`]
+    pub fn set_image_boundaries(&mut self, boundaries: Vec<(u64, i64)>) {
+        self.image_boundaries = boundaries;
+    }
+
+    /// 根据全局特征 ID 查找其所属图片 ID
+    fn id_to_image(&self, id: u64) -> Option<i64> {
+        let idx = self.image_boundaries.partition_point(|&(end, _)| end < id);
+        self.image_boundaries.get(idx).map(|&(_, image_id)| image_id)
+    }
+
+    /// 还原指定倒排列表第 `offset` 条记录的原始向量及其全局特征 ID
+    ///
+    /// [`Self::add`] 存入的是向量和所在聚类中心异或后的残差码，这里做一次反向异或即可还原，
+    /// 不需要额外保留原始向量，可用于重新量化、用不同 nlist 重建索引，以及核验倒排列表内容
+    pub fn reconstruct(&self, list_no: usize, offset: usize) -> Result<(u64, [u8; N])> {
+        let centroids = self.quantizer.centroids()?;
+        let (ids, codes) = self.invlists.get_list(list_no)?;
+        Ok((ids[offset], xor(&codes[offset], &centroids[list_no])))
+    }
+
+    /// 还原索引中的所有向量，返回 `(id, vector)` 列表，顺序和倒排列表的存储顺序一致，
+    /// 不保证和写入顺序相同
+    pub fn reconstruct_all(&self) -> Result<Vec<(u64, [u8; N])>> {
+        let centroids = self.quantizer.centroids()?;
+        let mut out = Vec::new();
+        for list_no in 0..self.invlists.nlist() {
+            let (ids, codes) = self.invlists.get_list(list_no)?;
+            let centroid = &centroids[list_no];
+            out.extend(ids.iter().zip(codes.iter()).map(|(&id, code)| (id, xor(code, centroid))));
+        }
+        Ok(out)
+    }
 }
 
 impl<const N: usize, Q: Quantizer<N>, I: InvertedLists<N>> IvfHnsw<N, Q, I>
@@ -80,41 +140,86 @@ where
         }
         Ok(())
     }
-}
-
-impl<const N: usize> IvfHnsw<N, HnswQuantizer<N>, ArrayInvertedLists<N>> {
-    pub fn open_array<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-
-        let quantizer = HnswQuantizer::open(path.join("quantizer.bin"))?;
 
-        let nlist = quantizer.nlist();
-        let invlists = ArrayInvertedLists::<N>::new(nlist);
+    /// 在索引中搜索一组向量，并返回搜索结果
+    /// 注意：搜索结果的大小并不等于 len(data) * k，也不保证顺序，因为对于 imsearch 应用场景来说来说这是可以接受的
+    pub fn search(&self, data: &[[u8; N]], k: usize, nprobe: usize) -> Result<SearchResult> {
+        let (quantizer_time, io_time, compute_time, search_time, neighbors) =
+            self.search_grouped(data, k, nprobe)?;
 
-        let pool = rayon::ThreadPoolBuilder::new().num_threads(num_cpus::get()).build()?;
+        // NOTE: 此处没有进行任何排序，因为 imsearch 不关心顺序，只关心频率
+        let neighbors = neighbors.into_par_iter().flatten().collect::<Vec<_>>();
 
-        Ok(Self { quantizer, invlists, pool, threads: num_cpus::get() })
+        Ok(SearchResult { quantizer_time, io_time, compute_time, search_time, neighbors })
     }
-}
 
-impl<const N: usize> IvfHnsw<N, HnswQuantizer<N>, OnDiskInvlists<N>> {
-    pub fn open_disk<P: AsRef<Path>>(path: P, threads: usize) -> Result<Self> {
-        let path = path.as_ref();
+    /// 在索引中搜索一组向量，按图片聚合为投票结果，返回按得分降序排列的 `(image_id, score)` 列表
+    ///
+    /// 需要先通过 [`Self::set_image_boundaries`] 设置好特征 ID 到图片 ID 的映射，否则无法匹配
+    /// 到任何图片的邻居会被直接丢弃
+    pub fn search_images(
+        &self,
+        data: &[[u8; N]],
+        k: usize,
+        nprobe: usize,
+        options: VoteOptions,
+    ) -> Result<Vec<(i64, u32)>> {
+        let (.., neighbors) = self.search_grouped(data, k, nprobe)?;
 
-        let quantizer = HnswQuantizer::open(path.join("quantizer.bin"))?;
+        let mut votes: HashMap<i64, u32> = HashMap::new();
+        for mut neighbors in neighbors {
+            // 按距离升序排列，保证"最近邻"和"次近邻"的语义正确
+            neighbors.sort_unstable();
 
-        let nlist = quantizer.nlist();
-        let invlists = OnDiskInvlists::<N>::load(path.join("invlists.bin"))?;
-        assert_eq!(nlist, invlists.nlist(), "nlist mismatch");
+            if let Some(ratio) = options.lowe_ratio {
+                let Some(best) = neighbors.first() else { continue };
+                if best.distance > options.max_distance {
+                    continue;
+                }
+                let passes = match neighbors.get(1) {
+                    // 次近邻距离为 0 时无法判断区分度，保守丢弃
+                    Some(second) if second.distance > 0 => {
+                        (best.distance as f32 / second.distance as f32) < ratio
+                    }
+                    Some(_) => false,
+                    // 只有一个候选，没有次近邻可比较，直接采用
+                    None => true,
+                };
+                if !passes {
+                    continue;
+                }
+                if let Some(image_id) = self.id_to_image(best.id) {
+                    *votes.entry(image_id).or_insert(0) += 1;
+                }
+                continue;
+            }
 
-        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build()?;
+            let mut voted = HashSet::new();
+            for neighbor in &neighbors {
+                if neighbor.distance > options.max_distance {
+                    continue;
+                }
+                let Some(image_id) = self.id_to_image(neighbor.id) else { continue };
+                if options.one_vote_per_feature && !voted.insert(image_id) {
+                    continue;
+                }
+                *votes.entry(image_id).or_insert(0) += 1;
+            }
+        }
 
-        Ok(Self { quantizer, invlists, pool, threads })
+        let mut result = votes.into_iter().collect::<Vec<_>>();
+        result.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+        Ok(result)
     }
 
-    /// 在索引中搜索一组向量，并返回搜索结果
-    /// 注意：搜索结果的大小并不等于 len(data) * k，也不保证顺序，因为对于 imsearch 应用场景来说来说这是可以接受的
-    pub fn search(&self, data: &[[u8; N]], k: usize, nprobe: usize) -> Result<SearchResult> {
+    /// 搜索的核心实现，保留每组查询特征各自的近邻列表，供 [`Self::search`] 和
+    /// [`Self::search_images`] 按不同方式处理
+    fn search_grouped(
+        &self,
+        data: &[[u8; N]],
+        k: usize,
+        nprobe: usize,
+    ) -> Result<(Duration, Duration, Duration, Duration, Vec<Vec<Neighbor>>)> {
         let start = Instant::now();
 
         // 量化得到每个向量对应的倒排列表序号
@@ -159,24 +264,84 @@ impl<const N: usize> IvfHnsw<N, HnswQuantizer<N>, OnDiskInvlists<N>> {
             });
         });
 
-        // NOTE: 此处没有进行任何排序，因为 imsearch 不关心顺序，只关心频率
         let neighbors = Arc::into_inner(neighbors)
             .unwrap()
             .into_inner()
             .unwrap()
             .into_par_iter()
-            .map(|l| l.into_vec())
-            .flatten()
+            .map(|l| l.into_sorted_vec())
             .collect::<Vec<_>>();
 
         let search_time = start.elapsed() - quantizer_time;
-        Ok(SearchResult {
+        Ok((
             quantizer_time,
-            io_time: Duration::from_nanos(io_time.load(Ordering::Relaxed)),
-            compute_time: Duration::from_nanos(compute_time.load(Ordering::Relaxed)),
+            Duration::from_nanos(io_time.load(Ordering::Relaxed)),
+            Duration::from_nanos(compute_time.load(Ordering::Relaxed)),
             search_time,
             neighbors,
-        })
+        ))
+    }
+}
+
+impl<const N: usize> IvfHnsw<N, HnswQuantizer<N>, ArrayInvertedLists<N>> {
+    pub fn open_array<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let quantizer = HnswQuantizer::open(path.join("quantizer.bin"))?;
+
+        let nlist = quantizer.nlist();
+        let invlists = ArrayInvertedLists::<N>::new(nlist);
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(num_cpus::get()).build()?;
+
+        Ok(Self { quantizer, invlists, pool, threads: num_cpus::get(), image_boundaries: vec![] })
+    }
+}
+
+impl<const N: usize> IvfHnsw<N, USearchQuantizer<N>, ArrayInvertedLists<N>> {
+    pub fn open_array<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let quantizer = USearchQuantizer::open(path.join("quantizer.bin"))?;
+
+        let nlist = quantizer.nlist();
+        let invlists = ArrayInvertedLists::<N>::new(nlist);
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(num_cpus::get()).build()?;
+
+        Ok(Self { quantizer, invlists, pool, threads: num_cpus::get(), image_boundaries: vec![] })
+    }
+}
+
+impl<const N: usize> IvfHnsw<N, HnswQuantizer<N>, OnDiskInvlists<N>> {
+    pub fn open_disk<P: AsRef<Path>>(path: P, threads: usize) -> Result<Self> {
+        let path = path.as_ref();
+
+        let quantizer = HnswQuantizer::open(path.join("quantizer.bin"))?;
+
+        let nlist = quantizer.nlist();
+        let invlists = OnDiskInvlists::<N>::load(path.join("invlists.bin"))?;
+        assert_eq!(nlist, invlists.nlist(), "nlist mismatch");
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build()?;
+
+        Ok(Self { quantizer, invlists, pool, threads, image_boundaries: vec![] })
+    }
+}
+
+impl<const N: usize> IvfHnsw<N, USearchQuantizer<N>, OnDiskInvlists<N>> {
+    pub fn open_disk<P: AsRef<Path>>(path: P, threads: usize) -> Result<Self> {
+        let path = path.as_ref();
+
+        let quantizer = USearchQuantizer::open(path.join("quantizer.bin"))?;
+
+        let nlist = quantizer.nlist();
+        let invlists = OnDiskInvlists::<N>::load(path.join("invlists.bin"))?;
+        assert_eq!(nlist, invlists.nlist(), "nlist mismatch");
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build()?;
+
+        Ok(Self { quantizer, invlists, pool, threads, image_boundaries: vec![] })
     }
 }
 
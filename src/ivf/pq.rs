@@ -0,0 +1,210 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, ensure};
+
+use crate::kmodes::kmodes_binary;
+
+/// 量化时每个子块固定使用 256 个候选中心点，索引用单字节存储
+const NCENTROIDS: usize = 256;
+
+/// N 字节残差码的二进制乘积量化（PQ）编码器
+///
+/// 训练时把每个向量切成 `m` 个等长子块，对每个子块单独用 [`kmodes_binary`] 聚类出 256 个
+/// 子中心点；编码后每个向量只需要 `m` 字节（每个子块存一个中心点序号），而不是完整的 N 字节，
+/// 从而大幅压缩 `OnDiskInvlists` 中存储的倒排列表体积。
+///
+/// 搜索时通过 [`Self::distance_table`] 预先计算查询向量和所有子中心点的汉明距离，之后对
+/// 每条存储的编码只需要查表再求和（见 [`Self::asymmetric_distance`]），不需要再展开成完整的
+/// N 字节向量去算汉明距离，这就是非对称距离计算（ADC）
+pub struct PqCodec<const N: usize> {
+    /// 子块数量 m
+    m: usize,
+    /// 每个子块的字节数，满足 `sub_size * m == N`
+    sub_size: usize,
+    /// 展平存储的子中心点，排列方式为 `[sub_block][centroid][byte]`，长度为 `m * 256 * sub_size`
+    codebooks: Vec<u8>,
+}
+
+impl<const N: usize> PqCodec<N> {
+    /// 训练一个 PQ 编码器，`SUB` 为单个子块的字节数（`m = N / SUB`）
+    ///
+    /// 每个子块的聚类直接复用 [`kmodes_binary`]，和粗量化训练走的是同一套实现
+    pub fn train<const SUB: usize>(data: &[[u8; N]], max_iter: usize) -> Self {
+        assert_eq!(N % SUB, 0, "N 必须是 SUB 的整数倍");
+        let m = N / SUB;
+
+        let mut codebooks = vec![0u8; m * NCENTROIDS * SUB];
+        for j in 0..m {
+            let sub_data: Vec<[u8; SUB]> =
+                data.iter().map(|v| v[j * SUB..(j + 1) * SUB].try_into().unwrap()).collect();
+            let ks = kmodes_binary::<SUB>(&sub_data, NCENTROIDS, max_iter, None);
+
+            let base = j * NCENTROIDS * SUB;
+            for (c, centroid) in ks.centroids.iter().enumerate() {
+                codebooks[base + c * SUB..base + (c + 1) * SUB].copy_from_slice(centroid);
+            }
+            // 训练集过小时 kmodes_binary 可能返回不足 256 个中心点，剩余槽位保持全零中心点即可，
+            // 编码/查表时依然能正常工作：这些槽位作为普通的全零中心点参与比较，离子块最近时同样会被选中
+        }
+
+        Self { m, sub_size: SUB, codebooks }
+    }
+
+    /// 子块数量 m，也是编码后每个向量占用的字节数
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    fn centroid(&self, sub_block: usize, centroid: usize) -> &[u8] {
+        let off = (sub_block * NCENTROIDS + centroid) * self.sub_size;
+        &self.codebooks[off..off + self.sub_size]
+    }
+
+    /// 将一个完整的 N 字节残差编码为 `m` 字节的子中心点序号
+    pub fn encode(&self, v: &[u8; N]) -> Vec<u8> {
+        (0..self.m)
+            .map(|j| {
+                let sub = &v[j * self.sub_size..(j + 1) * self.sub_size];
+                let (best, _) = (0..NCENTROIDS)
+                    .map(|c| (c, hamming_bytes(sub, self.centroid(j, c))))
+                    .min_by_key(|&(_, d)| d)
+                    .unwrap();
+                best as u8
+            })
+            .collect()
+    }
+
+    /// 把编码还原成完整的 N 字节向量（有损，取各子块中心点拼接）
+    pub fn decode(&self, code: &[u8]) -> [u8; N] {
+        assert_eq!(code.len(), self.m, "编码长度必须等于 m");
+        let mut out = [0u8; N];
+        for (j, &c) in code.iter().enumerate() {
+            let dst = &mut out[j * self.sub_size..(j + 1) * self.sub_size];
+            dst.copy_from_slice(self.centroid(j, c as usize));
+        }
+        out
+    }
+
+    /// 预计算查询向量和所有子中心点的汉明距离表，形状为 `m × 256`
+    ///
+    /// 之后对任意一条存储的编码，调用 [`Self::asymmetric_distance`] 即可在 O(m) 时间内算出
+    /// 和查询向量等价的汉明距离（等价于把编码解码成完整向量再算汉明距离）
+    pub fn distance_table(&self, query: &[u8; N]) -> Vec<[u32; NCENTROIDS]> {
+        (0..self.m)
+            .map(|j| {
+                let sub = &query[j * self.sub_size..(j + 1) * self.sub_size];
+                let mut row = [0u32; NCENTROIDS];
+                for (c, d) in row.iter_mut().enumerate() {
+                    *d = hamming_bytes(sub, self.centroid(j, c));
+                }
+                row
+            })
+            .collect()
+    }
+
+    /// 根据 [`Self::distance_table`] 算出的表，对一条 `m` 字节的编码求非对称距离
+    pub fn asymmetric_distance(table: &[[u32; NCENTROIDS]], code: &[u8]) -> u32 {
+        code.iter().zip(table).map(|(&c, row)| row[c as usize]).sum()
+    }
+
+    /// 保存编码器：依次写入 m、sub_size 和展平的子中心点数据
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut buf = Vec::with_capacity(16 + self.codebooks.len());
+        buf.extend_from_slice(&(self.m as u64).to_le_bytes());
+        buf.extend_from_slice(&(self.sub_size as u64).to_le_bytes());
+        buf.extend_from_slice(&self.codebooks);
+        fs::write(path, buf)?;
+        Ok(())
+    }
+
+    /// 加载编码器
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let data = fs::read(path)?;
+        ensure!(data.len() >= 16, "PQ 编码器文件已损坏");
+        let m = u64::from_le_bytes(data[0..8].try_into()?) as usize;
+        let sub_size = u64::from_le_bytes(data[8..16].try_into()?) as usize;
+        ensure!(sub_size * m == N, "N 与编码器训练时的参数不匹配");
+
+        let codebooks = data[16..].to_vec();
+        ensure!(codebooks.len() == m * NCENTROIDS * sub_size, "PQ 编码器文件已损坏");
+
+        Ok(Self { m, sub_size, codebooks })
+    }
+}
+
+/// 对任意长度相等的两个字节切片计算汉明距离
+///
+/// PQ 子块通常只有几个字节，达不到 [`crate::hamming::hamming`] 走 SIMD 快速路径的规模，
+/// 这里用朴素逐字节的实现即可
+#[inline]
+fn hamming_bytes(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::hamming::hamming;
+
+    fn random_data(n: usize, seed: u64) -> Vec<[u8; 16]> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| {
+                let mut v = [0u8; 16];
+                rng.fill(&mut v[..]);
+                v
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_train_and_encode_roundtrip() {
+        let data = random_data(4096, 1);
+        let codec = PqCodec::<16>::train::<4>(&data, 20);
+        assert_eq!(codec.m(), 4);
+
+        for v in data.iter().take(10) {
+            let code = codec.encode(v);
+            assert_eq!(code.len(), 4);
+            // 解码后的重建向量应当比随机向量更接近原始向量
+            let decoded = codec.decode(&code);
+            let other = data[data.len() - 1];
+            assert!(hamming::<16>(v, &decoded) <= hamming::<16>(v, &other));
+        }
+    }
+
+    #[test]
+    fn test_asymmetric_distance_matches_decoded_hamming() {
+        let data = random_data(4096, 2);
+        let codec = PqCodec::<16>::train::<4>(&data, 20);
+
+        let query = data[0];
+        let table = codec.distance_table(&query);
+
+        for v in data.iter().take(20) {
+            let code = codec.encode(v);
+            let adc = PqCodec::<16>::asymmetric_distance(&table, &code);
+            let decoded = codec.decode(&code);
+            assert_eq!(adc, hamming::<16>(&query, &decoded));
+        }
+    }
+
+    #[test]
+    fn test_save_and_open_roundtrip() {
+        let data = random_data(4096, 3);
+        let codec = PqCodec::<16>::train::<4>(&data, 20);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pq.bin");
+        codec.save(&path).unwrap();
+        let loaded = PqCodec::<16>::open(&path).unwrap();
+
+        assert_eq!(loaded.m(), codec.m());
+        for v in data.iter().take(10) {
+            assert_eq!(codec.encode(v), loaded.encode(v));
+        }
+    }
+}
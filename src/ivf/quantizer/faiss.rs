@@ -5,7 +5,7 @@ use std::ptr;
 use anyhow::Result;
 use faiss_sys::*;
 
-use crate::ivf::Quantizer;
+use crate::ivf::{HnswParams, Quantizer};
 
 #[derive(Debug)]
 pub struct FaissHNSWQuantizer<const N: usize> {
@@ -30,16 +30,18 @@ impl<const N: usize> Quantizer<N> for FaissHNSWQuantizer<N> {
         Ok(Self { index })
     }
 
-    fn init(x: &[[u8; N]]) -> Result<Self>
+    fn init_with_params(x: &[[u8; N]], params: HnswParams) -> Result<Self>
     where
         Self: Sized,
     {
         let mut index = ptr::null_mut();
         unsafe {
-            faiss_try(faiss_IndexBinaryHNSW_new(&mut index, (N * 8) as i32, 32))?;
-            // faiss 默认值为 40, 16
-            faiss_try(faiss_IndexBinaryHNSW_set_efConstruction(index, 128))?;
-            faiss_try(faiss_IndexBinaryHNSW_set_efSearch(index, 16))?;
+            faiss_try(faiss_IndexBinaryHNSW_new(&mut index, (N * 8) as i32, params.m as i32))?;
+            faiss_try(faiss_IndexBinaryHNSW_set_efConstruction(
+                index,
+                params.ef_construction as i32,
+            ))?;
+            faiss_try(faiss_IndexBinaryHNSW_set_efSearch(index, params.ef_search as i32))?;
         }
         let index = index.cast();
         let xf = x.as_flattened();
@@ -49,10 +51,19 @@ impl<const N: usize> Quantizer<N> for FaissHNSWQuantizer<N> {
         Ok(Self { index })
     }
 
+    /// 运行时调整 efSearch，无需重建索引
+    fn set_ef_search(&self, ef: usize) -> Result<()> {
+        unsafe { faiss_try(faiss_IndexBinaryHNSW_set_efSearch(self.index.cast(), ef as i32)) }
+    }
+
     fn search(&self, x: &[[u8; N]], k: usize) -> Result<Vec<i64>> {
+        Ok(self.search_with_distances(x, k)?.into_iter().map(|(label, _)| label).collect())
+    }
+
+    fn search_with_distances(&self, x: &[[u8; N]], k: usize) -> Result<Vec<(i64, u32)>> {
         let xf = x.as_flattened();
-        let mut distances = vec![0; x.len() * k];
-        let mut labels = vec![0; x.len() * k];
+        let mut distances = vec![0i32; x.len() * k];
+        let mut labels = vec![0i64; x.len() * k];
         unsafe {
             faiss_try(faiss_IndexBinary_search(
                 self.index,
@@ -63,7 +74,7 @@ impl<const N: usize> Quantizer<N> for FaissHNSWQuantizer<N> {
                 labels.as_mut_ptr(),
             ))?;
         }
-        Ok(labels)
+        Ok(labels.into_iter().zip(distances).map(|(label, dis)| (label, dis as u32)).collect())
     }
 
     fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
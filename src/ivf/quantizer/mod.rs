@@ -1,9 +1,29 @@
 mod faiss;
+mod usearch;
 
 use std::path::Path;
 
 use anyhow::Result;
 pub use faiss::FaissHNSWQuantizer as HnswQuantizer;
+pub use usearch::{USearchQuantizer, USearchQuantizerOptions};
+
+/// HNSW 图的构建/搜索参数，取值含义与 faiss/usearch 等主流实现一致
+#[derive(Debug, Clone, Copy)]
+pub struct HnswParams {
+    /// 每个节点的最大出边数（faiss 的 M，usearch 的 connectivity）
+    pub m: usize,
+    /// 构建时的候选集大小，越大图质量越高，构建耗时越长
+    pub ef_construction: usize,
+    /// 搜索时的候选集大小，越大召回率越高，查询延迟越高；可在构建后通过
+    /// [`Quantizer::set_ef_search`] 动态调整，无需重建索引
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self { m: 32, ef_construction: 128, ef_search: 16 }
+    }
+}
 
 /// 适用于 N 位二进制向量的量化器
 pub trait Quantizer<const N: usize> {
@@ -12,15 +32,32 @@ pub trait Quantizer<const N: usize> {
     where
         Self: Sized;
 
-    /// 使用指定向量初始化量化器
+    /// 使用默认 HNSW 参数初始化量化器，等价于 `Self::init_with_params(x, HnswParams::default())`
     fn init(x: &[[u8; N]]) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::init_with_params(x, HnswParams::default())
+    }
+
+    /// 使用指定的 HNSW 构建参数初始化量化器
+    fn init_with_params(x: &[[u8; N]], params: HnswParams) -> Result<Self>
     where
         Self: Sized;
 
+    /// 运行时调整搜索阶段的 ef（候选集大小），无需重建索引即可在召回率和延迟之间权衡
+    fn set_ef_search(&self, ef: usize) -> Result<()>;
+
     /// 在数据集中为多组向量搜索最接近的的 k 个向量，返回最匹配的 k 个 ID 列表
     /// 返回结果大小固定为 x.len() * k，没有找到位置的填充 -1
     fn search(&self, x: &[[u8; N]], k: usize) -> Result<Vec<i64>>;
 
+    /// 和 [`Self::search`] 一样搜索最接近的 k 个向量，但连同每个匹配与查询向量的汉明距离
+    /// 一起返回，供调用方做重排序或按距离阈值过滤
+    ///
+    /// 返回结果大小固定为 x.len() * k，没有找到位置的填充 `(-1, u32::MAX)`
+    fn search_with_distances(&self, x: &[[u8; N]], k: usize) -> Result<Vec<(i64, u32)>>;
+
     /// 保存量化器
     fn save<P: AsRef<Path>>(&self, path: P) -> Result<()>;
 
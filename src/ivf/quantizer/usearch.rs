@@ -1,67 +1,239 @@
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use rayon::prelude::*;
 use usearch::{Index, IndexOptions, MetricKind, ScalarKind, b1x8};
 
-use super::Quantizer;
+use super::{HnswParams, Quantizer};
+
+/// [`USearchQuantizer`] 的 HNSW 构建参数，默认值与 usearch 自身的默认参数一致
+/// （faiss 对应的默认值为 32 - 40 - 16）
+#[derive(Debug, Clone, Copy)]
+pub struct USearchQuantizerOptions {
+    pub connectivity: usize,
+    pub expansion_add: usize,
+    pub expansion_search: usize,
+    /// 压缩后保留的比特数，为 `None` 时不压缩，直接使用完整的 N*8 位二进制向量
+    pub compressed_bits: Option<usize>,
+}
+
+impl Default for USearchQuantizerOptions {
+    fn default() -> Self {
+        Self { connectivity: 32, expansion_add: 40, expansion_search: 16, compressed_bits: None }
+    }
+}
 
 pub struct USearchQuantizer<const N: usize> {
     /// 索引
     index: Index,
+    /// 压缩模式下选中的比特位（按训练集方差从高到低排序），为空表示未启用压缩
+    bit_mask: Vec<u16>,
+    /// 完整的（未压缩的）聚类中心，key 即为向量在此处的下标，用于 [`Quantizer::centroids`]
+    ///
+    /// usearch 的索引内部只保存投影/压缩后的向量，压缩模式下无法从索引反推出原始向量，
+    /// 所以这里单独持有一份，和 IVF 残差编码（[`crate::ivf::IvfHnsw::add`]）依赖的
+    /// 原始 N 字节聚类中心保持一致，与 [`super::FaissHNSWQuantizer`] 直接从 faiss 存储区
+    /// 读取的效果等价
+    centroids: Vec<[u8; N]>,
 }
 
 impl<const N: usize> USearchQuantizer<N> {
-    pub fn new() -> Result<Self> {
-        let options = IndexOptions {
-            // 向量的二进制位数
-            dimensions: N * 8,
+    fn new(dimensions: usize, options: USearchQuantizerOptions) -> Result<Self> {
+        let index_options = IndexOptions {
+            dimensions,
             metric: MetricKind::Hamming,
             quantization: ScalarKind::B1,
-            // 此处为 usearch 默认参数
-            // faiss 默认为 32 - 40 - 16
-            connectivity: 32,
-            expansion_add: 40,
-            expansion_search: 16,
+            connectivity: options.connectivity,
+            expansion_add: options.expansion_add,
+            expansion_search: options.expansion_search,
             ..Default::default()
         };
-        let index = Index::new(&options)?;
-        Ok(Self { index })
+        let index = Index::new(&index_options)?;
+        Ok(Self { index, bit_mask: vec![], centroids: vec![] })
     }
-}
 
-impl<const N: usize> Quantizer<N> for USearchQuantizer<N> {
-    fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let s = Self::new()?;
-        s.index.load(path.as_ref().to_str().unwrap())?;
-        Ok(s)
+    /// 压缩掩码的附属文件路径，与索引文件放在一起
+    fn mask_path(path: impl AsRef<Path>) -> PathBuf {
+        let mut path = path.as_ref().to_path_buf();
+        let ext = path.extension().map(|e| format!("{}.mask", e.to_string_lossy()));
+        match ext {
+            Some(ext) => path.set_extension(ext),
+            None => path.set_extension("mask"),
+        };
+        path
     }
 
-    /// 为量化器填充训练好的聚类中心
-    fn init(x: &[[u8; N]]) -> Result<Self> {
-        let s = Self::new()?;
+    /// 原始聚类中心的附属文件路径，与索引文件放在一起
+    fn centroids_path(path: impl AsRef<Path>) -> PathBuf {
+        let mut path = path.as_ref().to_path_buf();
+        let ext = path.extension().map(|e| format!("{}.centroids", e.to_string_lossy()));
+        match ext {
+            Some(ext) => path.set_extension(ext),
+            None => path.set_extension("centroids"),
+        };
+        path
+    }
+
+    /// 将原始向量按 `bit_mask` 投影为压缩后的向量；`bit_mask` 为空时原样返回
+    fn project(&self, x: &[u8; N]) -> Vec<u8> {
+        if self.bit_mask.is_empty() {
+            return x.to_vec();
+        }
+        let mut out = vec![0u8; self.bit_mask.len().div_ceil(8)];
+        for (i, &bit) in self.bit_mask.iter().enumerate() {
+            let byte = x[(bit / 8) as usize];
+            if byte & (1 << (bit % 8)) != 0 {
+                out[i / 8] |= 1 << (i % 8);
+            }
+        }
+        out
+    }
+
+    /// 基于训练集中每个比特位的方差（`p * (1 - p)`，`p` 为该位取 1 的比例）选出方差最大
+    /// 的 `bits` 个比特位：方差越大说明这个比特在训练集上的区分度越高，越值得保留
+    fn select_bits(x: &[[u8; N]], bits: usize) -> Vec<u16> {
+        let total_bits = N * 8;
+        let mut ones = vec![0u32; total_bits];
+        for v in x {
+            for (byte_idx, &byte) in v.iter().enumerate() {
+                for b in 0..8 {
+                    if byte & (1 << b) != 0 {
+                        ones[byte_idx * 8 + b] += 1;
+                    }
+                }
+            }
+        }
+
+        let n = x.len().max(1) as f32;
+        let mut scored: Vec<(f32, u16)> = ones
+            .into_iter()
+            .enumerate()
+            .map(|(bit, count)| {
+                let p = count as f32 / n;
+                (p * (1. - p), bit as u16)
+            })
+            .collect();
+        scored.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(bits);
+        scored.into_iter().map(|(_, bit)| bit).collect()
+    }
+
+    /// 使用指定参数初始化量化器，可选按训练集学习一个比特选择掩码进行压缩
+    pub fn init_with_options(x: &[[u8; N]], options: USearchQuantizerOptions) -> Result<Self> {
+        let bit_mask = match options.compressed_bits {
+            Some(bits) => Self::select_bits(x, bits),
+            None => vec![],
+        };
+        let dimensions = if bit_mask.is_empty() { N * 8 } else { bit_mask.len() };
+
+        let mut s = Self::new(dimensions, options)?;
+        s.bit_mask = bit_mask;
+        s.centroids = x.to_vec();
         s.index.reserve(x.len())?;
         x.par_iter().enumerate().for_each(|(i, chunk)| {
-            let v = b1x8::from_u8s(chunk);
+            let v = b1x8::from_u8s(&s.project(chunk));
             s.index.add(i as u64, v).unwrap();
         });
         Ok(s)
     }
 
-    /// 搜索一组向量，返回最接近的 k 个聚类中心
-    fn search(&self, x: &[[u8; N]], k: usize) -> Result<Vec<Vec<usize>>> {
+    /// 从指定路径加载量化器，附带加载原始聚类中心（文件必须存在）和压缩掩码（如果存在）
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: USearchQuantizerOptions,
+    ) -> Result<Self> {
+        let mask_path = Self::mask_path(&path);
+        let bit_mask = if mask_path.exists() {
+            bytemuck::cast_slice(&fs::read(&mask_path)?).to_vec()
+        } else {
+            vec![]
+        };
+        let dimensions = if bit_mask.is_empty() { N * 8 } else { bit_mask.len() };
+
+        let mut s = Self::new(dimensions, options)?;
+        s.index.load(path.as_ref().to_str().unwrap())?;
+        s.bit_mask = bit_mask;
+        let centroids_bytes = fs::read(Self::centroids_path(&path))?;
+        let (centroids, _) = centroids_bytes.as_chunks::<N>();
+        s.centroids = centroids.to_vec();
+        Ok(s)
+    }
+
+    /// 搜索一组向量，返回最接近的 k 个聚类中心；`expansion_search` 用于临时覆盖索引的搜索
+    /// 扩张参数，让调用方无需重建索引就能按查询在延迟和召回率之间权衡
+    pub fn search_with_ef(
+        &self,
+        x: &[[u8; N]],
+        k: usize,
+        expansion_search: usize,
+    ) -> Result<Vec<Vec<usize>>> {
+        self.index.change_expansion_search(expansion_search)?;
         x.par_iter()
             .map(|chunk| {
-                let q = b1x8::from_u8s(chunk);
+                let q = b1x8::from_u8s(&self.project(chunk));
                 let m = self.index.search(q, k)?;
                 Ok(m.keys.into_iter().map(|key| key as usize).collect())
             })
             .collect::<Result<Vec<_>>>()
     }
+}
+
+impl<const N: usize> Quantizer<N> for USearchQuantizer<N> {
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_options(path, USearchQuantizerOptions::default())
+    }
+
+    /// 为量化器填充训练好的聚类中心
+    fn init_with_params(x: &[[u8; N]], params: HnswParams) -> Result<Self> {
+        let options = USearchQuantizerOptions {
+            connectivity: params.m,
+            expansion_add: params.ef_construction,
+            expansion_search: params.ef_search,
+            ..Default::default()
+        };
+        Self::init_with_options(x, options)
+    }
 
-    /// 保存量化器
+    /// 运行时调整 expansion_search，无需重建索引
+    fn set_ef_search(&self, ef: usize) -> Result<()> {
+        self.index.change_expansion_search(ef)?;
+        Ok(())
+    }
+
+    /// 搜索一组向量，返回最接近的 k 个聚类中心
+    fn search(&self, x: &[[u8; N]], k: usize) -> Result<Vec<i64>> {
+        Ok(self.search_with_distances(x, k)?.into_iter().map(|(label, _)| label).collect())
+    }
+
+    /// 和 [`Self::search`] 一样搜索最接近的 k 个聚类中心，但连同 usearch 自身算出的汉明
+    /// 距离（`m.distances`）一起返回，省得调用方事后再拿 code 重新算一遍
+    fn search_with_distances(&self, x: &[[u8; N]], k: usize) -> Result<Vec<(i64, u32)>> {
+        let results: Vec<Vec<(i64, u32)>> = x
+            .par_iter()
+            .map(|chunk| {
+                let q = b1x8::from_u8s(&self.project(chunk));
+                let m = self.index.search(q, k)?;
+                let mut matches: Vec<(i64, u32)> = m
+                    .keys
+                    .into_iter()
+                    .zip(m.distances)
+                    .map(|(key, dis)| (key as i64, dis.round() as u32))
+                    .collect();
+                matches.resize(k, (-1, u32::MAX));
+                Ok(matches)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// 保存量化器，连带写入原始聚类中心，压缩模式下还会写入比特选择掩码
     fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         self.index.save(path.as_ref().to_str().unwrap())?;
+        fs::write(Self::centroids_path(&path), self.centroids.as_flattened())?;
+        if !self.bit_mask.is_empty() {
+            fs::write(Self::mask_path(&path), bytemuck::cast_slice::<u16, u8>(&self.bit_mask))?;
+        }
         Ok(())
     }
 
@@ -69,4 +241,9 @@ impl<const N: usize> Quantizer<N> for USearchQuantizer<N> {
     fn nlist(&self) -> usize {
         self.index.size()
     }
+
+    /// 获取原始（未压缩）聚类中心
+    fn centroids(&self) -> Result<&[[u8; N]]> {
+        Ok(&self.centroids)
+    }
 }
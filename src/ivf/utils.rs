@@ -22,4 +22,9 @@ impl TopKNeighbors {
     pub fn into_vec(self) -> Vec<Neighbor> {
         self.heap.into_vec()
     }
+
+    /// 按距离升序返回结果，用于需要区分最近邻/次近邻的场景（如 Lowe 比率测试）
+    pub fn into_sorted_vec(self) -> Vec<Neighbor> {
+        self.heap.into_sorted_vec()
+    }
 }
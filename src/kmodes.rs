@@ -7,15 +7,28 @@ use rayon::prelude::*;
 use crate::hamming::{batch_knn_hamming, hamming};
 use crate::utils::pb_style;
 
-pub fn kmodes_2level<const N: usize>(x: &[[u8; N]], nc: usize, max_iter: usize) -> KModeState<N> {
+/// `init` 为 `Some` 时，传入的中心点数量必须等于 `nc`，用于从已有索引（例如一个训练好的
+/// [`crate::ivf::HnswQuantizer`]）的中心点出发热启动训练，而不是随机采样；函数会先把这些
+/// 中心点按照 1 级聚类的划分结果分桶，再用每个桶内的中心点热启动对应的 2 级聚类，分桶数量
+/// 对不上（通常因为 nc 发生了变化）的聚类仍然退化为随机初始化
+pub fn kmodes_2level<const N: usize>(
+    x: &[[u8; N]],
+    nc: usize,
+    max_iter: usize,
+    init: Option<&[[u8; N]]>,
+) -> KModeState<N> {
     let n = x.len();
     assert!(n >= 30 * nc, "向量数量必须大于 30 * {nc}");
+    if let Some(init) = init {
+        assert_eq!(init.len(), nc, "初始中心点数量必须等于 nc");
+    }
     let nc1 = nc.isqrt();
 
     // 没有必要用全部向量进行一级聚类，这里取 nc1 的 1024 倍来训练，平衡精度和耗时
+    // 1 级聚类只是为了划分训练集，重新随机训练的代价很低，因此不热启动
     let n1 = (nc1 * 1024).min(n);
     info!("对 {n1} 组向量进行 1 级聚类，中心点数量 = {nc1}");
-    let ks = kmodes_binary::<N>(&x[..n1], nc1, max_iter);
+    let ks = kmodes_binary::<N>(&x[..n1], nc1, max_iter, None);
     info!("1 级聚类完成，不平衡度：{:.2}", imbalance_factor(&ks.centroid_frequency));
 
     info!("根据 1 级聚类结果划分训练集");
@@ -27,6 +40,16 @@ pub fn kmodes_2level<const N: usize>(x: &[[u8; N]], nc: usize, max_iter: usize)
         xc[*r].push(x[i]);
     });
 
+    // 按照同样的 1 级划分结果，把传入的热启动中心点分桶，后面用来热启动对应的 2 级聚类
+    let init_xc: Vec<Vec<[u8; N]>> = if let Some(init) = init {
+        let (ir, _) = update_assignments(init, &ks.centroids);
+        let mut buckets = vec![vec![]; nc1];
+        ir.iter().enumerate().for_each(|(i, r)| buckets[*r].push(init[i]));
+        buckets
+    } else {
+        vec![vec![]; nc1]
+    };
+
     // 计算累加和，用于计算二级聚类中心点数量
     let bc_sum = xc
         .iter()
@@ -53,7 +76,10 @@ pub fn kmodes_2level<const N: usize>(x: &[[u8; N]], nc: usize, max_iter: usize)
     for i in (0..nc1).progress_with(pb.clone()) {
         let x = &xc[i];
         if nc2[i] > 0 {
-            let ks = kmodes_binary::<N>(x, nc2[i], max_iter);
+            // 分桶后的热启动中心点数量对不上时（通常因为 nc 相比训练索引时发生了变化），
+            // 退化为随机初始化，而不是报错
+            let seed = (init_xc[i].len() == nc2[i]).then_some(init_xc[i].as_slice());
+            let ks = kmodes_binary::<N>(x, nc2[i], max_iter, seed);
             let factor = imbalance_factor(&ks.centroid_frequency);
             pb.set_message(format!(
                 "对 {} 组向量进行二级聚类，中心点数量 = {}, 不平衡度 = {factor:.2}",
@@ -85,18 +111,30 @@ pub struct KModeState<const N: usize> {
 }
 
 /// K-modes 聚类算法，用于二进制向量
+///
+/// `init` 为 `Some` 时直接用传入的中心点热启动（数量必须等于 `k`），例如复用一个已训练好的
+/// 量化器的中心点做增量重训；为 `None` 时退化为随机采样初始化
 /// 返回聚类后的二进制向量，和每个聚类中心的向量数量
-pub fn kmodes_binary<const N: usize>(data: &[[u8; N]], k: usize, max_iter: usize) -> KModeState<N> {
+pub fn kmodes_binary<const N: usize>(
+    data: &[[u8; N]],
+    k: usize,
+    max_iter: usize,
+    init: Option<&[[u8; N]]>,
+) -> KModeState<N> {
     if data.is_empty() || k == 0 {
         return KModeState::default();
     }
 
-    let mut rng = rng();
-
-    // 随机初始化聚类中心
-    let mut centroids: Vec<[u8; N]> = data.choose_multiple(&mut rng, k).cloned().collect();
+    let mut centroids: Vec<[u8; N]> = match init {
+        Some(init) => {
+            assert_eq!(init.len(), k, "初始中心点数量必须等于 k");
+            init.to_vec()
+        }
+        // 随机初始化聚类中心
+        None => data.choose_multiple(&mut rng(), k).cloned().collect(),
+    };
 
-    let mut assignments;
+    let mut assignments = Vec::new();
     let mut distance = u32::MAX;
     let mut centroid_frequency = vec![0; k];
 
@@ -120,32 +158,115 @@ pub fn kmodes_binary<const N: usize>(data: &[[u8; N]], k: usize, max_iter: usize
         centroid_frequency = new_centroid_frequency;
     }
 
+    // ELBG 后处理：把利用率过低的中心点迁移到利用率过高的聚类，降低不平衡度
+    elbg_redistribute(data, &mut centroids, &mut assignments, &mut centroid_frequency, &mut distance);
+
     KModeState { distsum: distance, centroids, centroid_frequency }
 }
 
+/// ELBG（Enhanced LBG）中心点再分配
+///
+/// 对每个聚类计算失真度 `D_i`（聚类内所有点到中心的汉明距离之和）和平均失真度 `D_mean`，
+/// 利用率 `U_i = D_i / D_mean`：远小于 1 的聚类是"捐献者"（贡献度低，可以放弃其中心点），
+/// 远大于 1 的聚类是"接收者"（过载，适合拆分出第二个中心点）。
+/// 每次迁移删除一个捐献者的中心点，在接收者内部找出离其中心汉明距离最远的点作为新中心点，
+/// 重新计算全局分配，只有总失真度确实下降时才接受这次迁移，否则停止。
+fn elbg_redistribute<const N: usize>(
+    data: &[[u8; N]],
+    centroids: &mut Vec<[u8; N]>,
+    assignments: &mut Vec<usize>,
+    centroid_frequency: &mut Vec<usize>,
+    distance: &mut u32,
+) {
+    const LOW_UTILITY: f64 = 0.3;
+    const HIGH_UTILITY: f64 = 2.0;
+    const SHIFT_BUDGET: usize = 32;
+
+    let k = centroids.len();
+    if k < 2 || data.is_empty() {
+        return;
+    }
+
+    for _ in 0..SHIFT_BUDGET {
+        let mut distortion = vec![0u64; k];
+        for (point, &a) in data.iter().zip(assignments.iter()) {
+            distortion[a] += hamming::<N>(point, &centroids[a]) as u64;
+        }
+        let mean = distortion.iter().sum::<u64>() as f64 / k as f64;
+        if mean == 0.0 {
+            break;
+        }
+
+        let (donor, donor_u) = distortion
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| (i, d as f64 / mean))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        let (receiver, receiver_u) = distortion
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| (i, d as f64 / mean))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+
+        // 没有明显的低/高利用率聚类对可迁移，停止
+        if donor == receiver || donor_u >= LOW_UTILITY || receiver_u <= HIGH_UTILITY {
+            break;
+        }
+
+        let receiver_members: Vec<[u8; N]> = data
+            .iter()
+            .zip(assignments.iter())
+            .filter_map(|(p, &a)| (a == receiver).then_some(*p))
+            .collect();
+        let Some(farthest) =
+            receiver_members.iter().max_by_key(|p| hamming::<N>(*p, &centroids[receiver]))
+        else {
+            break;
+        };
+
+        let mut trial_centroids = centroids.clone();
+        trial_centroids[donor] = *farthest;
+
+        let (new_assignments, new_distance) = update_assignments(data, &trial_centroids);
+
+        // 只有迁移后总失真度确实下降才接受，否则这次尝试以及后续尝试大概率也不会更好
+        if new_distance >= *distance {
+            break;
+        }
+
+        *centroids = trial_centroids;
+        *assignments = new_assignments;
+        *distance = new_distance;
+
+        let (new_centroids, new_centroid_frequency): (Vec<[u8; N]>, Vec<usize>) = (0..k)
+            .into_par_iter()
+            .map(|cluster_id| update_centroid(data, assignments, cluster_id))
+            .unzip();
+        *centroids = new_centroids;
+        *centroid_frequency = new_centroid_frequency;
+    }
+}
+
 /// 将每个点分配给最近的聚类中心，并返回聚类中心的序号和总距离
+///
+/// 本质上是对每个点在聚类中心集合里做一次 k=1 的汉明距离 kNN，因此直接复用
+/// [`batch_knn_hamming`]：数据量大时这是整个训练过程里最热的循环，复用同一个接口意味着
+/// 它可以和 `IvfHnsw::search` 的扫描共享同一套 GPU 加速后端
 fn update_assignments<const N: usize>(
     data: &[[u8; N]],
     centroids: &[[u8; N]],
 ) -> (Vec<usize>, u32) {
-    let (assignments, distances): (Vec<_>, Vec<_>) = data
-        .par_iter()
-        .map(|point| {
-            let mut min_distance = u32::MAX;
-            let mut best_cluster = 0;
-
-            for (j, centroid) in centroids.iter().enumerate() {
-                let distance = hamming::<N>(point, centroid);
-                if distance < min_distance {
-                    min_distance = distance;
-                    best_cluster = j;
-                }
-            }
-
-            (best_cluster, min_distance)
+    let mut distance = 0u32;
+    let assignments = batch_knn_hamming::<N>(data, centroids, 1)
+        .into_iter()
+        .map(|mut nearest| {
+            let (best_cluster, min_distance) = nearest.remove(0);
+            distance += min_distance;
+            best_cluster
         })
-        .unzip();
-    let distance = distances.iter().sum();
+        .collect();
     (assignments, distance)
 }
 
@@ -257,7 +378,7 @@ mod tests {
             [0b00000000, 0b00001111, 0b11110000, 0b11111111], // 类型2
         ];
 
-        let ks = kmodes_binary(&data, 2, 100);
+        let ks = kmodes_binary(&data, 2, 100, None);
 
         assert_eq!(ks.centroids.len(), 2);
 
@@ -270,7 +391,7 @@ mod tests {
     #[test]
     fn test_kmodes_complete() {
         let (data, cluster_centers) = generate_clustered_data(30720, 1024);
-        let ks = kmodes_binary::<32>(&data, 1024, 100);
+        let ks = kmodes_binary::<32>(&data, 1024, 100, None);
         assert_eq!(ks.centroids.len(), 1024);
 
         // for (i, centroid) in centroids.iter().enumerate() {
@@ -1,15 +1,29 @@
+pub mod ahash;
+pub mod bktree;
+pub mod bloom;
 pub mod cli;
 pub mod config;
 pub mod db;
+pub mod dedup;
+pub mod dhash;
+pub mod features;
+#[cfg(feature = "gpu")]
+pub mod gpu;
 pub mod hamming;
 pub mod hnsw;
 pub mod imdb;
+pub mod invlists;
 pub mod ivf;
 pub mod kmodes;
+pub mod lru;
 pub mod metrics;
+pub mod minhash;
 pub mod orb;
+pub mod phash;
+pub mod ranking;
+pub mod rerank;
 pub mod server;
 pub mod utils;
 
 pub use config::Opts;
-pub use imdb::{IMDB, IMDBBuilder};
+pub use imdb::{IMDB, IMDBBuilder, SearchTiming};
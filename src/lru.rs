@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// 固定容量的 LRU 缓存
+///
+/// `map` 保存 key 到节点下标的映射，节点本身存放在 `nodes` 中，并通过 `prev`/`next`
+/// 下标组成双向链表维护访问顺序（链表头为最近访问，链表尾为最久未访问）；`put` 超过容量
+/// 时淘汰链表尾部的节点，被淘汰的下标会进入 `free` 以便复用，避免 `nodes` 无限增长
+pub struct LruCache<K, V> {
+    capacity: usize,
+    map: HashMap<K, usize>,
+    nodes: Vec<Node<K, V>>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    free: Vec<usize>,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            map: HashMap::new(),
+            nodes: Vec::new(),
+            head: None,
+            tail: None,
+            free: Vec::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let idx = *self.map.get(key)?;
+        self.move_to_front(idx);
+        Some(&self.nodes[idx].value)
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        if let Some(&idx) = self.map.get(&key) {
+            self.nodes[idx].value = value;
+            self.move_to_front(idx);
+            return;
+        }
+
+        let node = Node { key: key.clone(), value, prev: None, next: self.head };
+        let idx = if let Some(idx) = self.free.pop() {
+            self.nodes[idx] = node;
+            idx
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        };
+
+        if let Some(head) = self.head {
+            self.nodes[head].prev = Some(idx);
+        }
+        self.head = Some(idx);
+        if self.tail.is_none() {
+            self.tail = Some(idx);
+        }
+        self.map.insert(key, idx);
+
+        if self.map.len() > self.capacity {
+            self.evict_tail();
+        }
+    }
+
+    fn move_to_front(&mut self, idx: usize) {
+        if self.head == Some(idx) {
+            return;
+        }
+        let (prev, next) = (self.nodes[idx].prev, self.nodes[idx].next);
+        if let Some(prev) = prev {
+            self.nodes[prev].next = next;
+        }
+        if let Some(next) = next {
+            self.nodes[next].prev = prev;
+        } else {
+            self.tail = prev;
+        }
+        self.nodes[idx].prev = None;
+        self.nodes[idx].next = self.head;
+        if let Some(head) = self.head {
+            self.nodes[head].prev = Some(idx);
+        }
+        self.head = Some(idx);
+    }
+
+    fn evict_tail(&mut self) {
+        let Some(tail) = self.tail else { return };
+        let prev = self.nodes[tail].prev;
+        if let Some(prev) = prev {
+            self.nodes[prev].next = None;
+        }
+        self.tail = prev;
+        if self.head == Some(tail) {
+            self.head = None;
+        }
+        self.map.remove(&self.nodes[tail].key);
+        self.free.push(tail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_put_roundtrip() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, "a");
+        cache.put(2, "b");
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.get(&2), Some(&"b"));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, "a");
+        cache.put(2, "b");
+        // 访问 1，使其变为最近使用，2 变为最久未使用
+        assert_eq!(cache.get(&1), Some(&"a"));
+        cache.put(3, "c");
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.get(&3), Some(&"c"));
+    }
+
+    #[test]
+    fn put_existing_key_updates_value_without_growing() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, "a");
+        cache.put(1, "b");
+        assert_eq!(cache.get(&1), Some(&"b"));
+        assert_eq!(cache.nodes.len(), 1);
+    }
+
+    #[test]
+    fn reuses_freed_slots_after_eviction() {
+        let mut cache = LruCache::new(1);
+        cache.put(1, "a");
+        cache.put(2, "b");
+        cache.put(3, "c");
+        assert_eq!(cache.nodes.len(), 2);
+    }
+}
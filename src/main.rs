@@ -58,6 +58,9 @@ async fn main() -> anyhow::Result<()> {
         SubCommand::Search(config) => {
             config.run(&opts).await?;
         }
+        SubCommand::Query(config) => {
+            config.run(&opts).await?;
+        }
         SubCommand::Build(config) => {
             config.run(&opts).await?;
         }
@@ -70,6 +73,27 @@ async fn main() -> anyhow::Result<()> {
         SubCommand::Train(config) => {
             config.run(&opts).await?;
         }
+        SubCommand::Bench(config) => {
+            config.run(&opts).await?;
+        }
+        SubCommand::BenchCompare(config) => {
+            config.run(&opts).await?;
+        }
+        SubCommand::Scrub(config) => {
+            config.run(&opts).await?;
+        }
+        SubCommand::Backup(config) => {
+            config.run(&opts).await?;
+        }
+        SubCommand::Compact(config) => {
+            config.run(&opts).await?;
+        }
+        SubCommand::UpdateDb(config) => {
+            config.run(&opts).await?;
+        }
+        SubCommand::Match(config) => {
+            config.run(&opts).await?;
+        }
     }
 
     Ok(())
@@ -55,6 +55,15 @@ pub fn inc_search_max_score(size: (u32, u32), nprobe: usize, orb_scale_factor: f
         .observe(score as f64);
 }
 
+static METRIC_TASK_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("im_task_queue_depth", "number of tasks waiting to be processed").unwrap()
+});
+
+/// 设置任务队列深度指标
+pub fn set_task_queue_depth(depth: i64) {
+    METRIC_TASK_QUEUE_DEPTH.set(depth);
+}
+
 /// 将图像面积范围调整到几个固定值
 fn to_fixed_size((width, height): (u32, u32)) -> &'static str {
     let area = width * height;
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use rand::Rng;
+
+/// 用于取模的大质数，需要大于任何可能出现的视觉词 ID
+const PRIME: u64 = (1 << 61) - 1;
+
+/// 持久化的 MinHash 哈希函数参数
+///
+/// 每个视觉词集合 `S` 通过 H 个独立的哈希函数 `h_i(x) = (a_i * (x+1) + b_i) mod PRIME`
+/// 生成签名 `sig[i] = min_{x in S} (h_i(x) 截断为 u32)`；`a`/`b` 第一次使用时随机生成并写入磁盘，
+/// 之后固定不变，从而保证同一张图片在多次运行之间算出的签名仍然可比
+pub struct MinHashSeeds {
+    a: Vec<u64>,
+    b: Vec<u64>,
+    path: PathBuf,
+}
+
+impl MinHashSeeds {
+    /// 签名长度（哈希函数数量）
+    pub fn h(&self) -> usize {
+        self.a.len()
+    }
+
+    /// 打开已持久化的种子，不存在时使用 `h` 生成一组新的种子并写入磁盘
+    pub fn open_or_create(path: impl AsRef<Path>, h: usize) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            return Self::open(path);
+        }
+
+        let mut rng = rand::rng();
+        let a = (0..h).map(|_| rng.random_range(1..PRIME)).collect::<Vec<_>>();
+        let b = (0..h).map(|_| rng.random_range(0..PRIME)).collect::<Vec<_>>();
+        let seeds = Self { a, b, path };
+        seeds.write()?;
+        Ok(seeds)
+    }
+
+    fn open(path: PathBuf) -> Result<Self> {
+        let data = fs::read(&path)?;
+        let values: &[u64] = bytemuck::cast_slice(&data);
+        let h = values.len() / 2;
+        let (a, b) = values.split_at(h);
+        Ok(Self { a: a.to_vec(), b: b.to_vec(), path })
+    }
+
+    fn write(&self) -> Result<()> {
+        let mut values = self.a.clone();
+        values.extend_from_slice(&self.b);
+        fs::write(&self.path, bytemuck::cast_slice::<u64, u8>(&values))?;
+        Ok(())
+    }
+
+    /// 计算一组视觉词 ID 的 MinHash 签名
+    ///
+    /// 负数 ID（量化器未找到最近中心点）会被忽略；传入空集合（或全部被忽略）时返回
+    /// `None`，表示这张图片无法参与 MinHash 粗筛，调用方应当将其视为始终命中
+    pub fn signature(&self, words: &[i64]) -> Option<Vec<u32>> {
+        let words = words.iter().filter(|&&w| w >= 0).map(|&w| w as u64 + 1).collect::<Vec<_>>();
+        if words.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.a
+                .iter()
+                .zip(&self.b)
+                .map(|(&a, &b)| {
+                    words
+                        .iter()
+                        .map(|&x| ((a as u128 * x as u128 + b as u128) % PRIME as u128) as u32)
+                        .min()
+                        .unwrap()
+                })
+                .collect(),
+        )
+    }
+}
+
+/// 估计两个 MinHash 签名对应集合的 Jaccard 相似度：签名中取值相同的位置所占比例
+///
+/// 这是一个整数比较，不需要重新计算原始集合的交并集，因此比对一整个数据库的代价很低
+pub fn estimate_similarity(a: &[u32], b: &[u32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f32 / a.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_sets_match_exactly() {
+        let dir = tempfile::tempdir().unwrap();
+        let seeds = MinHashSeeds::open_or_create(dir.path().join("seeds"), 64).unwrap();
+
+        let sig = seeds.signature(&[1, 2, 3]).unwrap();
+        let sig2 = seeds.signature(&[3, 2, 1]).unwrap();
+        assert_eq!(sig, sig2);
+        assert_eq!(estimate_similarity(&sig, &sig2), 1.0);
+    }
+
+    #[test]
+    fn empty_set_has_no_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let seeds = MinHashSeeds::open_or_create(dir.path().join("seeds"), 64).unwrap();
+        assert!(seeds.signature(&[]).is_none());
+        assert!(seeds.signature(&[-1, -1]).is_none());
+    }
+
+    #[test]
+    fn seeds_are_stable_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seeds");
+        let seeds = MinHashSeeds::open_or_create(&path, 64).unwrap();
+        let sig = seeds.signature(&[1, 2, 3]).unwrap();
+
+        let reopened = MinHashSeeds::open_or_create(&path, 64).unwrap();
+        assert_eq!(sig, reopened.signature(&[1, 2, 3]).unwrap());
+    }
+}
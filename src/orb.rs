@@ -9,6 +9,7 @@ use opencv::imgproc::InterpolationFlags;
 use orb_slam3_sys::*;
 
 use crate::config::OrbOptions;
+use crate::features::FeatureExtractorKind;
 use crate::utils;
 
 // 注意：ORB_OPTIONS 必须在 ORB 之前初始化
@@ -81,8 +82,9 @@ impl Slam3ORB {
         mask: &impl ToInputArray,
         keypoints: &mut Vector<KeyPoint>,
         descriptors: &mut impl ToOutputArray,
+        lapping_area: (i32, i32),
     ) -> Result<()> {
-        let v_lapping_area = Vector::<i32>::from(vec![0, 0]);
+        let v_lapping_area = Vector::<i32>::from(vec![lapping_area.0, lapping_area.1]);
         input_array_arg!(image);
         input_array_arg!(mask);
         output_array_arg!(descriptors);
@@ -122,6 +124,13 @@ pub struct ORBDetector {
 
 impl ORBDetector {
     pub fn create(options: OrbOptions) -> Self {
+        // 调用方必须先调用 `OrbOptions::ensure_extractor_supported` 拒绝不支持的提取后端，
+        // 这里不再静默退化为 ORB：否则用户选了 `--extractor sift` 却悄悄拿到 ORB 的结果
+        debug_assert_eq!(
+            options.extractor,
+            FeatureExtractorKind::Orb,
+            "调用方应先校验 extractor，见 OrbOptions::ensure_extractor_supported"
+        );
         Self { orb: HashMap::new(), opts: options }
     }
 
@@ -161,24 +170,31 @@ impl ORBDetector {
         })
     }
 
+    fn lapping_area(&self) -> (i32, i32) {
+        (self.opts.orb_lapping_left, self.opts.orb_lapping_right)
+    }
+
     pub fn detect_file(&mut self, path: &str) -> Result<(Mat, Vec<KeyPoint>, Vec<[u8; 32]>)> {
         let image = utils::imread(path, self.opts.max_size)?;
+        let lapping_area = self.lapping_area();
         let orb = self.get_orb(&image);
-        let (keypoints, descriptors) = utils::detect_and_compute(orb, &image)?;
+        let (keypoints, descriptors) = utils::detect_and_compute(orb, &image, lapping_area)?;
         Ok((image, keypoints, descriptors))
     }
 
     pub fn detect_bytes(&mut self, bytes: &[u8]) -> Result<(Vec<KeyPoint>, Vec<[u8; 32]>)> {
         let image = utils::imdecode(bytes, self.opts.max_size)?;
+        let lapping_area = self.lapping_area();
         let orb = self.get_orb(&image);
-        let (keypoints, descriptors) = utils::detect_and_compute(orb, &image)?;
+        let (keypoints, descriptors) = utils::detect_and_compute(orb, &image, lapping_area)?;
         Ok((keypoints, descriptors))
     }
 
     pub fn detect_image(&mut self, image: Mat) -> Result<(Vec<KeyPoint>, Vec<[u8; 32]>)> {
         let image = utils::adjust_image_size(image, self.opts.max_size)?;
+        let lapping_area = self.lapping_area();
         let orb = self.get_orb(&image);
-        let (keypoints, descriptors) = utils::detect_and_compute(orb, &image)?;
+        let (keypoints, descriptors) = utils::detect_and_compute(orb, &image, lapping_area)?;
         Ok((keypoints, descriptors))
     }
 }
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use opencv::core::{self, CV_32F, Size, ToInputArray};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+pub type PHash = [u8; 8];
+
+/// 基于 DCT 的感知哈希：缩放到 32x32 后做二维 DCT，取左上角 8x8 低频块（不含直流分量），
+/// 以这 63 个系数的中位数为阈值，对全部 64 个系数逐位生成哈希，结果可以像 dhash 一样用
+/// Hamming 距离比较。相比 dhash，对 gamma/对比度变化以及局部裁剪更鲁棒
+pub fn p_hash(input_arr: &impl ToInputArray) -> Result<PHash> {
+    let mut resize_img = Mat::default();
+    imgproc::resize(
+        input_arr,
+        &mut resize_img,
+        Size::new(32, 32),
+        0.0,
+        0.0,
+        imgproc::INTER_LINEAR_EXACT,
+    )?;
+
+    let gray_img = if resize_img.channels() > 1 {
+        let mut output = Mat::default();
+        imgproc::cvt_color_def(&resize_img, &mut output, imgproc::COLOR_BGR2GRAY)?;
+        output
+    } else {
+        resize_img
+    };
+
+    let mut float_img = Mat::default();
+    gray_img.convert_to(&mut float_img, CV_32F, 1.0, 0.0)?;
+
+    let mut dct_img = Mat::default();
+    core::dct(&float_img, &mut dct_img, 0)?;
+
+    let mut coeffs = [0f32; 64];
+    for y in 0..8 {
+        for x in 0..8 {
+            coeffs[y * 8 + x] = *dct_img.at_2d::<f32>(y, x)?;
+        }
+    }
+
+    // 计算中位数时排除直流分量（左上角第一个系数）
+    let mut sorted: Vec<f32> = coeffs[1..].to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = sorted[sorted.len() / 2];
+
+    let mut hash = [0u8; 8];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        let mut b = 0u8;
+        for j in 0..8 {
+            b <<= 1;
+            b |= if coeffs[i * 8 + j] > median { 1 } else { 0 };
+        }
+        *byte = b;
+    }
+
+    Ok(hash)
+}
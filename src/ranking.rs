@@ -0,0 +1,194 @@
+//! 多阶段排名流水线
+//!
+//! 按顺序对候选图片应用一组 [`Criterion`]：排在前面的规则决定主要顺序，后面的规则只用来
+//! 给前面规则打出相同分数的候选排出先后顺序（即"决胜规则"）。实现上利用 Rust 稳定排序的
+//! 性质，从最后一个规则开始依次对候选做一次稳定排序，先应用的规则产生的顺序会被后续规则的
+//! 稳定排序保留，等价于一次多关键字排序；候选数量超过 [`BUCKET_THRESHOLD`] 时每条规则改用
+//! 分桶近似排序（见 [`Criterion::apply`]），同一个桶内分数不同的候选不再按本规则区分先后
+
+use clap::ValueEnum;
+
+use crate::utils::wilson_score;
+
+/// 候选数量超过这个阈值时，排序从精确比较排序切换为分桶近似排序
+const BUCKET_THRESHOLD: usize = 1000;
+/// 分桶排序使用的桶数量
+const BUCKET_COUNT: usize = 256;
+
+/// 排名流水线中的候选图片：聚合了该图片命中的所有查询特征
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub image_id: i64,
+    /// 命中的分片名称，索引由多个分片通过 HStack 联合而成时使用
+    pub shard: Option<String>,
+    /// 每个命中特征的相似度，范围 `[0, 1]`，值越大越相似，和 [`Self::query_indices`] 一一对应
+    pub scores: Vec<f32>,
+    /// 每个命中对应的查询描述符序号，用于 [`SpatialConsistency`] 估计匹配的覆盖范围
+    pub query_indices: Vec<usize>,
+}
+
+/// 排名流水线中的一条评分规则
+pub trait Criterion: Send + Sync {
+    /// 对一个候选图片打分，分数越大排名越靠前
+    fn score(&self, candidate: &Candidate) -> f32;
+
+    /// 对候选集合按本规则重新排序
+    ///
+    /// 候选数量不超过 [`BUCKET_THRESHOLD`] 时做一次精确的稳定排序；超过阈值后，完整的
+    /// `O(n log n)` 比较排序开销太大，改为把分数线性映射到 [`BUCKET_COUNT`] 个桶里做一次
+    /// `O(n)` 的近似排序，桶内顺序保持稳定
+    fn apply(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+        if candidates.len() <= BUCKET_THRESHOLD {
+            let mut candidates = candidates;
+            candidates.sort_by(|a, b| self.score(b).total_cmp(&self.score(a)));
+            candidates
+        } else {
+            bucket_sort(candidates, |c| self.score(c))
+        }
+    }
+}
+
+fn bucket_sort(candidates: Vec<Candidate>, score: impl Fn(&Candidate) -> f32) -> Vec<Candidate> {
+    let max = candidates.iter().map(&score).fold(0.0f32, f32::max);
+    if max <= 0.0 {
+        return candidates;
+    }
+
+    let mut buckets: Vec<Vec<Candidate>> = (0..BUCKET_COUNT).map(|_| Vec::new()).collect();
+    for candidate in candidates {
+        let ratio = (score(&candidate) / max).clamp(0.0, 1.0);
+        let idx = (ratio * (BUCKET_COUNT - 1) as f32) as usize;
+        // 桶序号越大分数越高，最后翻转成降序
+        buckets[BUCKET_COUNT - 1 - idx].push(candidate);
+    }
+    buckets.into_iter().flatten().collect()
+}
+
+/// 依次应用一组评分规则，规则顺序决定优先级：排在前面的是主排序依据，后面的规则只在前面
+/// 规则打平分时才会起作用
+pub fn run_pipeline(candidates: Vec<Candidate>, criteria: &[Box<dyn Criterion>]) -> Vec<Candidate> {
+    let mut candidates = candidates;
+    for criterion in criteria.iter().rev() {
+        candidates = criterion.apply(candidates);
+    }
+    candidates
+}
+
+/// 按命中特征数量排序
+pub struct MatchCount;
+
+impl Criterion for MatchCount {
+    fn score(&self, candidate: &Candidate) -> f32 {
+        candidate.scores.len() as f32
+    }
+}
+
+/// 按威尔逊评分排序，兼顾命中数量和匹配质量，对样本较少的图片更保守
+pub struct WilsonScore;
+
+impl Criterion for WilsonScore {
+    fn score(&self, candidate: &Candidate) -> f32 {
+        100. * wilson_score(&candidate.scores)
+    }
+}
+
+/// 按平均相似度排序
+pub struct MeanDistance;
+
+impl Criterion for MeanDistance {
+    fn score(&self, candidate: &Candidate) -> f32 {
+        candidate.scores.iter().sum::<f32>() / candidate.scores.len() as f32
+    }
+}
+
+/// 空间一致性排序
+///
+/// 完整的 RANSAC 单应性验证需要保留每个命中的关键点坐标，目前搜索路径还没有把坐标一路带到
+/// 排名阶段（持久化的几何重排序见 `SearchOptions::rerank`），这里先用命中覆盖的查询特征
+/// 去重数量做一个弱代理：命中分散在越多不同的查询特征上，通常意味着匹配区域在图片上分布
+/// 得越开，比起 [`MatchCount`] 的原始命中数更不容易被同一小块局部重复纹理刷高分数
+pub struct SpatialConsistency;
+
+impl Criterion for SpatialConsistency {
+    fn score(&self, candidate: &Candidate) -> f32 {
+        let mut indices = candidate.query_indices.clone();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.len() as f32
+    }
+}
+
+/// CLI/服务端可配置的评分规则种类，映射到具体的 [`Criterion`] 实现
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CriterionKind {
+    MatchCount,
+    WilsonScore,
+    MeanDistance,
+    SpatialConsistency,
+}
+
+impl CriterionKind {
+    pub fn build(self) -> Box<dyn Criterion> {
+        match self {
+            CriterionKind::MatchCount => Box::new(MatchCount),
+            CriterionKind::WilsonScore => Box::new(WilsonScore),
+            CriterionKind::MeanDistance => Box::new(MeanDistance),
+            CriterionKind::SpatialConsistency => Box::new(SpatialConsistency),
+        }
+    }
+}
+
+/// 把一组 [`CriterionKind`] 构建成流水线，方便从 `SearchOptions::criteria` 直接构造
+pub fn build_pipeline(kinds: &[CriterionKind]) -> Vec<Box<dyn Criterion>> {
+    kinds.iter().map(|kind| kind.build()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(image_id: i64, scores: Vec<f32>) -> Candidate {
+        let query_indices = (0..scores.len()).collect();
+        Candidate { image_id, shard: None, scores, query_indices }
+    }
+
+    #[test]
+    fn test_run_pipeline_uses_later_criteria_as_tiebreaker() {
+        // 两个候选的 MatchCount 相同（都命中 2 次），只有 MeanDistance 能分出高低
+        let candidates = vec![
+            candidate(1, vec![0.9, 0.1]), // 均值 0.5
+            candidate(2, vec![0.6, 0.6]), // 均值 0.6，应该排在前面
+        ];
+        let criteria: Vec<Box<dyn Criterion>> = vec![Box::new(MatchCount), Box::new(MeanDistance)];
+
+        let result = run_pipeline(candidates, &criteria);
+        assert_eq!(result[0].image_id, 2);
+        assert_eq!(result[1].image_id, 1);
+    }
+
+    #[test]
+    fn test_run_pipeline_primary_criterion_wins_over_tiebreaker() {
+        // MatchCount 不同时，排序应该完全由第一条规则决定，不受后面规则影响
+        let candidates = vec![
+            candidate(1, vec![0.1]),            // 命中 1 次，均值更高
+            candidate(2, vec![0.0, 0.0, 0.0]),   // 命中 3 次，均值更低
+        ];
+        let criteria: Vec<Box<dyn Criterion>> = vec![Box::new(MatchCount), Box::new(MeanDistance)];
+
+        let result = run_pipeline(candidates, &criteria);
+        assert_eq!(result[0].image_id, 2);
+        assert_eq!(result[1].image_id, 1);
+    }
+
+    #[test]
+    fn test_bucket_sort_matches_exact_sort_ordering() {
+        // 超过 BUCKET_THRESHOLD 时走近似分桶排序，这里验证其排序方向与精确排序一致：
+        // 分数越高排名越靠前
+        let candidates: Vec<Candidate> =
+            (0..(BUCKET_THRESHOLD + 1)).map(|i| candidate(i as i64, vec![i as f32])).collect();
+
+        let sorted = MatchCount.apply(candidates);
+        assert_eq!(sorted.first().unwrap().image_id, BUCKET_THRESHOLD as i64);
+        assert_eq!(sorted.last().unwrap().image_id, 0);
+    }
+}
@@ -0,0 +1,83 @@
+//! 基于 RANSAC 单应性验证的几何重排序
+//!
+//! 描述符投票得到的候选结果容易受到"恰好共享大量 ORB 描述符但实际无关"的图片干扰，
+//! 这里对 Top-N 候选结果重新做一次完整的描述符匹配 + RANSAC，
+//! 用几何一致的内点数量代替投票得分，过滤掉空间上不一致的误匹配。
+
+use opencv::calib3d;
+use opencv::core::{KeyPoint, Mat, Point2f, Vector};
+use opencv::prelude::*;
+
+use crate::hamming::hamming;
+
+/// 几何验证的结果
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RerankScore {
+    /// 通过比率测试的匹配数量
+    pub matches: usize,
+    /// RANSAC 估计单应性后的内点数量
+    pub inliers: usize,
+}
+
+impl RerankScore {
+    /// 内点比例，用于在内点数量相同时打破平局
+    pub fn inlier_ratio(&self) -> f32 {
+        if self.matches == 0 { 0. } else { self.inliers as f32 / self.matches as f32 }
+    }
+}
+
+/// 对查询图片和候选图片的描述符做几何一致性验证
+///
+/// * `ratio` - 比率测试阈值，通常取 0.75
+/// * `ransac_threshold` - 重投影误差阈值（像素），通常取 3~5
+pub fn geometric_verify(
+    query_kps: &[KeyPoint],
+    query_des: &[[u8; 32]],
+    cand_kps: &[KeyPoint],
+    cand_des: &[[u8; 32]],
+    ratio: f32,
+    ransac_threshold: f64,
+) -> opencv::Result<RerankScore> {
+    let matches = ratio_test_matches(query_des, cand_des, ratio);
+    if matches.len() < 4 {
+        return Ok(RerankScore { matches: matches.len(), inliers: 0 });
+    }
+
+    let mut src = Vector::<Point2f>::new();
+    let mut dst = Vector::<Point2f>::new();
+    for &(i, j) in &matches {
+        src.push(query_kps[i].pt()?);
+        dst.push(cand_kps[j].pt()?);
+    }
+
+    let mut mask = Mat::default();
+    let h = calib3d::find_homography(&src, &dst, &mut mask, calib3d::RANSAC, ransac_threshold)?;
+    if h.empty() {
+        return Ok(RerankScore { matches: matches.len(), inliers: 0 });
+    }
+
+    let inliers = mask.data_bytes()?.iter().filter(|&&b| b != 0).count();
+    Ok(RerankScore { matches: matches.len(), inliers })
+}
+
+/// 暴力匹配 + 比率测试：仅当最佳匹配距离 < ratio * 次佳匹配距离时才接受
+fn ratio_test_matches(query: &[[u8; 32]], cand: &[[u8; 32]], ratio: f32) -> Vec<(usize, usize)> {
+    let mut result = Vec::new();
+    for (i, q) in query.iter().enumerate() {
+        let mut best = (u32::MAX, usize::MAX);
+        let mut second = u32::MAX;
+        for (j, c) in cand.iter().enumerate() {
+            let d = hamming::<32>(q, c);
+            if d < best.0 {
+                second = best.0;
+                best = (d, j);
+            } else if d < second {
+                second = d;
+            }
+        }
+        if best.1 != usize::MAX && (best.0 as f32) < ratio * second as f32 {
+            result.push((i, best.1));
+        }
+    }
+    result
+}
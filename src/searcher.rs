@@ -1,11 +1,16 @@
+use std::collections::HashMap;
 use std::iter;
 use std::path::Path;
 
 use crate::db::ImageDB;
+use crate::knn::FaissSearcher;
+use crate::orb::ORBDetector;
+use crate::rerank::geometric_verify;
 use crate::slam3_orb::Slam3ORB;
 use crate::utils;
+use crate::utils::wilson_score;
 use anyhow::Result;
-use opencv::prelude::{MatTraitConst, MatTraitConstManual};
+use opencv::prelude::*;
 
 pub struct Neighbor {
     pub id: usize,
@@ -60,7 +65,87 @@ impl ImSearcher {
         self.db.add_image(image_path.as_ref(), descriptors)
     }
 
-    pub fn search_image(&self) {
+    /// 先用描述符投票给出一个初始候选排名，再对 Top-N 候选重新提取描述符做 RANSAC
+    /// 单应性验证，用几何一致的内点数量代替投票得分重新排序
+    ///
+    /// 返回 `(图片路径, 投票得分, 内点数量)` 列表，按内点数量降序排列
+    pub fn search_image(
+        &self,
+        image_path: &str,
+        orb: &mut ORBDetector,
+        k: usize,
+        top_n: usize,
+    ) -> Result<Vec<(String, f32, usize)>> {
+        let (_, query_kps, query_des) = orb.detect_file(image_path)?;
 
+        let train_mat = self.all_features_mat()?;
+        let mut index = FaissSearcher::new(32 * 8, "BFlat");
+        index.add(&train_mat);
+
+        let query_mat = descriptors_to_mat(&query_des)?;
+        let neighbors = index.search(&query_mat, k);
+
+        // 按命中的图片累加投票权重，距离越近权重越高
+        let mut votes: HashMap<String, Vec<f32>> = HashMap::new();
+        for group in &neighbors {
+            for n in group {
+                let path = self.db.find_image_path(n.index as u64)?;
+                votes.entry(path).or_default().push(1. - n.distance as f32 / 256.);
+            }
+        }
+
+        let mut candidates: Vec<(String, f32)> = votes
+            .into_iter()
+            .map(|(path, scores)| (path, 100. * wilson_score(&scores)))
+            .collect();
+        candidates.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        candidates.truncate(top_n);
+
+        // 对 Top-N 候选重新提取描述符，与查询描述符做比率测试 + RANSAC 几何验证
+        let mut verified = Vec::with_capacity(candidates.len());
+        for (path, score) in candidates {
+            let inliers = orb
+                .detect_file(&path)
+                .ok()
+                .and_then(|(_, cand_kps, cand_des)| {
+                    geometric_verify(&query_kps, &query_des, &cand_kps, &cand_des, 0.7, 3.).ok()
+                })
+                .map_or(0, |verified| verified.inliers);
+            verified.push((path, score, inliers));
+        }
+        verified.sort_unstable_by(|a, b| b.2.cmp(&a.2));
+
+        Ok(verified)
+    }
+
+    /// 将库内已入库的全部描述符拼成一个矩阵，用于临时建立一个暴力匹配索引
+    fn all_features_mat(&self) -> Result<opencv::core::Mat> {
+        let features: Vec<Box<[u8]>> = self.db.features(true).map(|(_, f)| f).collect();
+        let mut mat = opencv::core::Mat::new_rows_cols_with_default(
+            features.len() as i32,
+            32,
+            opencv::core::CV_8U,
+            opencv::core::Scalar::default(),
+        )?;
+        for (i, feature) in features.iter().enumerate() {
+            let row = mat.at_row_mut(i as i32)?;
+            row.copy_from_slice(feature);
+        }
+        Ok(mat)
+    }
+}
+
+/// 将一组描述符拼成一个矩阵，供 [`FaissSearcher`] 查询使用
+fn descriptors_to_mat(des: &[[u8; 32]]) -> Result<opencv::core::Mat> {
+    let mut mat = opencv::core::Mat::new_rows_cols_with_default(
+        des.len() as i32,
+        32,
+        opencv::core::CV_8U,
+        opencv::core::Scalar::default(),
+    )?;
+    for (i, d) in des.iter().enumerate() {
+        let row = mat.at_row_mut(i as i32)?;
+        row.copy_from_slice(d);
     }
+    Ok(mat)
 }
@@ -1,14 +1,23 @@
 use std::sync::Arc;
 use std::time::Instant;
 
-use axum::Json;
-use axum::extract::State;
+use axum::body::{Body, Bytes};
+use axum::extract::{Multipart, Request, State};
+use axum::http::header;
+use axum::response::Response;
+use axum::{Json, body};
 use axum_typed_multipart::TypedMultipart;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use futures::prelude::*;
 use log::info;
 use opencv::imgcodecs;
 use opencv::prelude::*;
 use prometheus::TextEncoder;
+use rayon::prelude::*;
+use tokio::sync::mpsc;
 use tokio::task::spawn_blocking;
+use tokio_stream::wrappers::ReceiverStream;
 
 use super::error::Result;
 use super::state::AppState;
@@ -16,6 +25,10 @@ use super::types::*;
 use crate::config::{OrbOptions, SearchOptions};
 use crate::metrics::*;
 use crate::orb::ORBDetector;
+use crate::utils::ImageHash;
+
+/// 批量搜索中单个查询的并发处理数量，与 `cli::add` 中的抓取/计算流水线保持一致
+const BATCH_CONCURRENCY: usize = 8;
 
 /// 搜索一张图片
 #[utoipa::path(
@@ -54,7 +67,8 @@ pub async fn search_handler(
     })
     .await??;
 
-    let result = { state.db.search(state.index.clone(), des, k, distance, count, nprobe).await? };
+    let index = state.index.read().await.clone();
+    let result = { state.db.search(index, des, k, distance, count, nprobe).await? };
 
     inc_image_count(size, nprobe, orb.orb_scale_factor);
     inc_search_duration(size, nprobe, orb.orb_scale_factor, start.elapsed().as_secs_f32());
@@ -63,6 +77,277 @@ pub async fn search_handler(
     Ok(Json(SearchResponse { time: start.elapsed().as_millis() as u32, result }))
 }
 
+/// 批量搜索多张图片
+///
+/// 支持两种请求格式：
+/// - `multipart/form-data`：可重复提交多个 `file` 字段，其余参数作用于批次中的每张图片
+/// - `application/x-ndjson`：每行一个 JSON 对象，字段同 [`BatchQueryLine`]，图片内容为 Base64 编码
+///
+/// 结果同样以 `application/x-ndjson` 流式返回，每条查询完成后立即写出一行，
+/// 不需要等待整个批次处理完毕
+#[utoipa::path(
+    post,
+    path = "/search/batch",
+    request_body(content = BatchSearchForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = BatchSearchResult, content_type = "application/x-ndjson"),
+    )
+)]
+pub async fn search_batch_handler(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+) -> Result<Response> {
+    let is_ndjson = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-ndjson"));
+
+    let queries =
+        if is_ndjson { collect_ndjson_queries(request).await? } else { collect_multipart_queries(request).await? };
+
+    info!("正在批量搜索 {} 张图片", queries.len());
+
+    let (tx, rx) = mpsc::channel::<std::result::Result<Bytes, std::io::Error>>(BATCH_CONCURRENCY);
+
+    tokio::spawn(async move {
+        stream::iter(queries.into_iter().enumerate())
+            .map(|(index, query)| {
+                let state = state.clone();
+                let tx = tx.clone();
+                async move {
+                    let line = search_one(&state, index, query).await;
+                    let mut buf = serde_json::to_vec(&line).expect("序列化搜索结果失败");
+                    buf.push(b'\n');
+                    let _ = tx.send(Ok(Bytes::from(buf))).await;
+                }
+            })
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .for_each(|_| future::ready(()))
+            .await;
+    });
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .body(Body::from_stream(ReceiverStream::new(rx)))
+        .unwrap())
+}
+
+/// 在一次请求中搜索多张图片
+///
+/// 与 `/search/batch` 的区别在于：这里只接受 `multipart/form-data`，所有图片的 ORB 特征提取
+/// 通过 rayon 一次性并行算完，再统一发起搜索，而不是把每张图片当作独立的异步任务单独调度；
+/// 这样可以把索引只锁定/克隆一次，分摊掉联邦查询场景下反复申请索引引用的开销。响应一次性
+/// 返回完整的 `Vec<SearchResponse>`，不像 `/search/batch` 那样逐条流式输出
+#[utoipa::path(
+    post,
+    path = "/multi-search",
+    request_body(content = BatchSearchForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = [SearchResponse]),
+    )
+)]
+pub async fn multi_search_handler(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+) -> Result<Json<Vec<SearchResponse>>> {
+    let queries = collect_multipart_queries(request).await?;
+    info!("正在搜索 {} 张图片", queries.len());
+
+    // 每张图片实际生效的 nprobe/orb_scale_factor，搜索和打点都要用到，
+    // 提前算好避免 queries 被下面的 spawn_blocking 移动后无法再访问
+    let effective: Vec<(usize, f32)> = queries
+        .iter()
+        .map(|q| {
+            (q.nprobe.unwrap_or(state.search.nprobe), q.orb_scale_factor.unwrap_or(state.orb.orb_scale_factor))
+        })
+        .collect();
+
+    // 先用 rayon 一次性并行完成所有图片的解码和 ORB 特征提取，再统一取用同一个索引引用发起
+    // 搜索，相比逐张图片分别 spawn_blocking 能减少调度开销，也只需要克隆一次索引引用
+    let orb_defaults = state.orb.clone();
+    let detected = spawn_blocking(move || -> Vec<anyhow::Result<((u32, u32), Vec<[u8; 32]>)>> {
+        queries
+            .par_iter()
+            .map(|query| {
+                let orb = OrbOptions {
+                    orb_nfeatures: query.orb_nfeatures.unwrap_or(orb_defaults.orb_nfeatures),
+                    orb_scale_factor: query.orb_scale_factor.unwrap_or(orb_defaults.orb_scale_factor),
+                    ..orb_defaults.clone()
+                };
+                let mat = Mat::from_slice(&query.file)?;
+                let img = imgcodecs::imdecode(&mat, imgcodecs::IMREAD_GRAYSCALE)?;
+                let size = (img.cols() as u32, img.rows() as u32);
+                let mut orb = ORBDetector::create(orb);
+                let (_, des) = orb.detect_image(img)?;
+                Ok((size, des))
+            })
+            .collect()
+    })
+    .await?;
+
+    let SearchOptions { k, distance, count, .. } = state.search;
+
+    let index = state.index.read().await.clone();
+    let mut responses = Vec::with_capacity(detected.len());
+    for ((nprobe, orb_scale_factor), detected) in effective.into_iter().zip(detected) {
+        let start = Instant::now();
+        let response = match detected {
+            Ok((size, des)) => {
+                let result = state.db.search(index.clone(), des, k, distance, count, nprobe).await?;
+                inc_image_count(size, nprobe, orb_scale_factor);
+                inc_search_duration(size, nprobe, orb_scale_factor, start.elapsed().as_secs_f32());
+                if let Some((score, _)) = result.first() {
+                    inc_search_max_score(size, nprobe, orb_scale_factor, *score);
+                }
+                SearchResponse { time: start.elapsed().as_millis() as u32, result }
+            }
+            Err(_) => SearchResponse { time: 0, result: vec![] },
+        };
+        responses.push(response);
+    }
+
+    Ok(Json(responses))
+}
+
+/// 搜索批次中的一张图片，出错时将错误信息放入 [`BatchSearchResult::error`] 而不中断其他查询
+async fn search_one(state: &Arc<AppState>, index: usize, query: BatchQuery) -> BatchSearchResult {
+    match search_one_inner(state, query).await {
+        Ok((time, result)) => BatchSearchResult { index, time, result, error: None },
+        Err(e) => BatchSearchResult { index, time: 0, result: vec![], error: Some(e.to_string()) },
+    }
+}
+
+async fn search_one_inner(
+    state: &Arc<AppState>,
+    query: BatchQuery,
+) -> anyhow::Result<(u32, Vec<(f32, String)>)> {
+    let orb = OrbOptions {
+        orb_nfeatures: query.orb_nfeatures.unwrap_or(state.orb.orb_nfeatures),
+        orb_scale_factor: query.orb_scale_factor.unwrap_or(state.orb.orb_scale_factor),
+        ..state.orb
+    };
+    let SearchOptions { k, distance, count, .. } = state.search;
+    let nprobe = query.nprobe.unwrap_or(state.search.nprobe);
+
+    let start = Instant::now();
+
+    let orbc = orb.clone();
+    let (size, des) = spawn_blocking(move || {
+        let mat = Mat::from_slice(&query.file)?;
+        let img = imgcodecs::imdecode(&mat, imgcodecs::IMREAD_GRAYSCALE)?;
+        let size = (img.cols() as u32, img.rows() as u32);
+        let mut orb = ORBDetector::create(orbc);
+        let (_, des) = orb.detect_image(img)?;
+        anyhow::Result::<_>::Ok((size, des))
+    })
+    .await??;
+
+    let index = state.index.read().await.clone();
+    let result = state.db.search(index, des, k, distance, count, nprobe).await?;
+
+    inc_image_count(size, nprobe, orb.orb_scale_factor);
+    inc_search_duration(size, nprobe, orb.orb_scale_factor, start.elapsed().as_secs_f32());
+    if let Some((score, _)) = result.first() {
+        inc_search_max_score(size, nprobe, orb.orb_scale_factor, *score);
+    }
+
+    Ok((start.elapsed().as_millis() as u32, result))
+}
+
+/// 从 `multipart/form-data` 请求中收集批量查询，可重复提交多个 `file` 字段
+async fn collect_multipart_queries(request: Request) -> Result<Vec<BatchQuery>> {
+    let mut multipart = Multipart::from_request(request, &()).await.map_err(anyhow::Error::from)?;
+
+    let mut files = vec![];
+    let (mut orb_nfeatures, mut orb_scale_factor, mut nprobe) = (None, None, None);
+
+    while let Some(field) = multipart.next_field().await? {
+        match field.name().unwrap_or_default() {
+            "file" => files.push(field.bytes().await?),
+            "orb_nfeatures" => orb_nfeatures = field.text().await?.parse().ok(),
+            "orb_scale_factor" => orb_scale_factor = field.text().await?.parse().ok(),
+            "nprobe" => nprobe = field.text().await?.parse().ok(),
+            _ => {}
+        }
+    }
+
+    Ok(files.into_iter().map(|file| BatchQuery { file, orb_nfeatures, orb_scale_factor, nprobe }).collect())
+}
+
+/// 从 `application/x-ndjson` 请求体中收集批量查询，每行一个 [`BatchQueryLine`]
+async fn collect_ndjson_queries(request: Request) -> Result<Vec<BatchQuery>> {
+    let data = body::to_bytes(request.into_body(), usize::MAX).await.map_err(anyhow::Error::from)?;
+
+    let mut queries = vec![];
+    for line in data.split(|&b| b == b'\n') {
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        let line: BatchQueryLine = serde_json::from_slice(line)?;
+        let file = Bytes::from(BASE64.decode(line.file)?);
+        queries.push(BatchQuery {
+            file,
+            orb_nfeatures: line.orb_nfeatures,
+            orb_scale_factor: line.orb_scale_factor,
+            nprobe: line.nprobe,
+        });
+    }
+
+    Ok(queries)
+}
+
+/// 添加一张图片到数据库
+///
+/// 复用 [`AddCommand`](crate::cli::add::AddCommand) 流水线中 hash/filter/calc/add 几个阶段
+/// 的逻辑：先用 blake3 精确匹配查重（同 `task_filter`/`task_add` 调用的
+/// [`IMDB::check_hash`]），未命中时再提取 ORB 特征并写入数据库。只做精确去重，不启用
+/// phash 近似去重——后者依赖 `imsearch add --hash phash` 建库时一并构建的 phash 索引，
+/// 服务器按现有惯例（与 `search`/`bench` 等子命令一致）不会在打开数据库时额外构建它。
+///
+/// 写入只更新 sqlite 和倒排列表的落盘数据，不会让新图片立刻出现在 `/search` 结果中，
+/// 需要调用 `/index/reload` 重建索引骨架后才能被检索到
+#[utoipa::path(
+    post,
+    path = "/add",
+    request_body(content = AddForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = AddResponse),
+    )
+)]
+pub async fn add_handler(
+    State(state): State<Arc<AppState>>,
+    data: TypedMultipart<AddRequest>,
+) -> Result<Json<AddResponse>> {
+    let hash = ImageHash::Blake3.hash_bytes(&data.file)?.1;
+
+    if let Some(id) = state.db.check_hash(&hash, 0).await? {
+        return Ok(Json(AddResponse { id, duplicate: true }));
+    }
+
+    let orb = OrbOptions {
+        orb_nfeatures: data.orb_nfeatures.unwrap_or(state.orb.orb_nfeatures),
+        orb_scale_factor: data.orb_scale_factor.unwrap_or(state.orb.orb_scale_factor),
+        ..state.orb
+    };
+    let file = data.file.clone();
+    let descriptors = spawn_blocking(move || {
+        let mat = Mat::from_slice(&file)?;
+        let img = imgcodecs::imdecode(&mat, imgcodecs::IMREAD_GRAYSCALE)?;
+        let mut orb = ORBDetector::create(orb);
+        let (_, des) = orb.detect_image(img)?;
+        Result::Ok(des)
+    })
+    .await??;
+
+    let id = state.db.add_image(&data.path, &hash, &descriptors).await?;
+    state.db.mark_ingested(&data.path).await?;
+
+    info!("已添加图片: {}", data.path);
+
+    Ok(Json(AddResponse { id, duplicate: false }))
+}
+
 /// 获取 Prometheus 指标
 #[utoipa::path(get, path = "/metrics")]
 pub async fn metrics_handler(State(_state): State<Arc<AppState>>) -> Result<String> {
@@ -1,6 +1,8 @@
 mod api;
 mod error;
 mod state;
+mod stats;
+mod tasks;
 mod types;
 
 use std::sync::Arc;
@@ -14,17 +16,38 @@ use utoipa_swagger_ui::SwaggerUi;
 
 pub use self::api::*;
 pub use self::state::*;
+pub use self::stats::*;
+pub use self::tasks::*;
 pub use self::types::*;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         search_handler,
-        metrics_handler
+        search_batch_handler,
+        multi_search_handler,
+        add_handler,
+        metrics_handler,
+        enqueue_task_handler,
+        get_task_handler,
+        index_stats_handler,
+        merge_index_handler,
+        reload_index_handler
     ),
     components(schemas(
         SearchForm,
         SearchResponse,
+        BatchSearchForm,
+        BatchSearchResult,
+        AddForm,
+        AddResponse,
+        TaskRequest,
+        TaskResponse,
+        InvertedListSample,
+        IndexStatsResponse,
+        MergeIndexRequest,
+        MergeIndexResponse,
+        ReloadIndexResponse,
     )),
     modifiers(&SecurityAddon)
 )]
@@ -34,7 +57,15 @@ pub struct ApiDoc;
 pub fn create_app(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/search", post(search_handler))
+        .route("/search/batch", post(search_batch_handler))
+        .route("/multi-search", post(multi_search_handler))
+        .route("/add", post(add_handler))
         .route("/metrics", get(metrics_handler))
+        .route("/tasks", post(enqueue_task_handler))
+        .route("/tasks/{id}", get(get_task_handler))
+        .route("/index/stats", get(index_stats_handler))
+        .route("/index/merge", post(merge_index_handler))
+        .route("/index/reload", post(reload_index_handler))
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .layer(DefaultBodyLimit::disable())
         // 上传限制：50M
@@ -1,16 +1,24 @@
 use std::sync::Arc;
 
+use tokio::sync::{Mutex, RwLock};
+
 use crate::IMDB;
 use crate::cli::server::ServerCommand;
-use crate::config::{OrbOptions, SearchOptions};
+use crate::config::{ConfDir, OrbOptions, SearchOptions};
 use crate::ivf::IvfHnswDisk;
 
 /// 应用状态
 pub struct AppState {
-    /// Faiss索引
-    pub index: Arc<IvfHnswDisk>,
+    /// Faiss索引，`/index/reload` 持有写锁重建完成后整体替换，`/search` 等只读路径
+    /// 每次搜索前克隆一次内部的 `Arc`，搜索期间即使索引被替换也仍然用旧版本查询完毕
+    pub index: RwLock<Arc<IvfHnswDisk>>,
     /// 数据库连接
     pub db: IMDB,
+    /// 配置文件目录，任务队列重放 `add`/`build` 等 CLI 流水线时需要据此重新打开数据库，
+    /// `/index` 管理接口也需要据此重新打开主索引文件
+    pub conf_dir: ConfDir,
+    /// 主索引文件的写锁，`/index/merge` 持有此锁期间读写主索引文件，避免并发合并请求互相覆盖
+    pub index_write_lock: Mutex<()>,
     /// 服务器配置选项
     pub orb: OrbOptions,
     /// 搜索配置选项
@@ -21,10 +29,12 @@ pub struct AppState {
 
 impl AppState {
     /// 创建新的应用状态
-    pub fn new(index: IvfHnswDisk, db: IMDB, opts: ServerCommand) -> Arc<Self> {
+    pub fn new(index: IvfHnswDisk, db: IMDB, conf_dir: ConfDir, opts: ServerCommand) -> Arc<Self> {
         Arc::new(AppState {
-            index: Arc::new(index),
+            index: RwLock::new(Arc::new(index)),
             db,
+            conf_dir,
+            index_write_lock: Mutex::new(()),
             orb: opts.orb,
             search: opts.search,
             token: opts.token,
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+use utoipa::{IntoParams, ToSchema};
+
+use super::error::Result;
+use super::state::AppState;
+use crate::faiss::FaissIndex;
+use crate::index::IndexManager;
+
+fn default_sample() -> usize {
+    100
+}
+
+/// 索引统计信息查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct IndexStatsQuery {
+    /// 采样多少个倒排列表上报大小，均匀分布在整个倒排列表范围内，避免大索引一次性返回全部列表
+    #[serde(default = "default_sample")]
+    pub sample: usize,
+}
+
+/// 一个倒排列表的采样大小
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InvertedListSample {
+    /// 倒排列表序号
+    pub list_no: usize,
+    /// 该倒排列表中的向量数量
+    pub size: usize,
+}
+
+/// 索引健康状况
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IndexStatsResponse {
+    /// 已添加的特征点总数
+    pub ntotal: i64,
+    /// 倒排列表数量
+    pub nlist: usize,
+    /// 不平衡度，1 表示完全平衡，越大表示越不平衡
+    pub imbalance_factor: f64,
+    /// 索引是否已经训练
+    pub is_trained: bool,
+    /// 单条向量编码的字节数
+    pub code_size: i32,
+    /// 按 `sample` 均匀采样的倒排列表大小
+    pub list_samples: Vec<InvertedListSample>,
+}
+
+/// 查询索引健康状况
+///
+/// 以只读 mmap 模式打开当前索引读取统计信息，不会影响正在提供搜索服务的索引
+#[utoipa::path(
+    get,
+    path = "/index/stats",
+    params(IndexStatsQuery),
+    responses(
+        (status = 200, body = IndexStatsResponse),
+    )
+)]
+pub async fn index_stats_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IndexStatsQuery>,
+) -> Result<Json<IndexStatsResponse>> {
+    let conf_dir = state.conf_dir.clone();
+    let response = spawn_blocking(move || {
+        let manager = IndexManager::new(conf_dir);
+        let index = manager.get_aggregate_index(true, false);
+
+        let nlist = index.nlist();
+        let sample = query.sample.max(1).min(nlist);
+        let list_samples = (0..sample)
+            .map(|i| i * nlist / sample)
+            .map(|list_no| InvertedListSample { list_no, size: index.list_size(list_no) })
+            .collect();
+
+        IndexStatsResponse {
+            ntotal: index.ntotal(),
+            nlist,
+            imbalance_factor: index.imbalance_factor(),
+            is_trained: index.is_trained(),
+            code_size: index.code_size(),
+            list_samples,
+        }
+    })
+    .await?;
+
+    Ok(Json(response))
+}
+
+/// 合并索引请求参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MergeIndexRequest {
+    /// 需要合并进当前索引的另一个 Faiss 索引文件路径
+    pub path: String,
+}
+
+/// 合并索引响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MergeIndexResponse {
+    /// 合并完成后，索引中的特征点总数
+    pub ntotal: i64,
+}
+
+/// 将一个索引文件合并进当前主索引
+///
+/// 合并期间持有写锁，避免并发的合并请求同时修改同一份主索引文件；
+/// 合并完成后通过临时文件重命名的方式原子写回，不影响合并过程中仍在进行的搜索
+#[utoipa::path(
+    post,
+    path = "/index/merge",
+    request_body = MergeIndexRequest,
+    responses(
+        (status = 200, body = MergeIndexResponse),
+    )
+)]
+pub async fn merge_index_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<MergeIndexRequest>,
+) -> Result<Json<MergeIndexResponse>> {
+    let _guard = state.index_write_lock.lock().await;
+
+    let conf_dir = state.conf_dir.clone();
+    let response = spawn_blocking(move || -> anyhow::Result<MergeIndexResponse> {
+        let index_file = conf_dir.index();
+        let mut main = FaissIndex::from_file(&index_file, false)?;
+        let other = FaissIndex::from_file(&request.path, false)?;
+
+        info!("正在合并索引 {} 到主索引……", request.path);
+        main.merge_from(&other, 0)?;
+        main.write_file(&index_file)?;
+
+        Ok(MergeIndexResponse { ntotal: main.ntotal() })
+    })
+    .await??;
+
+    Ok(Json(response))
+}
+
+/// 重建索引响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReloadIndexResponse {
+    /// 重建后索引骨架的倒排列表数量
+    pub nlist: usize,
+}
+
+/// 热重载主索引
+///
+/// 持有 `index_write_lock` 期间按当前 `--no-mmap`/`--ondisk` 配置重新从磁盘构建一份索引骨架，
+/// 构建完成后才整体替换 `state.index` 持有的 `Arc`：替换本身只是一次指针交换，
+/// 正在进行中的 `/search` 请求手上已经克隆了旧 `Arc`，会用旧索引查询完毕不受影响，
+/// 只有替换之后新发起的搜索才会看到 `/add` 新写入的图片
+#[utoipa::path(
+    post,
+    path = "/index/reload",
+    responses(
+        (status = 200, body = ReloadIndexResponse),
+    )
+)]
+pub async fn reload_index_handler(State(state): State<Arc<AppState>>) -> Result<Json<ReloadIndexResponse>> {
+    let _guard = state.index_write_lock.lock().await;
+
+    let mmap = !state.search.no_mmap;
+    let ondisk = state.search.ondisk;
+    let state_for_blocking = state.clone();
+    let index = spawn_blocking(move || state_for_blocking.db.get_index(mmap, ondisk)).await??;
+
+    let nlist = index.invlists.nlist();
+    *state.index.write().await = Arc::new(index);
+
+    info!("主索引已热重载");
+
+    Ok(Json(ReloadIndexResponse { nlist }))
+}
@@ -0,0 +1,282 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use tokio::time::{sleep, Duration};
+use utoipa::ToSchema;
+
+use super::error::Result;
+use super::state::AppState;
+use crate::cli::{AddCommand, BuildCommand, SubCommandExtend};
+use crate::config::{ConfDir, Opts, SubCommand};
+use crate::db::TaskRecord;
+use crate::metrics::set_task_queue_depth;
+use crate::utils::ImageHash;
+
+fn default_suffix() -> String {
+    "jpg,png,webp".to_string()
+}
+
+fn default_batch_size() -> usize {
+    100000
+}
+
+/// 入队任务的请求参数，`kind` 字段决定具体执行哪种操作
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TaskRequest {
+    /// 扫描一个目录（或 tar/zip 归档）批量导入图片，复用 `imsearch add` 命令的完整流水线
+    AddDirectory {
+        path: String,
+        /// 扫描的文件后缀名，多个后缀用逗号分隔
+        #[serde(default = "default_suffix")]
+        suffix: String,
+        /// 跳过已经完整入库的来源路径，用于从上次中断的地方继续导入
+        #[serde(default)]
+        resume: bool,
+        /// 清空之前记录的入库进度，忽略 `resume` 重新扫描所有文件
+        #[serde(default)]
+        force_rescan: bool,
+    },
+    /// 导入单张图片
+    AddImage { path: String },
+    /// 对尚未索引的特征点构建索引
+    BuildIndex {
+        /// 构建索引时，多少张图片为一个批次
+        #[serde(default = "default_batch_size")]
+        batch_size: usize,
+    },
+    /// 压缩数据库与倒排列表，彻底清理已删除的特征向量
+    Merge,
+    /// 清除索引缓存
+    ClearCache {
+        /// 清理所有缓存，由于不需要筛选，速度更快
+        #[serde(default)]
+        all: bool,
+    },
+}
+
+impl TaskRequest {
+    fn kind_name(&self) -> &'static str {
+        match self {
+            Self::AddDirectory { .. } => "add-directory",
+            Self::AddImage { .. } => "add-image",
+            Self::BuildIndex { .. } => "build-index",
+            Self::Merge => "merge",
+            Self::ClearCache { .. } => "clear-cache",
+        }
+    }
+}
+
+/// 任务状态响应
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TaskResponse {
+    pub id: i64,
+    pub kind: String,
+    /// enqueued / processing / succeeded / failed
+    pub status: String,
+    pub progress_done: i64,
+    pub progress_total: Option<i64>,
+    pub error: Option<String>,
+}
+
+impl From<TaskRecord> for TaskResponse {
+    fn from(record: TaskRecord) -> Self {
+        Self {
+            id: record.id,
+            kind: record.kind,
+            status: record.status,
+            progress_done: record.progress_done,
+            progress_total: record.progress_total,
+            error: record.error,
+        }
+    }
+}
+
+/// 提交一个索引任务
+///
+/// 任务立即入队并返回任务 ID，具体执行交给后台的 [`run_task_worker`]；
+/// 队列持久化在数据库中，服务重启后仍处于 `enqueued` 状态的任务会自动继续执行；重启时正在
+/// 执行的任务保持 `processing` 状态，不会被重新取出
+#[utoipa::path(
+    post,
+    path = "/tasks",
+    request_body = TaskRequest,
+    responses(
+        (status = 200, body = TaskResponse),
+    )
+)]
+pub async fn enqueue_task_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<TaskRequest>,
+) -> Result<Json<TaskResponse>> {
+    let kind = request.kind_name();
+    let payload = serde_json::to_string(&request)?;
+    let id = state.db.enqueue_task(kind, &payload).await?;
+    set_task_queue_depth(state.db.count_enqueued_tasks().await?);
+
+    Ok(Json(TaskResponse {
+        id,
+        kind: kind.to_string(),
+        status: "enqueued".to_string(),
+        progress_done: 0,
+        progress_total: None,
+        error: None,
+    }))
+}
+
+/// 查询一个索引任务的当前状态
+#[utoipa::path(
+    get,
+    path = "/tasks/{id}",
+    responses(
+        (status = 200, body = TaskResponse),
+        (status = 404, description = "任务不存在"),
+    )
+)]
+pub async fn get_task_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<TaskResponse>> {
+    let task = state.db.get_task(id).await?.ok_or_else(|| anyhow::anyhow!("任务 {id} 不存在"))?;
+    Ok(Json(task.into()))
+}
+
+/// 后台任务队列 worker：轮询数据库中最早入队的任务并执行
+///
+/// 队列状态完全落在数据库里而不是内存中的 channel，所以这里不需要任何启动时的恢复逻辑：
+/// 重启后遗留的 `enqueued` 任务会在下一次轮询时被直接取出继续处理（重启前处于 `processing`
+/// 状态的任务不会被取出）
+pub async fn run_task_worker(state: Arc<AppState>) {
+    loop {
+        let task = match state.db.fetch_next_task().await {
+            Ok(Some(task)) => task,
+            Ok(None) => {
+                sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            Err(e) => {
+                error!("查询任务队列失败: {e}");
+                sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+
+        if let Err(e) = state.db.mark_task_processing(task.id).await {
+            error!("更新任务 {} 状态失败: {e}", task.id);
+            continue;
+        }
+        report_queue_depth(&state).await;
+
+        match execute_task(&state, &task).await {
+            Ok(()) => {
+                if let Err(e) = state.db.mark_task_succeeded(task.id).await {
+                    error!("更新任务 {} 状态失败: {e}", task.id);
+                }
+            }
+            Err(e) => {
+                warn!("任务 {} 执行失败: {e}", task.id);
+                if let Err(e) = state.db.mark_task_failed(task.id, &e.to_string()).await {
+                    error!("更新任务 {} 状态失败: {e}", task.id);
+                }
+            }
+        }
+        report_queue_depth(&state).await;
+    }
+}
+
+async fn report_queue_depth(state: &Arc<AppState>) {
+    match state.db.count_enqueued_tasks().await {
+        Ok(depth) => set_task_queue_depth(depth),
+        Err(e) => error!("统计任务队列深度失败: {e}"),
+    }
+}
+
+/// 构造一个只用到 `conf_dir` 字段的 [`Opts`]，用于直接复用现有 CLI 子命令的执行逻辑，
+/// 而不需要把它们的流水线代码再抄一遍
+fn opts_with_subcmd(conf_dir: ConfDir, subcmd: SubCommand) -> Opts {
+    Opts { subcmd, conf_dir }
+}
+
+async fn execute_task(state: &Arc<AppState>, task: &TaskRecord) -> anyhow::Result<()> {
+    let request: TaskRequest = serde_json::from_str(&task.payload)?;
+    match request {
+        TaskRequest::AddDirectory { path, suffix, resume, force_rescan } => {
+            let add = AddCommand {
+                orb: state.orb.clone(),
+                path: PathBuf::from(path),
+                suffix,
+                replace: vec![],
+                min_keypoints: 250,
+                hash: ImageHash::Blake3,
+                phash_distance: 8,
+                overwrite: false,
+                append: false,
+                minhash_size: 128,
+                minhash_bands: 16,
+                jaccard_threshold: 0.5,
+                resume,
+                force_rescan,
+            };
+            let opts = opts_with_subcmd(state.conf_dir.clone(), SubCommand::Add(add.clone()));
+
+            // AddDirectory 耗时较长且没有明确的总量（目录是流式扫描的），用已入库的图片
+            // 数量近似反映进度，每隔几秒上报一次，让 /tasks/{id} 不至于一直卡在入队时的默认值
+            let progress_state = state.clone();
+            let task_id = task.id;
+            let progress_task = tokio::spawn(async move {
+                loop {
+                    sleep(Duration::from_secs(3)).await;
+                    match progress_state.db.image_count().await {
+                        Ok(done) => {
+                            if let Err(e) =
+                                progress_state.db.update_task_progress(task_id, done, None).await
+                            {
+                                error!("更新任务 {task_id} 进度失败: {e}");
+                            }
+                        }
+                        Err(e) => error!("统计任务 {task_id} 进度失败: {e}"),
+                    }
+                }
+            });
+
+            let result = add.run(&opts).await;
+            progress_task.abort();
+            if result.is_ok() {
+                if let Ok(done) = state.db.image_count().await {
+                    let _ = state.db.update_task_progress(task_id, done, Some(done)).await;
+                }
+            }
+            result
+        }
+        TaskRequest::AddImage { path } => add_single_image(state, &path).await,
+        TaskRequest::BuildIndex { batch_size } => {
+            let build = BuildCommand { batch_size };
+            let opts = opts_with_subcmd(state.conf_dir.clone(), SubCommand::Build(build.clone()));
+            build.run(&opts).await
+        }
+        TaskRequest::Merge => state.db.compact().await,
+        TaskRequest::ClearCache { all } => state.db.clear_cache(all).await,
+    }
+}
+
+/// 导入单张图片：计算 hash、提取 ORB 特征点、写入数据库，与 `/search` 提取特征的方式一致
+async fn add_single_image(state: &Arc<AppState>, path: &str) -> anyhow::Result<()> {
+    use crate::orb::ORBDetector;
+
+    let hash = ImageHash::Blake3.hash_file(path)?;
+    let path = path.to_string();
+    let orb = state.orb.clone();
+    let path_for_detect = path.clone();
+    let (_, _, descriptors) = tokio::task::spawn_blocking(move || {
+        let mut orb = ORBDetector::create(orb);
+        orb.detect_file(&path_for_detect)
+    })
+    .await??;
+
+    state.db.add_image(&path, &hash, &descriptors).await?;
+    Ok(())
+}
@@ -53,3 +53,83 @@ pub struct SearchResponse {
     /// 图片的搜索结果，格式为 `(相似度, 图片路径)`
     pub result: Vec<(f32, String)>,
 }
+
+/// 添加请求参数
+#[derive(TryFromMultipart)]
+pub struct AddRequest {
+    pub file: Bytes,
+    /// 记录到数据库中的图片来源路径
+    pub path: String,
+    pub orb_nfeatures: Option<u32>,
+    pub orb_scale_factor: Option<f32>,
+}
+
+/// 添加表单（用于API文档）
+#[derive(Debug, ToSchema)]
+#[allow(unused)]
+pub struct AddForm {
+    /// 上传的图片文件
+    #[schema(format = Binary, content_media_type = "application/octet-stream")]
+    pub file: String,
+    /// 记录到数据库中的图片来源路径
+    pub path: String,
+    /// ORB特征提取数量
+    pub orb_nfeatures: Option<u32>,
+    /// ORB特征提取缩放因子
+    pub orb_scale_factor: Option<f32>,
+}
+
+/// 添加响应
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddResponse {
+    /// 新写入图片的数据库 ID；命中去重时为已存在图片的 ID
+    pub id: i64,
+    /// 是否因为 blake3 哈希命中已有图片而跳过了写入
+    pub duplicate: bool,
+}
+
+/// NDJSON 模式下批量搜索的一行查询
+#[derive(Debug, Deserialize)]
+pub struct BatchQueryLine {
+    /// 图片文件内容，Base64 编码
+    pub file: String,
+    pub orb_nfeatures: Option<u32>,
+    pub orb_scale_factor: Option<f32>,
+    pub nprobe: Option<usize>,
+}
+
+/// 批量搜索表单（用于API文档，对应 multipart 模式）
+#[derive(Debug, ToSchema)]
+#[allow(unused)]
+pub struct BatchSearchForm {
+    /// 上传的图片文件，可重复多次以提交多张图片
+    #[schema(format = Binary, content_media_type = "application/octet-stream")]
+    pub file: Vec<String>,
+    /// ORB特征提取数量，作用于批次中的每张图片
+    pub orb_nfeatures: Option<u32>,
+    /// ORB特征提取缩放因子，作用于批次中的每张图片
+    pub orb_scale_factor: Option<f32>,
+    /// 搜索扫描的倒排列表数量，作用于批次中的每张图片
+    pub nprobe: Option<usize>,
+}
+
+/// 批量搜索中单张图片的查询结果，以 NDJSON 形式逐条流式返回
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchSearchResult {
+    /// 该查询在批次中的序号，从 0 开始
+    pub index: usize,
+    /// 搜索耗时，单位为毫秒
+    pub time: u32,
+    /// 图片的搜索结果，格式为 `(相似度, 图片路径)`
+    pub result: Vec<(f32, String)>,
+    /// 该查询失败时的错误信息，成功时为 `None`
+    pub error: Option<String>,
+}
+
+/// 批量搜索中的一条内部查询，统一了 multipart 和 NDJSON 两种输入方式
+pub(super) struct BatchQuery {
+    pub file: Bytes,
+    pub orb_nfeatures: Option<u32>,
+    pub orb_scale_factor: Option<f32>,
+    pub nprobe: Option<usize>,
+}
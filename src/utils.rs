@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::BufReader;
 
 use anyhow::Result;
 use axum_typed_multipart::TryFromField;
@@ -11,17 +11,20 @@ use opencv::{imgcodecs, imgproc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::ahash::a_hash;
 use crate::dhash::d_hash;
 use crate::orb::Slam3ORB;
+use crate::phash::p_hash;
 
 pub fn detect_and_compute(
     orb: &mut Slam3ORB,
     image: &impl ToInputArray,
+    lapping_area: (i32, i32),
 ) -> opencv::Result<(Vec<KeyPoint>, Array2<u8>)> {
     let mask = Mat::default();
     let mut kps = Vector::<KeyPoint>::new();
     let mut des = Mat::default();
-    orb.detect_and_compute(image, &mask, &mut kps, &mut des)?;
+    orb.detect_and_compute(image, &mask, &mut kps, &mut des, lapping_area)?;
     let kps = kps.to_vec();
     let des = ArrayView2::from_shape((kps.len(), 32), des.data_bytes()?).unwrap();
     Ok((kps, des.to_owned()))
@@ -96,6 +99,9 @@ pub fn pb_style_speed() -> ProgressStyle {
         .progress_chars("#>-")
 }
 
+/// 目前只支持和 dhash/phash 一样定长（64/256 位）、可以直接用 Hamming 距离比较的算法；
+/// blockhash、双梯度哈希这类需要不同位长或不同距离度量的算法，会牵扯到像 SIFT 浮点描述符
+/// 那样的独立索引/距离路径（见 [`crate::features::FeatureExtractorKind`]），这里先不做
 #[derive(
     Debug, Clone, Copy, Eq, PartialEq, ValueEnum, ToSchema, TryFromField, Serialize, Deserialize,
 )]
@@ -106,23 +112,50 @@ pub enum ImageHash {
     /// 使用 dhash 哈希算法，长度 8 字节
     #[schema(rename = "dhash")]
     Dhash,
+    /// 使用 phash 哈希算法，长度 8 字节，对 gamma/对比度变化和局部裁剪更鲁棒
+    #[schema(rename = "phash")]
+    Phash,
+    /// 使用 ahash（均值哈希）算法，长度 8 字节，计算量最小但对整体亮度/对比度变化敏感
+    #[schema(rename = "ahash")]
+    Ahash,
 }
 
+/// 小于该大小的文件直接用单线程 reader 哈希，mmap + rayon 并行的线程调度开销反而更高
+const BLAKE3_RAYON_THRESHOLD: u64 = 128 * 1024;
+
 impl ImageHash {
     /// 对一个图片文件进行哈希，返回哈希值
     pub fn hash_file(&self, path: &str) -> Result<Vec<u8>> {
         match self {
             Self::Blake3 => {
-                let mut file = File::open(path)?;
-                let mut data = vec![];
-                file.read_to_end(&mut data)?;
-                Ok(blake3::hash(&data).as_bytes().to_vec())
+                let mut hasher = blake3::Hasher::new();
+                let len = std::fs::metadata(path)?.len();
+                // blake3 是 Merkle 树结构，按 1 KiB 分块后两两合并链值，不同子树互相独立，
+                // 因此可以用 mmap 配合 rayon 全局线程池并行计算，结果和单线程哈希逐位相同；
+                // 小文件直接走 reader 路径，避免 mmap 和线程调度带来的额外开销
+                if len >= BLAKE3_RAYON_THRESHOLD && hasher.update_mmap_rayon(path).is_ok() {
+                    // mmap 成功时 hasher 已经在上面这一步更新完毕
+                } else {
+                    let file = File::open(path)?;
+                    hasher.update_reader(BufReader::new(file))?;
+                }
+                Ok(hasher.finalize().as_bytes().to_vec())
             }
             Self::Dhash => {
                 let img = imgcodecs::imread(path, imgcodecs::IMREAD_GRAYSCALE)?;
                 let hash = d_hash(&img)?;
                 Ok(hash.to_vec())
             }
+            Self::Phash => {
+                let img = imgcodecs::imread(path, imgcodecs::IMREAD_GRAYSCALE)?;
+                let hash = p_hash(&img)?;
+                Ok(hash.to_vec())
+            }
+            Self::Ahash => {
+                let img = imgcodecs::imread(path, imgcodecs::IMREAD_GRAYSCALE)?;
+                let hash = a_hash(&img)?;
+                Ok(hash.to_vec())
+            }
         }
     }
 
@@ -136,6 +169,18 @@ impl ImageHash {
                 let hash = d_hash(&img)?;
                 Ok((Some(img), hash.to_vec()))
             }
+            Self::Phash => {
+                let mat = Mat::from_slice(data)?;
+                let img = imgcodecs::imdecode(&mat, imgcodecs::IMREAD_GRAYSCALE)?;
+                let hash = p_hash(&img)?;
+                Ok((Some(img), hash.to_vec()))
+            }
+            Self::Ahash => {
+                let mat = Mat::from_slice(data)?;
+                let img = imgcodecs::imdecode(&mat, imgcodecs::IMREAD_GRAYSCALE)?;
+                let hash = a_hash(&img)?;
+                Ok((Some(img), hash.to_vec()))
+            }
         }
     }
 }
@@ -145,3 +190,66 @@ impl Default for ImageHash {
         Self::Blake3
     }
 }
+
+impl ImageHash {
+    /// 算法名称，与 `#[schema(rename = ...)]` 保持一致，用于持久化到 `hash_config` 表
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Dhash => "dhash",
+            Self::Phash => "phash",
+            Self::Ahash => "ahash",
+        }
+    }
+}
+
+impl std::str::FromStr for ImageHash {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "blake3" => Ok(Self::Blake3),
+            "dhash" => Ok(Self::Dhash),
+            "phash" => Ok(Self::Phash),
+            "ahash" => Ok(Self::Ahash),
+            _ => Err(anyhow::anyhow!("未知的哈希算法：{s}")),
+        }
+    }
+}
+
+/// 用户友好的相似度档位，免去用户直接填写原始 Hamming 距离阈值
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, ValueEnum, ToSchema, Serialize, Deserialize, Default,
+)]
+pub enum SimilarityLevel {
+    /// 几乎完全相同才判定为重复
+    Strict,
+    #[default]
+    Normal,
+    /// 允许水印、重新编码、较大幅度裁剪等变化
+    Loose,
+}
+
+impl ImageHash {
+    /// 哈希结果的位长，用于换算相似度档位对应的 Hamming 距离阈值
+    pub fn bit_len(&self) -> u32 {
+        match self {
+            Self::Blake3 => 256,
+            Self::Dhash | Self::Phash | Self::Ahash => 64,
+        }
+    }
+
+    /// 按位长与相似度档位换算出具体的 Hamming 距离阈值（经验取值，按位长等比例放大）
+    pub fn distance_for(&self, level: SimilarityLevel) -> u32 {
+        let (strict, normal, loose) = match self.bit_len() {
+            ..=64 => (4, 8, 16),
+            ..=256 => (16, 32, 64),
+            _ => (64, 128, 256),
+        };
+        match level {
+            SimilarityLevel::Strict => strict,
+            SimilarityLevel::Normal => normal,
+            SimilarityLevel::Loose => loose,
+        }
+    }
+}